@@ -32,6 +32,7 @@ fn main() {
     let config = SpectralGateConfig {
         noise_threshold_db: threshold_db,   // Attenuate signals 6 dB below noise floor
         smoothing_window: 3,                 // Smooth across 3 frequency bins
+        ..Default::default()
     };
     let gate = SpectralGate::new(noise_profile, config);
     println!("  Gate configured with {} dB threshold\n", threshold_db);
@@ -5,11 +5,12 @@
 //! - Spectral gating for noise reduction using recorded noise profiles  
 //! - Background noise spectrum estimation
 
-use fundsp::hacker::*;
 use super::{Spectrum};
 use audio_utils as audio;
 use super::util::{rms, mean_std_deviation};
 use crate::spectral_gating::{SpectralGate, SpectralGateConfig};
+use crate::filters::{BandpassConfig, BandpassFilter};
+use crate::processor::AudioProcessor;
 
 /// Default vocal frequency range for bandpass filtering
 pub const DEFAULT_VOCAL_LOW_HZ: f32 = 80.0;
@@ -33,25 +34,15 @@ pub const DEFAULT_VOCAL_HIGH_HZ: f32 = 1200.0;
 /// # Returns
 /// Filtered audio samples with the same length as input
 pub fn bandpass_vocal_range(samples: &[f32], sample_rate: f32, low_hz: f32, high_hz: f32) -> Vec<f32> {
-    let mut filtered = Vec::with_capacity(samples.len());
-    let center = (low_hz + high_hz) * 0.5;
-    let bandwidth = high_hz - low_hz;
-    let q = if bandwidth > 0.0 { center / bandwidth } else { 1.0 };
-    
-    let mut filter = bandpass_hz(center, q);
-    filter.set_sample_rate(sample_rate as f64);
-    
-    for &sample in samples {
-        filtered.push(filter.filter_mono(sample));
-    }
-    
-    filtered
+    let mut filter = BandpassFilter::new(sample_rate, BandpassConfig { low_hz, high_hz, ..Default::default() });
+    filter.process(samples)
 }
 
 /// Cleans audio signal for improved pitch detection using spectral gating or bandpass filtering
 /// 
 /// If a noise spectrum is provided, it uses spectral gating to attenuate frequency bins that are below the noise floor.
 /// Otherwise, it falls back to bandpass filtering for the vocal range.
+/// Use a [`CleaningPipeline`](crate::CleaningPipeline) to combine several stages in a chosen order.
 /// 
 /// # Arguments
 /// * `samples` - Input audio samples to clean
@@ -128,6 +119,7 @@ fn apply_spectral_gating(
     let config = SpectralGateConfig {
         noise_threshold_db: threshold_db,
         smoothing_window: 1,
+        ..Default::default()
     };
     
     let gate = SpectralGate::new(noise_spec, config);
@@ -0,0 +1,305 @@
+//! Constant-Q Transform
+//!
+//! A linear-frequency [`Spectrogram`](crate::Spectrogram) spends most of its bins above
+//! the singing range and resolves low notes poorly: at 44.1 kHz with a 1024-point
+//! FFT, one bin spans 43 Hz, which is more than a whole tone at A3. The constant-Q
+//! transform instead spaces its bins geometrically, a fixed number per octave, with a
+//! window length inversely proportional to frequency. Every bin then has the same
+//! musical resolution, and with a reference tuning the bins line up with semitones.
+//!
+//! This module provides:
+//! - Conversions between Hz and (fractional) MIDI note numbers for any tuning,
+//!   re-exported from [`audio_utils::pitch`]
+//! - [`ConstantQTransform`], which precomputes sparse spectral kernels once and then
+//!   analyses frames with a single FFT each
+//! - [`ConstantQSpectrogram`], which keeps the frequency and time axes of the result
+
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use crate::types::Spectrum;
+
+pub use audio_utils::pitch::{hz_to_midi, midi_to_hz, A4_HZ, A4_MIDI};
+
+/// Kernel entries smaller than this fraction of the kernel's largest entry are
+/// dropped, which keeps the kernels sparse
+const KERNEL_SPARSITY: f32 = 1e-3;
+
+/// Configuration for the constant-Q transform
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConstantQConfig {
+    /// MIDI note of the lowest bin. Default: 36.0 (C2, 65.4 Hz)
+    pub min_midi: f32,
+    /// Number of octaves analysed. Bins that would lie above the Nyquist frequency are
+    /// dropped. Default: 6
+    pub octaves: usize,
+    /// Bins per octave; 12 gives one bin per semitone, 36 one per third of a
+    /// semitone. Default: 36
+    pub bins_per_octave: usize,
+    /// Frequency of A4 the bins are aligned to, in Hz. Default: 440.0
+    pub reference_hz: f32,
+    /// Samples between successive frames. Default: 256
+    pub hop_size: usize,
+}
+
+impl Default for ConstantQConfig {
+    fn default() -> Self {
+        Self {
+            min_midi: 36.0,
+            octaves: 6,
+            bins_per_octave: 36,
+            reference_hz: A4_HZ,
+            hop_size: 256,
+        }
+    }
+}
+
+/// Precomputed constant-Q analysis for one sample rate and configuration
+pub struct ConstantQTransform {
+    config: ConstantQConfig,
+    sample_rate: f32,
+    fft_size: usize,
+    /// Sparse conjugated kernel spectra: (FFT bin, weight) per CQT bin
+    kernels: Vec<Vec<(usize, Complex<f32>)>>,
+}
+
+impl ConstantQTransform {
+    /// Build the kernels for the given sample rate
+    pub fn new(sample_rate: f32, config: ConstantQConfig) -> Self {
+        let bins_per_octave = config.bins_per_octave.max(1);
+        let config = ConstantQConfig { bins_per_octave, ..config };
+        let nyquist = sample_rate / 2.0;
+        let n_bins = (0..config.octaves * bins_per_octave)
+            .take_while(|&k| bin_hz(&config, k) < nyquist)
+            .count();
+
+        // Quality factor giving adjacent bins that just touch
+        let q = 1.0 / (2f32.powf(1.0 / bins_per_octave as f32) - 1.0);
+        let longest = (q * sample_rate / bin_hz(&config, 0)).ceil() as usize;
+        let fft_size = longest.max(1).next_power_of_two();
+
+        let kernels = (0..n_bins)
+            .map(|k| {
+                let frequency = bin_hz(&config, k);
+                let length = ((q * sample_rate / frequency).ceil() as usize).clamp(1, fft_size);
+                let window: Vec<f32> = (0..length)
+                    .map(|n| 0.5 - 0.5 * (2.0 * PI * (n as f32 + 0.5) / length as f32).cos())
+                    .collect();
+                // Scaled so a sinusoid of amplitude A on the bin centre reads A
+                let scale = 2.0 / window.iter().sum::<f32>();
+                let mut kernel = vec![0.0f32; fft_size];
+                let mut kernel_im = vec![0.0f32; fft_size];
+                let start = (fft_size - length) / 2;
+                for (n, w) in window.iter().enumerate() {
+                    let phase = 2.0 * PI * frequency * (n as f32 - length as f32 / 2.0) / sample_rate;
+                    kernel[start + n] = scale * w * phase.cos();
+                    kernel_im[start + n] = scale * w * phase.sin();
+                }
+                sparse_kernel(&kernel, &kernel_im, fft_size)
+            })
+            .collect();
+
+        Self { config, sample_rate, fft_size, kernels }
+    }
+
+    /// Build with the default configuration
+    pub fn with_defaults(sample_rate: f32) -> Self {
+        Self::new(sample_rate, ConstantQConfig::default())
+    }
+
+    /// The configuration in use
+    pub fn config(&self) -> &ConstantQConfig {
+        &self.config
+    }
+
+    /// Number of frequency bins
+    pub fn n_bins(&self) -> usize {
+        self.kernels.len()
+    }
+
+    /// Length of the analysis frame (the longest kernel, rounded up to a power of two)
+    pub fn frame_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// Centre frequency of bin `k` in Hz
+    pub fn bin_frequency(&self, k: usize) -> f32 {
+        bin_hz(&self.config, k)
+    }
+
+    /// Fractional MIDI note of bin `k`
+    pub fn bin_midi(&self, k: usize) -> f32 {
+        bin_midi(&self.config, k)
+    }
+
+    /// Constant-Q magnitudes of one frame of `frame_size()` samples, centred on the
+    /// middle of the frame
+    pub fn analyse_frame(&self, frame: &[f32]) -> Vec<f32> {
+        let mut padded = vec![0.0f32; self.fft_size];
+        let n = Ord::min(frame.len(), self.fft_size);
+        padded[..n].copy_from_slice(&frame[..n]);
+        let spectrum = Spectrum::from_waveform(&padded);
+        let scale = 1.0 / self.fft_size as f32;
+        self.kernels
+            .iter()
+            .map(|kernel| {
+                let sum: Complex<f32> = kernel.iter().map(|&(j, weight)| spectrum.complex[j] * weight).sum();
+                sum.norm() * scale
+            })
+            .collect()
+    }
+
+    /// Constant-Q spectrogram of a whole signal. Frame `t` is centred on sample
+    /// `t * hop_size`; the signal is zero-padded at both ends.
+    pub fn spectrogram(&self, signal: &[f32]) -> ConstantQSpectrogram {
+        let hop = self.config.hop_size.max(1);
+        let half = self.fft_size / 2;
+        let n_frames = if signal.is_empty() { 0 } else { signal.len() / hop + 1 };
+        let mut frame = vec![0.0f32; self.fft_size];
+        let frames = (0..n_frames)
+            .map(|t| {
+                let centre = t * hop;
+                frame.iter_mut().enumerate().for_each(|(i, x)| {
+                    *x = (centre + i).checked_sub(half).and_then(|n| signal.get(n)).copied().unwrap_or(0.0);
+                });
+                self.analyse_frame(&frame)
+            })
+            .collect();
+        ConstantQSpectrogram { frames, sample_rate: self.sample_rate, config: self.config }
+    }
+}
+
+/// Constant-Q magnitude frames together with their frequency and time axes
+#[derive(Debug, Clone)]
+pub struct ConstantQSpectrogram {
+    /// One magnitude vector per frame, lowest bin first
+    pub frames: Vec<Vec<f32>>,
+    pub sample_rate: f32,
+    pub config: ConstantQConfig,
+}
+
+impl ConstantQSpectrogram {
+    /// Compute the constant-Q spectrogram of a signal
+    pub fn from_waveform(signal: &[f32], sample_rate: f32, config: ConstantQConfig) -> Self {
+        ConstantQTransform::new(sample_rate, config).spectrogram(signal)
+    }
+
+    /// Number of frames
+    pub fn n_time_steps(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Number of frequency bins in each frame
+    pub fn n_bins(&self) -> usize {
+        self.frames.first().map_or(0, |frame| frame.len())
+    }
+
+    /// Centre frequency of bin `k` in Hz
+    pub fn bin_frequency(&self, k: usize) -> f32 {
+        bin_hz(&self.config, k)
+    }
+
+    /// Fractional MIDI note of bin `k`
+    pub fn bin_midi(&self, k: usize) -> f32 {
+        bin_midi(&self.config, k)
+    }
+
+    /// Time of the centre of frame `t` in seconds
+    pub fn frame_time(&self, t: usize) -> f32 {
+        (t * self.config.hop_size) as f32 / self.sample_rate
+    }
+}
+
+fn bin_midi(config: &ConstantQConfig, k: usize) -> f32 {
+    config.min_midi + 12.0 * k as f32 / config.bins_per_octave.max(1) as f32
+}
+
+fn bin_hz(config: &ConstantQConfig, k: usize) -> f32 {
+    midi_to_hz(bin_midi(config, k), config.reference_hz)
+}
+
+/// FFT of a complex kernel, conjugated and thresholded to its significant entries
+fn sparse_kernel(re: &[f32], im: &[f32], fft_size: usize) -> Vec<(usize, Complex<f32>)> {
+    // The FFT of a complex signal from two real FFTs: F(re) + i F(im)
+    let re_spectrum = Spectrum::from_waveform(re);
+    let im_spectrum = Spectrum::from_waveform(im);
+    let spectrum: Vec<Complex<f32>> = (0..fft_size)
+        .map(|j| re_spectrum.complex[j] + Complex::<f32>::i() * im_spectrum.complex[j])
+        .collect();
+    let peak = spectrum.iter().map(|c| c.norm()).fold(0.0f32, f32::max);
+    spectrum
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.norm() > KERNEL_SPARSITY * peak)
+        .map(|(j, c)| (j, c.conj()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, sample_rate: f32, len: usize) -> Vec<f32> {
+        (0..len).map(|i| (2.0 * PI * frequency * i as f32 / sample_rate).sin()).collect()
+    }
+
+    #[test]
+    fn test_bins_align_to_semitones() {
+        let config = ConstantQConfig { bins_per_octave: 12, ..Default::default() };
+        let cqt = ConstantQTransform::new(16000.0, config);
+        assert_eq!(cqt.bin_midi(0), 36.0);
+        assert_eq!(cqt.bin_midi(33), 69.0);
+        assert!((cqt.bin_frequency(33) - 440.0).abs() < 1e-2);
+        assert!((cqt.bin_frequency(12) / cqt.bin_frequency(0) - 2.0).abs() < 1e-4);
+
+        let tuned = ConstantQTransform::new(16000.0, ConstantQConfig { reference_hz: 415.0, ..config });
+        assert!((tuned.bin_frequency(33) - 415.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_bins_above_nyquist_are_dropped() {
+        let config = ConstantQConfig { bins_per_octave: 12, octaves: 8, ..Default::default() };
+        let cqt = ConstantQTransform::new(8000.0, config);
+        assert!(cqt.n_bins() < 96);
+        assert!(cqt.bin_frequency(cqt.n_bins() - 1) < 4000.0);
+        assert!(cqt.bin_frequency(cqt.n_bins()) >= 4000.0);
+    }
+
+    #[test]
+    fn test_sine_peaks_at_its_note() {
+        let sample_rate = 16000.0;
+        let cqt = ConstantQTransform::with_defaults(sample_rate);
+        for (frequency, midi) in [(110.0, 45.0), (440.0, 69.0), (466.1638, 70.0), (1046.502, 84.0)] {
+            let frame = cqt.analyse_frame(&sine(frequency, sample_rate, cqt.frame_size()));
+            let peak = (0..frame.len()).max_by(|&a, &b| frame[a].total_cmp(&frame[b])).unwrap();
+            assert!((cqt.bin_midi(peak) - midi).abs() < 1e-3, "{} Hz peaked at {}", frequency, cqt.bin_midi(peak));
+            // Unit-amplitude sine reads close to 1 on its bin
+            assert!((frame[peak] - 1.0).abs() < 0.05, "{} Hz magnitude {}", frequency, frame[peak]);
+        }
+    }
+
+    #[test]
+    fn test_semitone_neighbours_are_resolved() {
+        // A 1024-point FFT at 16 kHz has 15.6 Hz bins, more than two semitones at A2.
+        // The constant-Q bins a semitone away from the tone stay nearly empty.
+        let sample_rate = 16000.0;
+        let config = ConstantQConfig { bins_per_octave: 24, octaves: 2, ..Default::default() };
+        let cqt = ConstantQTransform::new(sample_rate, config);
+        let frame = cqt.analyse_frame(&sine(110.0, sample_rate, cqt.frame_size()));
+        // Bins for G#2 (MIDI 44), A2 (45) and B-flat 2 (46)
+        let (below, tone, above) = (frame[16], frame[18], frame[20]);
+        assert!(below < 0.05 * tone && above < 0.05 * tone, "{} {} {}", below, tone, above);
+    }
+
+    #[test]
+    fn test_frame_axis() {
+        let sample_rate = 8000.0;
+        let config = ConstantQConfig { hop_size: 100, min_midi: 48.0, octaves: 3, ..Default::default() };
+        let spectrogram = ConstantQSpectrogram::from_waveform(&sine(220.0, sample_rate, 1000), sample_rate, config);
+        assert_eq!(spectrogram.n_time_steps(), 11);
+        assert_eq!(spectrogram.n_bins(), 108);
+        assert!((spectrogram.frame_time(4) - 0.05).abs() < 1e-6);
+        assert!(ConstantQSpectrogram::from_waveform(&[], sample_rate, config).frames.is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+//! Level Control
+//!
+//! This module provides stages that change the level of a signal rather than its
+//! spectrum:
+//! - Peak normalization
+//! - Automatic gain control (AGC) with compression and a noise gate, for quiet or
+//!   distant singers
+
+use serde::{Deserialize, Serialize};
+use crate::processor::AudioProcessor;
+
+/// Lowest linear gain the AGC reports (-100 dB). A closed gate heads towards zero
+/// gain, which would otherwise read as an unbounded number of decibels.
+const MIN_REPORTED_GAIN: f32 = 1e-5;
+
+/// Configuration for peak normalization
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizeConfig {
+    /// Target absolute peak level (linear, 0.0-1.0). Default: 0.9
+    pub target_peak: f32,
+}
+
+impl Default for NormalizeConfig {
+    fn default() -> Self {
+        Self { target_peak: 0.9 }
+    }
+}
+
+/// Peak normalizer
+///
+/// In batch mode the whole signal is scaled so that its peak hits `target_peak`.
+/// In streaming mode each chunk is scaled by the gain implied by the largest peak
+/// seen so far, so the gain only ever decreases and never pumps.
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    config: NormalizeConfig,
+    running_peak: f32,
+}
+
+impl Normalizer {
+    /// Create a peak normalizer
+    pub fn new(config: NormalizeConfig) -> Self {
+        Self { config, running_peak: 0.0 }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &NormalizeConfig {
+        &self.config
+    }
+}
+
+impl AudioProcessor for Normalizer {
+    fn name(&self) -> &'static str {
+        "normalize"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        let block_peak = samples
+            .iter()
+            .filter(|x| x.is_finite())
+            .fold(0.0f32, |peak, x| peak.max(x.abs()));
+        self.running_peak = self.running_peak.max(block_peak);
+        if self.running_peak <= 0.0 {
+            return;
+        }
+        let gain = self.config.target_peak / self.running_peak;
+        for sample in samples.iter_mut() {
+            *sample *= gain;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.running_peak = 0.0;
+    }
+}
+
+/// Configuration for automatic gain control
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgcConfig {
+    /// Target RMS level (linear). Default: 0.1
+    pub target_rms: f32,
+    /// Maximum gain applied to quiet signals, in dB. Must not be negative; the AGC
+    /// treats negative or NaN values as 0 dB. Default: 30.0 dB
+    pub max_gain_db: f32,
+    /// Time for the gain to come down when the level rises, in milliseconds.
+    /// Also the time constant of the level detector. Default: 10.0 ms
+    pub attack_ms: f32,
+    /// Time for the gain to come back up when the level falls, in milliseconds.
+    /// Default: 300.0 ms
+    pub release_ms: f32,
+    /// Compression ratio applied above `target_rms`: a level 12 dB over target comes
+    /// out 12/ratio dB over target. Quiet signals are always boosted all the way to
+    /// the target (up to `max_gain_db`). Default: 4.0
+    pub compression_ratio: f32,
+    /// RMS level (linear) below which the input is treated as noise and gated to
+    /// silence instead of boosted. Default: 0.001 (-60 dBFS)
+    pub noise_floor_rms: f32,
+}
+
+impl Default for AgcConfig {
+    fn default() -> Self {
+        Self {
+            target_rms: 0.1,
+            max_gain_db: 30.0,
+            attack_ms: 10.0,
+            release_ms: 300.0,
+            compression_ratio: 4.0,
+            noise_floor_rms: 0.001,
+        }
+    }
+}
+
+/// Automatic gain control with compression and a noise gate
+///
+/// Tracks the mean-square level of the input with a one-pole detector and computes
+/// the gain that brings it towards `target_rms`: quiet input is boosted by up to
+/// `max_gain_db`, and loud input is compressed by `compression_ratio`. That gain
+/// follows its target in dB, using the attack time when falling and the release time
+/// when rising. Input below `noise_floor_rms` closes a gate that fades to silence over
+/// the release time and reopens within the attack time. The applied gain can be read
+/// back with [`gain_db`](AudioProcessor::gain_db) for level meters; it bottoms out at
+/// -100 dB while the gate is closed.
+#[derive(Debug, Clone)]
+pub struct AutomaticGainControl {
+    config: AgcConfig,
+    attack: f32,
+    release: f32,
+    mean_square: f32,
+    level_gain_db: f32,
+    gate: f32,
+}
+
+/// One-pole smoothing coefficient for a time constant in milliseconds
+fn smoothing_coefficient(time_ms: f32, sample_rate: f32) -> f32 {
+    let time_constant_samples = time_ms * 0.001 * sample_rate;
+    1.0 - (-1.0 / time_constant_samples.max(1.0)).exp()
+}
+
+impl AutomaticGainControl {
+    /// Create an AGC for the given sample rate
+    pub fn new(sample_rate: f32, config: AgcConfig) -> Self {
+        Self {
+            config,
+            attack: smoothing_coefficient(config.attack_ms, sample_rate),
+            release: smoothing_coefficient(config.release_ms, sample_rate),
+            mean_square: config.target_rms * config.target_rms,
+            level_gain_db: 0.0,
+            gate: 1.0,
+        }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &AgcConfig {
+        &self.config
+    }
+
+    /// Linear gain applied to the most recent sample
+    pub fn gain(&self) -> f32 {
+        self.gate * 10.0_f32.powf(self.level_gain_db / 20.0)
+    }
+
+    /// Whether the input is currently below the noise floor
+    pub fn is_gated(&self) -> bool {
+        self.mean_square.sqrt() < self.config.noise_floor_rms
+    }
+
+    /// Leveling gain (dB) the AGC is heading towards for the current input level
+    fn target_gain_db(&self) -> f32 {
+        let level_db = 10.0 * self.mean_square.max(1e-20).log10();
+        let excess_db = level_db - 20.0 * self.config.target_rms.log10();
+        if excess_db <= 0.0 {
+            // `max` also maps NaN to 0 dB
+            (-excess_db).min(self.config.max_gain_db.max(0.0))
+        } else {
+            // Above target: keep 1/compression_ratio of the excess
+            -excess_db * (1.0 - 1.0 / self.config.compression_ratio.max(1.0))
+        }
+    }
+}
+
+impl AudioProcessor for AutomaticGainControl {
+    fn name(&self) -> &'static str {
+        "agc"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            if sample.is_finite() {
+                self.mean_square += self.attack * (*sample * *sample - self.mean_square);
+            }
+
+            let target_db = self.target_gain_db();
+            let smoothing = if target_db < self.level_gain_db { self.attack } else { self.release };
+            self.level_gain_db += smoothing * (target_db - self.level_gain_db);
+
+            if self.is_gated() {
+                self.gate -= self.release * self.gate;
+            } else {
+                self.gate += self.attack * (1.0 - self.gate);
+            }
+
+            *sample *= self.gain();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.mean_square = self.config.target_rms * self.config.target_rms;
+        self.level_gain_db = 0.0;
+        self.gate = 1.0;
+    }
+
+    fn gain_db(&self) -> Option<f32> {
+        Some(20.0 * self.gain().max(MIN_REPORTED_GAIN).log10())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rms(signal: &[f32]) -> f32 {
+        (signal.iter().map(|x| x * x).sum::<f32>() / signal.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_normalizer_batch_hits_target_peak() {
+        let mut normalizer = Normalizer::new(NormalizeConfig { target_peak: 0.5 });
+        let output = normalizer.process(&[0.1, -0.2, 0.05]);
+        assert!((output[1] + 0.5).abs() < 1e-6);
+        assert!((output[0] - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_normalizer_silence_unchanged() {
+        let mut normalizer = Normalizer::new(NormalizeConfig::default());
+        assert_eq!(normalizer.process(&[0.0; 4]), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_agc_boosts_quiet_signal() {
+        let sample_rate = 8000.0;
+        let quiet: Vec<f32> = (0..16000)
+            .map(|i| 0.01 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / sample_rate).sin())
+            .collect();
+        let mut agc = AutomaticGainControl::new(sample_rate, AgcConfig::default());
+        let output = agc.process(&quiet);
+        let settled = rms(&output[8000..]);
+        assert!((settled - 0.1).abs() < 0.02, "Settled RMS: {}", settled);
+    }
+
+    #[test]
+    fn test_agc_respects_max_gain() {
+        let config = AgcConfig { max_gain_db: 6.0, noise_floor_rms: 0.0, ..Default::default() };
+        let mut agc = AutomaticGainControl::new(8000.0, config);
+        let output = agc.process(&vec![0.001; 16000]);
+        assert!(output[15999] <= 0.001 * 2.0 + 1e-6);
+        assert!((agc.gain_db().unwrap() - 6.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_agc_invalid_max_gain_does_not_boost() {
+        for max_gain_db in [-6.0, f32::NAN] {
+            let config = AgcConfig { max_gain_db, noise_floor_rms: 0.0, ..Default::default() };
+            let mut agc = AutomaticGainControl::new(8000.0, config);
+            agc.process(&vec![0.001; 16000]);
+            assert!(agc.gain_db().unwrap().abs() < 0.1, "{}: {:?}", max_gain_db, agc.gain_db());
+        }
+    }
+
+    fn sine(amplitude: f32, sample_rate: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_agc_compresses_loud_signal() {
+        let sample_rate = 8000.0;
+        // 0.5 amplitude sine: RMS 0.354, about 11 dB over the 0.1 target
+        let loud = sine(0.5, sample_rate, 16000);
+        let mut agc = AutomaticGainControl::new(sample_rate, AgcConfig::default());
+        let output = agc.process(&loud);
+        let over_target_db = 20.0 * (rms(&output[8000..]) / 0.1).log10();
+        let input_over_db = 20.0 * (rms(&loud) / 0.1).log10();
+        assert!((over_target_db - input_over_db / 4.0).abs() < 0.5, "{} dB over target", over_target_db);
+    }
+
+    #[test]
+    fn test_agc_gates_noise_floor() {
+        let sample_rate = 8000.0;
+        let config = AgcConfig { release_ms: 100.0, ..Default::default() };
+        let mut agc = AutomaticGainControl::new(sample_rate, config);
+        let mut signal = sine(0.0005, sample_rate, 16000);
+        agc.process_block(&mut signal);
+        assert!(agc.is_gated());
+        assert!(rms(&signal[8000..]) < 1e-5);
+        assert!(agc.gain_db().unwrap() < -60.0);
+
+        // A long silence closes the gate fully, but the reported gain stays bounded
+        agc.process_block(&mut vec![0.0; 80000]);
+        let floor = agc.gain_db().unwrap();
+        assert!(floor.is_finite() && floor >= -100.0, "{}", floor);
+
+        // The gate opens again once the singer comes in
+        let mut voice = sine(0.02, sample_rate, 8000);
+        agc.process_block(&mut voice);
+        assert!(!agc.is_gated());
+        assert!(agc.gain_db().unwrap() > 10.0);
+    }
+
+    #[test]
+    fn test_agc_attack_faster_than_release() {
+        let sample_rate = 8000.0;
+        let config = AgcConfig { attack_ms: 5.0, release_ms: 500.0, ..Default::default() };
+        let mut agc = AutomaticGainControl::new(sample_rate, config);
+
+        // Settle on a quiet signal, then jump up: the gain drops within ~50 ms
+        agc.process_block(&mut sine(0.01, sample_rate, 16000));
+        let boosted = agc.gain_db().unwrap();
+        agc.process_block(&mut sine(0.3, sample_rate, 400));
+        let after_attack = agc.gain_db().unwrap();
+        assert!(after_attack < boosted - 15.0, "{} -> {}", boosted, after_attack);
+
+        // Back to quiet: after the same 50 ms the gain has barely recovered
+        agc.process_block(&mut sine(0.01, sample_rate, 400));
+        assert!(agc.gain_db().unwrap() < after_attack + 5.0);
+    }
+}
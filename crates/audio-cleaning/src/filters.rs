@@ -0,0 +1,967 @@
+//! Stateful Filters
+//!
+//! This module provides the time-domain filter stages used by the cleaning pipeline:
+//! - DC offset removal
+//! - Second-order high-pass filtering for rumble removal
+//! - First-order pre-emphasis
+//! - Bandpass filtering for vocal range isolation, either with a single state-variable
+//!   filter or with higher-order Butterworth, Chebyshev and Linkwitz-Riley designs
+//!   built from cascaded biquads
+//!
+//! All filters keep their state between calls, so they can process a live stream
+//! in arbitrary chunk sizes and produce the same output as a single batch call.
+//! Offline, the bandpass filter can also run forward and backward over the signal
+//! (zero-phase filtering) so that note onsets are not delayed or smeared.
+
+use fundsp::hacker::*;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use crate::processor::AudioProcessor;
+use crate::cleaning::{DEFAULT_VOCAL_LOW_HZ, DEFAULT_VOCAL_HIGH_HZ};
+
+/// Second-order IIR filter section (transposed direct form II)
+///
+/// Coefficients follow the RBJ audio EQ cookbook and are normalized so that `a0 = 1`.
+/// State is kept in `f64` to stay accurate for low cutoffs at high sample rates.
+#[derive(Debug, Clone, Copy)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    /// Create a biquad from raw coefficients (`a0` is assumed to be 1)
+    pub fn from_coefficients(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Self {
+        Self { b0, b1, b2, a1, a2, z1: 0.0, z2: 0.0 }
+    }
+
+    /// Second-order high-pass filter
+    ///
+    /// # Arguments
+    /// * `sample_rate` - Sample rate of the audio in Hz
+    /// * `cutoff_hz` - -3 dB cutoff frequency (for `q = 1/sqrt(2)`)
+    /// * `q` - Quality factor
+    pub fn highpass(sample_rate: f32, cutoff_hz: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Self::intermediates(sample_rate, cutoff_hz, q);
+        let a0 = 1.0 + alpha;
+        Self::from_coefficients(
+            (1.0 + cos_w0) / 2.0 / a0,
+            -(1.0 + cos_w0) / a0,
+            (1.0 + cos_w0) / 2.0 / a0,
+            -2.0 * cos_w0 / a0,
+            (1.0 - alpha) / a0,
+        )
+    }
+
+    /// Second-order low-pass filter
+    ///
+    /// # Arguments
+    /// * `sample_rate` - Sample rate of the audio in Hz
+    /// * `cutoff_hz` - -3 dB cutoff frequency (for `q = 1/sqrt(2)`)
+    /// * `q` - Quality factor
+    pub fn lowpass(sample_rate: f32, cutoff_hz: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Self::intermediates(sample_rate, cutoff_hz, q);
+        let a0 = 1.0 + alpha;
+        Self::from_coefficients(
+            (1.0 - cos_w0) / 2.0 / a0,
+            (1.0 - cos_w0) / a0,
+            (1.0 - cos_w0) / 2.0 / a0,
+            -2.0 * cos_w0 / a0,
+            (1.0 - alpha) / a0,
+        )
+    }
+
+    /// Second-order notch (band-reject) filter
+    ///
+    /// # Arguments
+    /// * `sample_rate` - Sample rate of the audio in Hz
+    /// * `center_hz` - Frequency that is removed completely
+    /// * `q` - Quality factor (center frequency divided by the -3 dB bandwidth)
+    pub fn notch(sample_rate: f32, center_hz: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Self::intermediates(sample_rate, center_hz, q);
+        let a0 = 1.0 + alpha;
+        Self::from_coefficients(
+            1.0 / a0,
+            -2.0 * cos_w0 / a0,
+            1.0 / a0,
+            -2.0 * cos_w0 / a0,
+            (1.0 - alpha) / a0,
+        )
+    }
+
+    /// First-order high-pass section (bilinear transform, prewarped at the cutoff)
+    pub fn first_order_highpass(sample_rate: f32, cutoff_hz: f32) -> Self {
+        let k = (PI * cutoff_hz as f64 / sample_rate as f64).tan();
+        Self::from_coefficients(1.0 / (1.0 + k), -1.0 / (1.0 + k), 0.0, (k - 1.0) / (k + 1.0), 0.0)
+    }
+
+    /// First-order low-pass section (bilinear transform, prewarped at the cutoff)
+    pub fn first_order_lowpass(sample_rate: f32, cutoff_hz: f32) -> Self {
+        let k = (PI * cutoff_hz as f64 / sample_rate as f64).tan();
+        Self::from_coefficients(k / (1.0 + k), k / (1.0 + k), 0.0, (k - 1.0) / (k + 1.0), 0.0)
+    }
+
+    /// Theoretical magnitude response (linear gain) at `frequency_hz`
+    pub fn magnitude_at(&self, sample_rate: f32, frequency_hz: f32) -> f32 {
+        let w = 2.0 * PI * frequency_hz as f64 / sample_rate as f64;
+        let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+        let num_re = self.b0 + self.b1 * c1 + self.b2 * c2;
+        let num_im = -(self.b1 * s1 + self.b2 * s2);
+        let den_re = 1.0 + self.a1 * c1 + self.a2 * c2;
+        let den_im = -(self.a1 * s1 + self.a2 * s2);
+        ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)).sqrt() as f32
+    }
+
+    fn intermediates(sample_rate: f32, frequency_hz: f32, q: f32) -> (f64, f64) {
+        let w0 = 2.0 * PI * frequency_hz as f64 / sample_rate as f64;
+        (w0.cos(), w0.sin() / (2.0 * q as f64))
+    }
+
+    /// Filter a single sample
+    pub fn process_sample(&mut self, x: f32) -> f32 {
+        let x = x as f64;
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y as f32
+    }
+
+    /// Clear the filter memory
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+/// Filter family used to design the bandpass skirts
+///
+/// `order` is the order of each skirt: a 4th-order Butterworth bandpass rolls off at
+/// 24 dB/octave below the low cutoff and above the high cutoff.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FilterDesign {
+    /// Single fundsp state-variable bandpass with a Q derived from the band edges.
+    /// Gentle skirts and a resonant peak at the center; kept for compatibility.
+    #[default]
+    Svf,
+    /// Maximally flat passband, -3 dB at the cutoffs
+    Butterworth { order: usize },
+    /// Steeper skirts at the cost of `ripple_db` of passband ripple (type I)
+    Chebyshev { order: usize, ripple_db: f32 },
+    /// Squared Butterworth, -6 dB at the cutoffs. `order` must be even.
+    LinkwitzRiley { order: usize },
+}
+
+impl FilterDesign {
+    /// Largest supported skirt order
+    pub const MAX_ORDER: usize = 16;
+
+    /// Check that the design parameters are usable
+    pub fn validate(&self) -> Result<(), String> {
+        let check_order = |order: usize| {
+            if (1..=Self::MAX_ORDER).contains(&order) {
+                Ok(())
+            } else {
+                Err(format!("filter order must be between 1 and {}, got {}", Self::MAX_ORDER, order))
+            }
+        };
+        match *self {
+            FilterDesign::Svf => Ok(()),
+            FilterDesign::Butterworth { order } => check_order(order),
+            FilterDesign::Chebyshev { order, ripple_db } => {
+                check_order(order)?;
+                if ripple_db > 0.0 {
+                    Ok(())
+                } else {
+                    Err(format!("Chebyshev ripple must be positive, got {} dB", ripple_db))
+                }
+            }
+            FilterDesign::LinkwitzRiley { order } => {
+                check_order(order)?;
+                if order % 2 == 0 {
+                    Ok(())
+                } else {
+                    Err(format!("Linkwitz-Riley order must be even, got {}", order))
+                }
+            }
+        }
+    }
+}
+
+/// Pole of a normalized (1 rad/s) analog low-pass prototype, as used by one section
+#[derive(Debug, Clone, Copy)]
+enum PrototypeSection {
+    /// Real pole at `-radius`
+    FirstOrder { radius: f64 },
+    /// Complex pole pair with natural frequency `radius` and quality factor `q`
+    SecondOrder { radius: f64, q: f64 },
+}
+
+/// Analog prototype sections and overall gain correction for a design
+fn prototype_sections(design: FilterDesign) -> (Vec<PrototypeSection>, f64) {
+    match design {
+        FilterDesign::Svf => (Vec::new(), 1.0),
+        FilterDesign::Butterworth { order } => (butterworth_sections(order), 1.0),
+        FilterDesign::LinkwitzRiley { order } => {
+            let half = butterworth_sections(order / 2);
+            (half.iter().chain(half.iter()).copied().collect(), 1.0)
+        }
+        FilterDesign::Chebyshev { order, ripple_db } => {
+            let epsilon = (10.0_f64.powf(ripple_db as f64 / 10.0) - 1.0).sqrt();
+            let mu = (1.0 / epsilon).asinh() / order as f64;
+            let mut sections = Vec::new();
+            for k in 1..=order / 2 {
+                let theta = PI * (2 * k - 1) as f64 / (2 * order) as f64;
+                let sigma = mu.sinh() * theta.sin();
+                let omega = mu.cosh() * theta.cos();
+                let radius = (sigma * sigma + omega * omega).sqrt();
+                sections.push(PrototypeSection::SecondOrder { radius, q: radius / (2.0 * sigma) });
+            }
+            if order % 2 == 1 {
+                sections.push(PrototypeSection::FirstOrder { radius: mu.sinh() });
+            }
+            // Even orders start the passband at the bottom of the ripple; scale so the
+            // ripple peaks at 0 dB instead of exceeding it
+            let gain = if order % 2 == 0 { 1.0 / (1.0 + epsilon * epsilon).sqrt() } else { 1.0 };
+            (sections, gain)
+        }
+    }
+}
+
+fn butterworth_sections(order: usize) -> Vec<PrototypeSection> {
+    let mut sections: Vec<PrototypeSection> = (1..=order / 2)
+        .map(|k| PrototypeSection::SecondOrder {
+            radius: 1.0,
+            q: 1.0 / (2.0 * (PI * (2 * k - 1) as f64 / (2 * order) as f64).sin()),
+        })
+        .collect();
+    if order % 2 == 1 {
+        sections.push(PrototypeSection::FirstOrder { radius: 1.0 });
+    }
+    sections
+}
+
+/// Series of biquad sections with an overall gain
+#[derive(Debug, Clone)]
+pub struct BiquadCascade {
+    sections: Vec<Biquad>,
+    gain: f64,
+}
+
+impl BiquadCascade {
+    /// Create a cascade from explicit sections
+    pub fn new(sections: Vec<Biquad>, gain: f64) -> Self {
+        Self { sections, gain }
+    }
+
+    /// High-pass cascade of the given design with its corner at `cutoff_hz`
+    pub fn highpass(design: FilterDesign, sample_rate: f32, cutoff_hz: f32) -> Self {
+        let (prototype, gain) = prototype_sections(design);
+        let sections = prototype
+            .iter()
+            .map(|section| match *section {
+                // Low-pass to high-pass transform: s -> wc / s maps radius r to wc / r
+                PrototypeSection::FirstOrder { radius } => {
+                    Biquad::first_order_highpass(sample_rate, (cutoff_hz as f64 / radius) as f32)
+                }
+                PrototypeSection::SecondOrder { radius, q } => {
+                    Biquad::highpass(sample_rate, (cutoff_hz as f64 / radius) as f32, q as f32)
+                }
+            })
+            .collect();
+        Self { sections, gain }
+    }
+
+    /// Low-pass cascade of the given design with its corner at `cutoff_hz`
+    pub fn lowpass(design: FilterDesign, sample_rate: f32, cutoff_hz: f32) -> Self {
+        let (prototype, gain) = prototype_sections(design);
+        let sections = prototype
+            .iter()
+            .map(|section| match *section {
+                PrototypeSection::FirstOrder { radius } => {
+                    Biquad::first_order_lowpass(sample_rate, (cutoff_hz as f64 * radius) as f32)
+                }
+                PrototypeSection::SecondOrder { radius, q } => {
+                    Biquad::lowpass(sample_rate, (cutoff_hz as f64 * radius) as f32, q as f32)
+                }
+            })
+            .collect();
+        Self { sections, gain }
+    }
+
+    /// Append the sections of another cascade
+    pub fn chain(mut self, other: BiquadCascade) -> Self {
+        self.sections.extend(other.sections);
+        self.gain *= other.gain;
+        self
+    }
+
+    /// Number of biquad sections
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Whether the cascade has no sections
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// Filter a single sample through all sections
+    pub fn process_sample(&mut self, x: f32) -> f32 {
+        let y = self.sections.iter_mut().fold(x, |acc, section| section.process_sample(acc));
+        (y as f64 * self.gain) as f32
+    }
+
+    /// Clear the memory of all sections
+    pub fn reset(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.reset();
+        }
+    }
+
+    /// Theoretical magnitude response (linear gain) at `frequency_hz`
+    pub fn magnitude_at(&self, sample_rate: f32, frequency_hz: f32) -> f32 {
+        self.sections
+            .iter()
+            .fold(self.gain as f32, |acc, section| acc * section.magnitude_at(sample_rate, frequency_hz))
+    }
+}
+
+/// Configuration for DC offset removal
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DcRemovalConfig {
+    /// Corner frequency of the DC blocking filter in Hz.
+    /// Default: 20.0 Hz (well below any sung fundamental)
+    pub cutoff_hz: f32,
+}
+
+impl Default for DcRemovalConfig {
+    fn default() -> Self {
+        Self { cutoff_hz: 20.0 }
+    }
+}
+
+/// One-pole DC blocking filter: `y[n] = x[n] - x[n-1] + r * y[n-1]`
+#[derive(Debug, Clone)]
+pub struct DcBlocker {
+    config: DcRemovalConfig,
+    pole: f32,
+    prev_input: f32,
+    prev_output: f32,
+}
+
+impl DcBlocker {
+    /// Create a DC blocker for the given sample rate
+    pub fn new(sample_rate: f32, config: DcRemovalConfig) -> Self {
+        let pole = (-2.0 * std::f32::consts::PI * config.cutoff_hz / sample_rate).exp();
+        Self { config, pole, prev_input: 0.0, prev_output: 0.0 }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &DcRemovalConfig {
+        &self.config
+    }
+}
+
+impl AudioProcessor for DcBlocker {
+    fn name(&self) -> &'static str {
+        "dc_removal"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            let x = *sample;
+            let y = x - self.prev_input + self.pole * self.prev_output;
+            self.prev_input = x;
+            self.prev_output = y;
+            *sample = y;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.prev_output = 0.0;
+    }
+}
+
+/// Configuration for the high-pass (rumble) filter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighPassConfig {
+    /// Cutoff frequency in Hz. Default: 60.0 Hz
+    pub cutoff_hz: f32,
+    /// Quality factor. Default: 0.7071 (Butterworth, no resonance)
+    pub q: f32,
+}
+
+impl Default for HighPassConfig {
+    fn default() -> Self {
+        Self {
+            cutoff_hz: 60.0,
+            q: std::f32::consts::FRAC_1_SQRT_2,
+        }
+    }
+}
+
+/// Second-order high-pass filter for removing low-frequency rumble
+#[derive(Debug, Clone)]
+pub struct HighPassFilter {
+    config: HighPassConfig,
+    biquad: Biquad,
+}
+
+impl HighPassFilter {
+    /// Create a high-pass filter for the given sample rate
+    pub fn new(sample_rate: f32, config: HighPassConfig) -> Self {
+        Self {
+            config,
+            biquad: Biquad::highpass(sample_rate, config.cutoff_hz, config.q),
+        }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &HighPassConfig {
+        &self.config
+    }
+}
+
+impl AudioProcessor for HighPassFilter {
+    fn name(&self) -> &'static str {
+        "high_pass"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample = self.biquad.process_sample(*sample);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.biquad.reset();
+    }
+}
+
+/// Configuration for pre-emphasis filtering
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreEmphasisConfig {
+    /// Filter coefficient `alpha` in `y[n] = x[n] - alpha * x[n-1]`.
+    /// Default: 0.97
+    pub coefficient: f32,
+}
+
+impl Default for PreEmphasisConfig {
+    fn default() -> Self {
+        Self { coefficient: 0.97 }
+    }
+}
+
+/// First-order pre-emphasis filter, boosting high frequencies by ~6 dB/octave
+#[derive(Debug, Clone)]
+pub struct PreEmphasis {
+    config: PreEmphasisConfig,
+    prev_input: f32,
+}
+
+impl PreEmphasis {
+    /// Create a pre-emphasis filter
+    pub fn new(config: PreEmphasisConfig) -> Self {
+        Self { config, prev_input: 0.0 }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &PreEmphasisConfig {
+        &self.config
+    }
+}
+
+impl AudioProcessor for PreEmphasis {
+    fn name(&self) -> &'static str {
+        "pre_emphasis"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            let x = *sample;
+            *sample = x - self.config.coefficient * self.prev_input;
+            self.prev_input = x;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.prev_input = 0.0;
+    }
+}
+
+/// Configuration for de-emphasis filtering
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeEmphasisConfig {
+    /// Filter coefficient `alpha` in `y[n] = x[n] + alpha * y[n-1]`. Use the
+    /// pre-emphasis coefficient to undo pre-emphasis.
+    /// Default: 0.97
+    pub coefficient: f32,
+}
+
+impl Default for DeEmphasisConfig {
+    fn default() -> Self {
+        Self { coefficient: 0.97 }
+    }
+}
+
+/// First-order de-emphasis filter, the exact inverse of [`PreEmphasis`] with the
+/// same coefficient
+#[derive(Debug, Clone)]
+pub struct DeEmphasis {
+    config: DeEmphasisConfig,
+    prev_output: f32,
+}
+
+impl DeEmphasis {
+    /// Create a de-emphasis filter
+    pub fn new(config: DeEmphasisConfig) -> Self {
+        Self { config, prev_output: 0.0 }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &DeEmphasisConfig {
+        &self.config
+    }
+}
+
+impl AudioProcessor for DeEmphasis {
+    fn name(&self) -> &'static str {
+        "de_emphasis"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample += self.config.coefficient * self.prev_output;
+            self.prev_output = *sample;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.prev_output = 0.0;
+    }
+}
+
+/// Apply pre-emphasis to a whole signal
+pub fn pre_emphasis(samples: &[f32], config: PreEmphasisConfig) -> Vec<f32> {
+    PreEmphasis::new(config).process(samples)
+}
+
+/// Undo pre-emphasis of a whole signal
+pub fn de_emphasis(samples: &[f32], config: DeEmphasisConfig) -> Vec<f32> {
+    DeEmphasis::new(config).process(samples)
+}
+
+/// Configuration for bandpass filtering
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandpassConfig {
+    /// Low cutoff frequency in Hz. Default: 80.0 Hz
+    pub low_hz: f32,
+    /// High cutoff frequency in Hz. Default: 1200.0 Hz
+    pub high_hz: f32,
+    /// Filter family and order. Default: `Svf` (single state-variable filter)
+    pub design: FilterDesign,
+    /// Run the filter forward and backward in batch mode, cancelling its phase
+    /// shift and squaring its magnitude response. Streaming always runs causally.
+    /// Default: false
+    pub zero_phase: bool,
+}
+
+impl Default for BandpassConfig {
+    fn default() -> Self {
+        Self {
+            low_hz: DEFAULT_VOCAL_LOW_HZ,
+            high_hz: DEFAULT_VOCAL_HIGH_HZ,
+            design: FilterDesign::Svf,
+            zero_phase: false,
+        }
+    }
+}
+
+enum BandpassKernel {
+    Svf(An<FixedSvf<f64, BandpassMode<f64>>>),
+    Cascade(BiquadCascade),
+}
+
+/// Stateful bandpass filter for isolating the vocal range
+///
+/// With the default `Svf` design this uses fundsp's bandpass filter with the center
+/// frequency and Q derived from the cutoffs, exactly like
+/// [`bandpass_vocal_range`](crate::bandpass_vocal_range). The other designs cascade
+/// a high-pass and a low-pass of the requested order.
+pub struct BandpassFilter {
+    config: BandpassConfig,
+    sample_rate: f32,
+    kernel: BandpassKernel,
+}
+
+impl BandpassFilter {
+    /// Create a bandpass filter for the given sample rate
+    pub fn new(sample_rate: f32, config: BandpassConfig) -> Self {
+        let kernel = match config.design {
+            FilterDesign::Svf => {
+                let center = (config.low_hz + config.high_hz) * 0.5;
+                let bandwidth = config.high_hz - config.low_hz;
+                let q = if bandwidth > 0.0 { center / bandwidth } else { 1.0 };
+
+                let mut filter = bandpass_hz(center, q);
+                filter.set_sample_rate(sample_rate as f64);
+                BandpassKernel::Svf(filter)
+            }
+            design => BandpassKernel::Cascade(
+                BiquadCascade::highpass(design, sample_rate, config.low_hz)
+                    .chain(BiquadCascade::lowpass(design, sample_rate, config.high_hz)),
+            ),
+        };
+
+        Self { config, sample_rate, kernel }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &BandpassConfig {
+        &self.config
+    }
+
+    /// Theoretical magnitude response (linear gain) at `frequency_hz`, including
+    /// the squaring caused by zero-phase filtering.
+    /// Returns `None` for the `Svf` design, whose response is not modelled here.
+    pub fn magnitude_at(&self, frequency_hz: f32) -> Option<f32> {
+        match &self.kernel {
+            BandpassKernel::Svf(_) => None,
+            BandpassKernel::Cascade(cascade) => {
+                let magnitude = cascade.magnitude_at(self.sample_rate, frequency_hz);
+                Some(if self.config.zero_phase { magnitude * magnitude } else { magnitude })
+            }
+        }
+    }
+
+    fn filter_sample(&mut self, x: f32) -> f32 {
+        match &mut self.kernel {
+            BandpassKernel::Svf(filter) => filter.filter_mono(x),
+            BandpassKernel::Cascade(cascade) => cascade.process_sample(x),
+        }
+    }
+
+    /// Forward-backward filtering with odd reflection padding at both ends,
+    /// so the start-up transient falls in the padding rather than the signal.
+    fn process_zero_phase(&mut self, samples: &[f32]) -> Vec<f32> {
+        let n = samples.len();
+        if n < 2 {
+            return samples.to_vec();
+        }
+        // Pad with about three periods of the lowest passband frequency
+        let pad = Ord::min((3.0 * self.sample_rate / self.config.low_hz.max(1.0)) as usize, n - 1);
+        let mut padded = Vec::with_capacity(n + 2 * pad);
+        padded.extend((1..=pad).rev().map(|i| 2.0 * samples[0] - samples[i]));
+        padded.extend_from_slice(samples);
+        padded.extend((1..=pad).map(|i| 2.0 * samples[n - 1] - samples[n - 1 - i]));
+
+        self.reset();
+        for sample in padded.iter_mut() {
+            *sample = self.filter_sample(*sample);
+        }
+        self.reset();
+        for sample in padded.iter_mut().rev() {
+            *sample = self.filter_sample(*sample);
+        }
+        self.reset();
+
+        padded[pad..pad + n].to_vec()
+    }
+}
+
+impl AudioProcessor for BandpassFilter {
+    fn name(&self) -> &'static str {
+        "bandpass"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample = self.filter_sample(*sample);
+        }
+    }
+
+    fn reset(&mut self) {
+        match &mut self.kernel {
+            BandpassKernel::Svf(filter) => filter.reset(),
+            BandpassKernel::Cascade(cascade) => cascade.reset(),
+        }
+    }
+
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        if self.config.zero_phase {
+            return self.process_zero_phase(samples);
+        }
+        self.reset();
+        let mut output = samples.to_vec();
+        self.process_block(&mut output);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_wave(freq: f32, sample_rate: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    fn steady_state_rms(signal: &[f32]) -> f32 {
+        let tail = &signal[signal.len() / 2..];
+        (tail.iter().map(|x| x * x).sum::<f32>() / tail.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_dc_blocker_removes_offset() {
+        let samples: Vec<f32> = sine_wave(220.0, 8000.0, 8000).iter().map(|x| x + 0.5).collect();
+        let mut dc = DcBlocker::new(8000.0, DcRemovalConfig::default());
+        let output = dc.process(&samples);
+        let tail = &output[4000..];
+        let mean = tail.iter().sum::<f32>() / tail.len() as f32;
+        assert!(mean.abs() < 0.01, "Residual DC offset: {}", mean);
+    }
+
+    #[test]
+    fn test_highpass_attenuates_rumble() {
+        let sample_rate = 16000.0;
+        let mut hp = HighPassFilter::new(sample_rate, HighPassConfig { cutoff_hz: 100.0, ..Default::default() });
+        let rumble = hp.process(&sine_wave(20.0, sample_rate, 16000));
+        let voice = hp.process(&sine_wave(440.0, sample_rate, 16000));
+        assert!(steady_state_rms(&rumble) < 0.1);
+        assert!(steady_state_rms(&voice) > 0.65);
+    }
+
+    #[test]
+    fn test_lowpass_attenuates_hiss() {
+        let sample_rate = 16000.0;
+        let mut lp = Biquad::lowpass(sample_rate, 1000.0, std::f32::consts::FRAC_1_SQRT_2);
+        let hiss: Vec<f32> = sine_wave(6000.0, sample_rate, 16000)
+            .iter()
+            .map(|&x| lp.process_sample(x))
+            .collect();
+        assert!(steady_state_rms(&hiss) < 0.05);
+    }
+
+    #[test]
+    fn test_pre_emphasis_first_sample_and_difference() {
+        let mut pre = PreEmphasis::new(PreEmphasisConfig { coefficient: 0.5 });
+        let output = pre.process(&[1.0, 1.0, 0.0]);
+        assert_eq!(output, vec![1.0, 0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_de_emphasis_inverts_pre_emphasis() {
+        let signal: Vec<f32> = (0..2000).map(|i| (i as f32 * 0.05).sin() + 0.3 * (i as f32 * 1.3).cos()).collect();
+        let emphasized = pre_emphasis(&signal, PreEmphasisConfig::default());
+        let restored = de_emphasis(&emphasized, DeEmphasisConfig::default());
+        assert!(signal.iter().zip(&restored).all(|(a, b)| (a - b).abs() < 1e-4));
+
+        // Streaming in chunks matches batch
+        let mut pre = PreEmphasis::new(PreEmphasisConfig::default());
+        let mut de = DeEmphasis::new(DeEmphasisConfig::default());
+        let mut streamed = signal.clone();
+        for chunk in streamed.chunks_mut(300) {
+            pre.process_block(chunk);
+            de.process_block(chunk);
+        }
+        assert!(signal.iter().zip(&streamed).all(|(a, b)| (a - b).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_pre_emphasis_tilts_spectrum() {
+        // About +6 dB/octave well above the filter's corner
+        let sample_rate = 16000.0;
+        let gain = |freq: f32| {
+            let input = sine_wave(freq, sample_rate, 4000);
+            let output = pre_emphasis(&input, PreEmphasisConfig::default());
+            let rms = |s: &[f32]| (s[100..].iter().map(|x| x * x).sum::<f32>() / (s.len() - 100) as f32).sqrt();
+            rms(&output) / rms(&input)
+        };
+        let octave = to_db(gain(1000.0)) - to_db(gain(500.0));
+        assert!((octave - 6.0).abs() < 0.5, "{} dB/octave", octave);
+        assert!(gain(50.0) < 0.1);
+    }
+
+    /// Measure the steady-state gain of `filter` for a sine at `freq`
+    fn measured_gain(config: BandpassConfig, sample_rate: f32, freq: f32) -> f32 {
+        let len = (sample_rate * 1.0) as usize;
+        let input = sine_wave(freq, sample_rate, len);
+        let mut filter = BandpassFilter::new(sample_rate, config);
+        let output = filter.process(&input);
+        // Skip the first quarter (start-up transient) and the last quarter (zero-phase end effects)
+        let range = len / 4..3 * len / 4;
+        let rms = |s: &[f32]| (s.iter().map(|x| x * x).sum::<f32>() / s.len() as f32).sqrt();
+        rms(&output[range.clone()]) / rms(&input[range])
+    }
+
+    fn to_db(gain: f32) -> f32 {
+        20.0 * gain.log10()
+    }
+
+    const SAMPLE_RATES: [f32; 4] = [8000.0, 16000.0, 44100.0, 48000.0];
+
+    #[test]
+    fn test_butterworth_response_across_sample_rates() {
+        for &sample_rate in &SAMPLE_RATES {
+            let config = BandpassConfig {
+                design: FilterDesign::Butterworth { order: 4 },
+                ..Default::default()
+            };
+            let passband = to_db(measured_gain(config, sample_rate, 300.0));
+            let low_edge = to_db(measured_gain(config, sample_rate, 80.0));
+            let high_edge = to_db(measured_gain(config, sample_rate, 1200.0));
+            let rumble = to_db(measured_gain(config, sample_rate, 40.0));
+            let hiss = to_db(measured_gain(config, sample_rate, 2400.0));
+
+            assert!(passband.abs() < 0.5, "{} Hz: passband {:.2} dB", sample_rate, passband);
+            assert!((low_edge + 3.0).abs() < 1.0, "{} Hz: low edge {:.2} dB", sample_rate, low_edge);
+            assert!((high_edge + 3.0).abs() < 1.0, "{} Hz: high edge {:.2} dB", sample_rate, high_edge);
+            // One octave outside the band a 4th-order skirt is down ~24 dB
+            assert!(rumble < -20.0, "{} Hz: 40 Hz at {:.2} dB", sample_rate, rumble);
+            assert!(hiss < -20.0, "{} Hz: 2400 Hz at {:.2} dB", sample_rate, hiss);
+        }
+    }
+
+    #[test]
+    fn test_linkwitz_riley_is_6db_down_at_cutoff() {
+        for &sample_rate in &SAMPLE_RATES {
+            let config = BandpassConfig {
+                design: FilterDesign::LinkwitzRiley { order: 4 },
+                ..Default::default()
+            };
+            let low_edge = to_db(measured_gain(config, sample_rate, 80.0));
+            let passband = to_db(measured_gain(config, sample_rate, 310.0));
+            assert!((low_edge + 6.0).abs() < 1.0, "{} Hz: low edge {:.2} dB", sample_rate, low_edge);
+            assert!(passband.abs() < 0.5, "{} Hz: passband {:.2} dB", sample_rate, passband);
+        }
+    }
+
+    #[test]
+    fn test_chebyshev_is_steeper_than_butterworth() {
+        for &sample_rate in &SAMPLE_RATES {
+            let butterworth = BandpassConfig {
+                design: FilterDesign::Butterworth { order: 4 },
+                ..Default::default()
+            };
+            let chebyshev = BandpassConfig {
+                design: FilterDesign::Chebyshev { order: 4, ripple_db: 1.0 },
+                ..Default::default()
+            };
+            let passband = to_db(measured_gain(chebyshev, sample_rate, 300.0));
+            assert!(passband < 0.1 && passband > -1.1, "{} Hz: passband {:.2} dB", sample_rate, passband);
+            assert!(
+                measured_gain(chebyshev, sample_rate, 50.0) < measured_gain(butterworth, sample_rate, 50.0),
+                "{} Hz: Chebyshev should attenuate more outside the band", sample_rate
+            );
+        }
+    }
+
+    #[test]
+    fn test_measured_matches_theoretical_response() {
+        for &sample_rate in &SAMPLE_RATES {
+            for design in [
+                FilterDesign::Butterworth { order: 3 },
+                FilterDesign::Chebyshev { order: 5, ripple_db: 0.5 },
+                FilterDesign::LinkwitzRiley { order: 2 },
+            ] {
+                let config = BandpassConfig { design, ..Default::default() };
+                let filter = BandpassFilter::new(sample_rate, config);
+                for freq in [60.0, 200.0, 900.0, 1800.0] {
+                    let expected = to_db(filter.magnitude_at(freq).unwrap());
+                    let measured = to_db(measured_gain(config, sample_rate, freq));
+                    assert!(
+                        (expected - measured).abs() < 0.5,
+                        "{:?} at {} Hz / {} Hz: expected {:.2} dB, measured {:.2} dB",
+                        design, freq, sample_rate, expected, measured
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_phase_squares_response_without_delay() {
+        let sample_rate = 16000.0;
+        let config = BandpassConfig {
+            design: FilterDesign::Butterworth { order: 2 },
+            zero_phase: true,
+            ..Default::default()
+        };
+        let edge = to_db(measured_gain(config, sample_rate, 80.0));
+        assert!((edge + 6.0).abs() < 1.0, "Zero-phase edge: {:.2} dB", edge);
+
+        // A sine in the passband comes out in phase with the input
+        let input = sine_wave(300.0, sample_rate, 16000);
+        let output = BandpassFilter::new(sample_rate, config).process(&input);
+        let range = 4000..12000;
+        let correlation: f32 = input[range.clone()].iter().zip(&output[range.clone()]).map(|(a, b)| a * b).sum();
+        let energy: f32 = input[range].iter().map(|x| x * x).sum();
+        assert!(correlation / energy > 0.95);
+    }
+
+    #[test]
+    fn test_zero_phase_keeps_onset_in_place() {
+        // A tone burst starting at sample 4000: the causal filter delays the envelope,
+        // the zero-phase filter keeps it centered on the true onset
+        let sample_rate = 16000.0;
+        let mut input = vec![0.0; 8000];
+        for (i, x) in input.iter_mut().enumerate().skip(4000) {
+            *x = (2.0 * std::f32::consts::PI * 300.0 * i as f32 / sample_rate).sin();
+        }
+        let causal = BandpassConfig { design: FilterDesign::Butterworth { order: 4 }, ..Default::default() };
+        let zero_phase = BandpassConfig { zero_phase: true, ..causal };
+        let first_loud = |signal: &[f32]| signal.iter().position(|x| x.abs() > 0.5).unwrap();
+
+        let causal_onset = first_loud(&BandpassFilter::new(sample_rate, causal).process(&input));
+        let zero_phase_onset = first_loud(&BandpassFilter::new(sample_rate, zero_phase).process(&input));
+        assert!(zero_phase_onset.abs_diff(4000) < causal_onset.abs_diff(4000));
+    }
+
+    #[test]
+    fn test_filter_design_validation() {
+        assert!(FilterDesign::Butterworth { order: 4 }.validate().is_ok());
+        assert!(FilterDesign::Butterworth { order: 0 }.validate().is_err());
+        assert!(FilterDesign::LinkwitzRiley { order: 3 }.validate().is_err());
+        assert!(FilterDesign::Chebyshev { order: 4, ripple_db: 0.0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_streaming_matches_batch() {
+        let sample_rate = 8000.0;
+        let signal = sine_wave(330.0, sample_rate, 1000);
+        let mut batch = BandpassFilter::new(sample_rate, BandpassConfig::default());
+        let expected = batch.process(&signal);
+
+        let mut streaming = BandpassFilter::new(sample_rate, BandpassConfig::default());
+        let mut output = signal.clone();
+        for chunk in output.chunks_mut(137) {
+            streaming.process_block(chunk);
+        }
+        for (a, b) in expected.iter().zip(output.iter()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+
+        let config = BandpassConfig { design: FilterDesign::Chebyshev { order: 6, ripple_db: 0.5 }, ..Default::default() };
+        let expected = BandpassFilter::new(sample_rate, config).process(&signal);
+        let mut streaming = BandpassFilter::new(sample_rate, config);
+        let mut output = signal.clone();
+        for chunk in output.chunks_mut(137) {
+            streaming.process_block(chunk);
+        }
+        assert_eq!(expected, output);
+    }
+}
@@ -0,0 +1,242 @@
+//! Harmonic/Percussive Source Separation
+//!
+//! Sustained sounds (voice, held chords) form horizontal ridges in a spectrogram,
+//! while transients (drum hits, strums, clicks) form vertical ones. Median filtering
+//! the magnitudes along time keeps the ridges of the first kind and along frequency
+//! those of the second (Fitzgerald, 2010). The two filtered spectrograms give soft
+//! masks that split the STFT into a harmonic and a percussive part, and the parts are
+//! resynthesised with the inverse STFT.
+//!
+//! Pitch detection on the harmonic part of a recording sung over a backing track
+//! mostly sees the voice and chords instead of drum transients.
+//!
+//! Separation needs future frames for the median along time, so it is offline only.
+//!
+//! # Example
+//!
+//! ```
+//! use audio_cleaning::hpss::{harmonic_component, HpssConfig};
+//! use audio_utils::MonoAudio;
+//!
+//! let mix = MonoAudio::new(vec![0.0; 8000], 8000);
+//! let harmonic = harmonic_component(&mix, &HpssConfig::default());
+//! assert_eq!(harmonic.samples.len(), mix.samples.len());
+//! ```
+
+use audio_utils::MonoAudio;
+use serde::{Deserialize, Serialize};
+use crate::stft::{Stft, StftConfig, WindowType};
+
+/// Configuration for harmonic/percussive separation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HpssConfig {
+    /// STFT frame length in samples. Default: 2048
+    pub window_size: usize,
+    /// Samples between STFT frames. Default: 512
+    pub hop_size: usize,
+    /// Length of the median filter along time, in frames. Longer kernels need a
+    /// sound to be sustained for longer to count as harmonic. Default: 17
+    pub harmonic_kernel: usize,
+    /// Length of the median filter along frequency, in bins. Longer kernels need a
+    /// sound to be broader-band to count as percussive. Default: 17
+    pub percussive_kernel: usize,
+    /// Exponent of the soft (Wiener-like) masks. 1.0 is gentle, 2.0 the usual
+    /// choice; large values approach a binary mask. Default: 2.0
+    pub mask_power: f32,
+}
+
+impl Default for HpssConfig {
+    fn default() -> Self {
+        Self {
+            window_size: 2048,
+            hop_size: 512,
+            harmonic_kernel: 17,
+            percussive_kernel: 17,
+            mask_power: 2.0,
+        }
+    }
+}
+
+/// The two parts of a separated signal. They sum to the input (to rounding).
+#[derive(Debug, Clone)]
+pub struct HarmonicPercussive {
+    /// Sustained, tonal part
+    pub harmonic: MonoAudio,
+    /// Transient, broadband part
+    pub percussive: MonoAudio,
+}
+
+/// Split `audio` into its harmonic and percussive parts
+pub fn separate(audio: &MonoAudio, config: &HpssConfig) -> HarmonicPercussive {
+    let stft_config = StftConfig {
+        window_size: config.window_size,
+        hop_size: config.hop_size,
+        window: WindowType::Hann,
+    };
+    let mut harmonic = Stft::from_waveform(&audio.samples, audio.sample_rate as f32, stft_config);
+    let mut percussive = harmonic.clone();
+
+    let magnitudes = harmonic.magnitudes();
+    let harmonic_enhanced = median_along_time(&magnitudes, config.harmonic_kernel);
+    let percussive_enhanced = median_along_frequency(&magnitudes, config.percussive_kernel);
+
+    let power = config.mask_power.max(f32::EPSILON);
+    for (t, (h_frame, p_frame)) in harmonic.frames.iter_mut().zip(percussive.frames.iter_mut()).enumerate() {
+        for (k, (h_bin, p_bin)) in h_frame.iter_mut().zip(p_frame.iter_mut()).enumerate() {
+            let h = harmonic_enhanced[t][k].powf(power);
+            let p = percussive_enhanced[t][k].powf(power);
+            // Split silent bins evenly, so the parts still sum to the input
+            let mask = if h + p > 0.0 { h / (h + p) } else { 0.5 };
+            *h_bin *= mask;
+            *p_bin *= 1.0 - mask;
+        }
+    }
+
+    HarmonicPercussive {
+        harmonic: MonoAudio::new(harmonic.inverse(), audio.sample_rate),
+        percussive: MonoAudio::new(percussive.inverse(), audio.sample_rate),
+    }
+}
+
+/// The harmonic part of `audio`, for pitch detection over accompaniment
+pub fn harmonic_component(audio: &MonoAudio, config: &HpssConfig) -> MonoAudio {
+    separate(audio, config).harmonic
+}
+
+/// Median of each bin over a window of `kernel` frames centred on each frame
+fn median_along_time(magnitudes: &[Vec<f32>], kernel: usize) -> Vec<Vec<f32>> {
+    let n_frames = magnitudes.len();
+    let n_bins = magnitudes.first().map_or(0, Vec::len);
+    let half = kernel / 2;
+    let mut filtered = vec![vec![0.0f32; n_bins]; n_frames];
+    let mut window = Vec::with_capacity(kernel.max(1));
+    for k in 0..n_bins {
+        for (t, frame) in filtered.iter_mut().enumerate() {
+            window.clear();
+            let end = Ord::min(t + half + 1, n_frames);
+            window.extend(magnitudes[t.saturating_sub(half)..end].iter().map(|m| m[k]));
+            frame[k] = median(&mut window);
+        }
+    }
+    filtered
+}
+
+/// Median of each frame over a window of `kernel` bins centred on each bin
+fn median_along_frequency(magnitudes: &[Vec<f32>], kernel: usize) -> Vec<Vec<f32>> {
+    let half = kernel / 2;
+    let mut window = Vec::with_capacity(kernel.max(1));
+    magnitudes
+        .iter()
+        .map(|frame| {
+            (0..frame.len())
+                .map(|k| {
+                    window.clear();
+                    let end = Ord::min(k + half + 1, frame.len());
+                    window.extend_from_slice(&frame[k.saturating_sub(half)..end]);
+                    median(&mut window)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Median of a non-empty window (upper median for even lengths)
+fn median(values: &mut [f32]) -> f32 {
+    let mid = values.len() / 2;
+    *values.select_nth_unstable_by(mid, f32::total_cmp).1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sound_synth::white_noise;
+    use std::f32::consts::PI;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn chord(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                [220.0, 277.2, 329.6].iter().map(|f| 0.2 * (2.0 * PI * f * t).sin()).sum()
+            })
+            .collect()
+    }
+
+    /// Decaying noise bursts every `period` samples, like a hi-hat
+    fn drum_hits(len: usize, period: usize) -> Vec<f32> {
+        white_noise(len, 1.0, 12345)
+            .iter()
+            .enumerate()
+            .map(|(i, noise)| noise * (-((i % period) as f32) / 40.0).exp())
+            .collect()
+    }
+
+    fn energy(samples: &[f32]) -> f32 {
+        samples.iter().map(|x| x * x).sum()
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&mut [5.0]), 5.0);
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), 3.0);
+    }
+
+    #[test]
+    fn test_parts_sum_to_input() {
+        let len = 8000;
+        let mix: Vec<f32> = chord(len).iter().zip(drum_hits(len, 2000)).map(|(a, b)| a + b).collect();
+        let audio = MonoAudio::new(mix.clone(), SAMPLE_RATE);
+        let parts = separate(&audio, &HpssConfig::default());
+        assert_eq!(parts.harmonic.samples.len(), len);
+        assert_eq!(parts.percussive.sample_rate, SAMPLE_RATE);
+        for ((h, p), x) in parts.harmonic.samples.iter().zip(&parts.percussive.samples).zip(&mix) {
+            assert!((h + p - x).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_separates_chord_from_drums() {
+        let len = 32000;
+        let tonal = chord(len);
+        let drums = drum_hits(len, 4000);
+        let mix: Vec<f32> = tonal.iter().zip(&drums).map(|(a, b)| a + b).collect();
+        let parts = separate(&MonoAudio::new(mix, SAMPLE_RATE), &HpssConfig::default());
+
+        // Error of each part against the source it should recover. Without separation
+        // the error would be the whole drum track.
+        let harmonic_error: Vec<f32> = parts.harmonic.samples.iter().zip(&tonal).map(|(a, b)| a - b).collect();
+        let percussive_error: Vec<f32> = parts.percussive.samples.iter().zip(&drums).map(|(a, b)| a - b).collect();
+        assert!(
+            energy(&harmonic_error) < 0.2 * energy(&drums),
+            "drums left in the harmonic part: {} vs {}",
+            energy(&harmonic_error),
+            energy(&drums)
+        );
+        assert!(energy(&percussive_error) < 0.2 * energy(&drums));
+    }
+
+    #[test]
+    fn test_mask_power_sharpens_separation() {
+        let len = 16000;
+        let tonal = chord(len);
+        let drums = drum_hits(len, 4000);
+        let mix: Vec<f32> = tonal.iter().zip(&drums).map(|(a, b)| a + b).collect();
+        let audio = MonoAudio::new(mix, SAMPLE_RATE);
+        let leak = |power: f32| {
+            let config = HpssConfig { mask_power: power, ..Default::default() };
+            let harmonic = harmonic_component(&audio, &config);
+            let error: Vec<f32> = harmonic.samples.iter().zip(&tonal).map(|(a, b)| a - b).collect();
+            energy(&error)
+        };
+        assert!(leak(2.0) < leak(1.0));
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let harmonic = harmonic_component(&MonoAudio::new(Vec::new(), SAMPLE_RATE), &HpssConfig::default());
+        assert!(harmonic.samples.is_empty());
+    }
+}
@@ -0,0 +1,507 @@
+//! Mains Hum Removal
+//!
+//! Cheap microphones and unbalanced cables pick up hum from the mains supply at
+//! 50 Hz or 60 Hz together with its harmonics. Several of those harmonics fall inside
+//! the vocal range kept by [`bandpass_vocal_range`](crate::bandpass_vocal_range), and
+//! in quiet passages a pitch detector can lock onto 100/120 Hz.
+//!
+//! This module provides:
+//! - Detection of the mains frequency (50 or 60 Hz, allowing for slight drift) from
+//!   the spectrum of a recording
+//! - A comb of notch filters on the fundamental and its harmonics, usable in batch
+//!   and streaming mode
+
+use rustfft::num_complex::Complex32;
+use rustfft::{Fft, FftDirection};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::sync::Arc;
+use crate::filters::{Biquad, BiquadCascade};
+use crate::processor::AudioProcessor;
+use crate::types::cached_fft;
+
+/// Nominal mains frequencies in Hz
+pub const MAINS_FREQUENCIES_HZ: [f32; 2] = [50.0, 60.0];
+
+/// Maximum relative deviation of the mains frequency from its nominal value
+const MAX_DRIFT: f32 = 0.02;
+
+/// Number of harmonics (including the fundamental) examined during detection
+const DETECTION_HARMONICS: usize = 6;
+
+/// A harmonic counts as present when its peak stands this far above the
+/// surrounding spectrum (in dB)
+const MIN_PROMINENCE_DB: f32 = 20.0;
+
+/// Half-width of the neighbourhood used to estimate the local spectral level, in Hz
+const NEIGHBOURHOOD_HZ: f32 = 25.0;
+
+/// Shortest signal (in seconds) that gives enough frequency resolution to tell 50
+/// and 60 Hz apart
+const MIN_DETECTION_SECONDS: f32 = 0.5;
+
+/// Configuration for hum removal
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HumConfig {
+    /// Mains frequency in Hz, or `None` to detect it from the signal. Default: None
+    pub mains_hz: Option<f32>,
+    /// Number of notches, including the one on the fundamental. Default: 8
+    pub harmonics: usize,
+    /// -3 dB width of each notch in Hz. Default: 4.0 Hz
+    pub notch_width_hz: f32,
+    /// Amount of audio analysed before the notches are switched on when streaming
+    /// with auto-detection, in seconds. Default: 1.0 s
+    pub detection_seconds: f32,
+}
+
+impl Default for HumConfig {
+    fn default() -> Self {
+        Self {
+            mains_hz: None,
+            harmonics: 8,
+            notch_width_hz: 4.0,
+            detection_seconds: 1.0,
+        }
+    }
+}
+
+/// Detect mains hum in a signal
+///
+/// Looks for narrow spectral peaks at the harmonics of 50 Hz and 60 Hz (within ±2%
+/// to allow for drift). Hum is reported when at least half of the first six
+/// harmonics stand out from the surrounding spectrum, including the fundamental or
+/// the third harmonic, so that a voice singing at 100 or 120 Hz is not mistaken for hum.
+///
+/// # Arguments
+/// * `samples` - Audio samples, at least half a second long
+/// * `sample_rate` - Sample rate of the audio in Hz
+///
+/// # Returns
+/// The estimated mains frequency in Hz, or `None` if no hum was found
+pub fn detect_mains_frequency(samples: &[f32], sample_rate: f32) -> Option<f32> {
+    if (samples.len() as f32) < MIN_DETECTION_SECONDS * sample_rate {
+        return None;
+    }
+    MainsDetector::new(samples.len(), sample_rate).detect(samples)
+}
+
+/// FFT plan and buffers for detecting hum in blocks of one fixed length, so that
+/// detection does not allocate once constructed
+struct MainsDetector {
+    sample_rate: f32,
+    fft: Arc<dyn Fft<f32>>,
+    /// Hann window; it keeps leakage from loud vocal partials out of the hum bins
+    window: Vec<f32>,
+    buffer: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+    magnitudes: Vec<f32>,
+    /// Magnitudes around a harmonic, for estimating the local spectral level
+    neighbourhood: Vec<f32>,
+}
+
+impl MainsDetector {
+    fn new(len: usize, sample_rate: f32) -> Self {
+        let len = len.max(1);
+        let fft = cached_fft(len, FftDirection::Forward);
+        let scratch_len = fft.get_inplace_scratch_len();
+        let bin_hz = sample_rate / len as f32;
+        let neighbourhood_bins = (2.0 * NEIGHBOURHOOD_HZ / bin_hz).ceil() as usize + 3;
+        Self {
+            sample_rate,
+            fft,
+            window: (0..len)
+                .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / len as f32).cos())
+                .collect(),
+            buffer: vec![Complex32::new(0.0, 0.0); len],
+            scratch: vec![Complex32::new(0.0, 0.0); scratch_len],
+            magnitudes: vec![0.0; len / 2],
+            neighbourhood: Vec::with_capacity(neighbourhood_bins),
+        }
+    }
+
+    /// Detect hum in `samples`, which must have the length the detector was built for
+    fn detect(&mut self, samples: &[f32]) -> Option<f32> {
+        let n = self.buffer.len();
+        if samples.len() != n {
+            return None;
+        }
+        for ((bin, x), w) in self.buffer.iter_mut().zip(samples).zip(&self.window) {
+            *bin = Complex32::new(x * w, 0.0);
+        }
+        self.fft.process_with_scratch(&mut self.buffer, &mut self.scratch);
+        for (magnitude, bin) in self.magnitudes.iter_mut().zip(&self.buffer) {
+            *magnitude = bin.norm();
+        }
+        let bin_hz = self.sample_rate / n as f32;
+
+        let mut best: Option<(f32, f32)> = None;
+        for nominal in MAINS_FREQUENCIES_HZ {
+            if let Some(candidate) =
+                score_mains_candidate(&self.magnitudes, &mut self.neighbourhood, bin_hz, nominal)
+            {
+                if best.is_none_or(|(score, _)| candidate.0 > score) {
+                    best = Some(candidate);
+                }
+            }
+        }
+        best.map(|(_, frequency)| frequency)
+    }
+}
+
+/// Score one nominal mains frequency. Returns the summed prominence of the
+/// detected harmonics and the refined fundamental frequency. `neighbourhood` is
+/// scratch space.
+fn score_mains_candidate(
+    magnitudes: &[f32],
+    neighbourhood: &mut Vec<f32>,
+    bin_hz: f32,
+    nominal: f32,
+) -> Option<(f32, f32)> {
+    if magnitudes.is_empty() {
+        return None;
+    }
+    let to_bin = |hz: f32| ((hz / bin_hz).round() as usize).min(magnitudes.len() - 1);
+
+    let mut present = 0;
+    let mut odd_low_harmonic = false;
+    let mut score = 0.0;
+    let mut weighted_frequency = 0.0;
+    let mut total_weight = 0.0;
+    let mut examined = 0;
+
+    for harmonic in 1..=DETECTION_HARMONICS {
+        let center = nominal * harmonic as f32;
+        if (center + NEIGHBOURHOOD_HZ) / bin_hz >= magnitudes.len() as f32 {
+            break;
+        }
+        examined += 1;
+
+        let search = to_bin(center * (1.0 - MAX_DRIFT))..=to_bin(center * (1.0 + MAX_DRIFT));
+        let Some(peak_bin) = search.max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b])) else {
+            continue;
+        };
+
+        neighbourhood.clear();
+        neighbourhood.extend_from_slice(
+            &magnitudes[to_bin(center - NEIGHBOURHOOD_HZ)..=to_bin(center + NEIGHBOURHOOD_HZ)],
+        );
+        let middle = neighbourhood.len() / 2;
+        let (_, median, _) = neighbourhood.select_nth_unstable_by(middle, |a, b| a.total_cmp(b));
+        let floor = median.max(f32::MIN_POSITIVE);
+        let prominence_db = 20.0 * (magnitudes[peak_bin] / floor).log10();
+        if prominence_db < MIN_PROMINENCE_DB {
+            continue;
+        }
+
+        present += 1;
+        if harmonic == 1 || harmonic == 3 {
+            odd_low_harmonic = true;
+        }
+        score += prominence_db;
+
+        // Average of peak_hz / harmonic weighted by the harmonic number, since
+        // higher harmonics pin down the fundamental more precisely
+        weighted_frequency += interpolate_peak(magnitudes, peak_bin) * bin_hz;
+        total_weight += harmonic as f32;
+    }
+
+    if examined == 0 || !odd_low_harmonic || present * 2 < examined {
+        return None;
+    }
+    Some((score, weighted_frequency / total_weight))
+}
+
+/// Fractional bin of a spectral peak by parabolic interpolation of the log magnitudes
+fn interpolate_peak(magnitudes: &[f32], bin: usize) -> f32 {
+    if bin == 0 || bin + 1 >= magnitudes.len() {
+        return bin as f32;
+    }
+    let db = |i: usize| 20.0 * magnitudes[i].max(f32::MIN_POSITIVE).log10();
+    let (a, b, c) = (db(bin - 1), db(bin), db(bin + 1));
+    let denominator = a - 2.0 * b + c;
+    if denominator.abs() < f32::EPSILON {
+        return bin as f32;
+    }
+    bin as f32 + (0.5 * (a - c) / denominator).clamp(-0.5, 0.5)
+}
+
+/// Build the notch comb for a mains frequency, reusing the storage of `sections`
+fn notch_comb(sample_rate: f32, mains_hz: f32, config: &HumConfig, mut sections: Vec<Biquad>) -> BiquadCascade {
+    sections.clear();
+    sections.extend(
+        (1..=config.harmonics)
+            .map(|harmonic| mains_hz * harmonic as f32)
+            .take_while(|&hz| hz < sample_rate / 2.0)
+            .map(|hz| Biquad::notch(sample_rate, hz, hz / config.notch_width_hz)),
+    );
+    BiquadCascade::new(sections, 1.0)
+}
+
+/// Remove mains hum from a complete recording
+///
+/// # Arguments
+/// * `samples` - Input audio samples
+/// * `sample_rate` - Sample rate of the audio in Hz
+/// * `config` - Hum removal configuration
+///
+/// # Returns
+/// Filtered samples with the same length as input. The input is returned unchanged
+/// if auto-detection finds no hum.
+pub fn remove_hum(samples: &[f32], sample_rate: f32, config: HumConfig) -> Vec<f32> {
+    HumRemover::new(sample_rate, config).process(samples)
+}
+
+/// Notch comb on the mains frequency and its harmonics
+///
+/// With a fixed `mains_hz` the notches are active from the first sample. With
+/// auto-detection, batch processing analyses the whole signal first, while
+/// streaming passes audio through unchanged until `detection_seconds` of audio have
+/// been analysed and then switches the notches on. The analysis buffer and FFT are
+/// allocated up front, so detection does not allocate in the audio callback.
+pub struct HumRemover {
+    config: HumConfig,
+    sample_rate: f32,
+    detection_len: usize,
+    analysis: Vec<f32>,
+    /// Detector for streaming auto-detection, sized to `detection_len`
+    detector: Option<MainsDetector>,
+    /// Storage for the notch sections, reserved so switching the notches on does not allocate
+    spare_sections: Vec<Biquad>,
+    detection_done: bool,
+    mains_hz: Option<f32>,
+    notches: Option<BiquadCascade>,
+}
+
+impl HumRemover {
+    /// Create a hum remover for the given sample rate
+    pub fn new(sample_rate: f32, config: HumConfig) -> Self {
+        let detection_len = ((config.detection_seconds.max(MIN_DETECTION_SECONDS) * sample_rate) as usize).max(1);
+        let auto_detect = config.mains_hz.is_none();
+        let mut remover = Self {
+            config,
+            sample_rate,
+            detection_len,
+            analysis: Vec::with_capacity(if auto_detect { detection_len } else { 0 }),
+            detector: auto_detect.then(|| MainsDetector::new(detection_len, sample_rate)),
+            spare_sections: Vec::new(),
+            detection_done: false,
+            mains_hz: None,
+            notches: None,
+        };
+        remover.reset();
+        remover
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &HumConfig {
+        &self.config
+    }
+
+    /// Mains frequency the notches are tuned to, once known
+    pub fn mains_hz(&self) -> Option<f32> {
+        self.mains_hz
+    }
+
+    /// Whether detection has finished (successfully or not)
+    pub fn detection_done(&self) -> bool {
+        self.detection_done
+    }
+
+    fn set_mains(&mut self, mains_hz: Option<f32>) {
+        self.mains_hz = mains_hz;
+        let sections = std::mem::take(&mut self.spare_sections);
+        self.notches = mains_hz.map(|hz| notch_comb(self.sample_rate, hz, &self.config, sections));
+        self.detection_done = true;
+    }
+}
+
+impl AudioProcessor for HumRemover {
+    fn name(&self) -> &'static str {
+        "hum_removal"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        // Samples that went into the analysis buffer pass through unfiltered
+        let mut first_filtered = 0;
+        if !self.detection_done {
+            first_filtered = (self.detection_len - self.analysis.len()).min(samples.len());
+            self.analysis.extend_from_slice(&samples[..first_filtered]);
+            if self.analysis.len() >= self.detection_len {
+                let detected = self.detector.as_mut().and_then(|detector| detector.detect(&self.analysis));
+                self.set_mains(detected);
+                self.analysis.clear();
+            }
+        }
+        if let Some(notches) = self.notches.as_mut() {
+            for sample in samples[first_filtered..].iter_mut() {
+                *sample = notches.process_sample(*sample);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        match self.config.mains_hz {
+            Some(hz) => self.set_mains(Some(hz)),
+            None => {
+                self.mains_hz = None;
+                self.notches = None;
+                self.spare_sections = Vec::with_capacity(self.config.harmonics);
+                self.detection_done = false;
+                self.analysis.clear();
+            }
+        }
+    }
+
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.reset();
+        if self.config.mains_hz.is_none() {
+            let detected = detect_mains_frequency(samples, self.sample_rate);
+            self.set_mains(detected);
+        }
+        let mut output = samples.to_vec();
+        self.process_block(&mut output);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sung tone with vibrato plus hum on `mains_hz` and three harmonics
+    fn voice_with_hum(mains_hz: f32, sample_rate: f32, seconds: f32) -> (Vec<f32>, Vec<f32>) {
+        let len = (sample_rate * seconds) as usize;
+        let mut phase = 0.0f32;
+        let voice: Vec<f32> = (0..len)
+            .map(|i| {
+                let t = i as f32 / sample_rate;
+                let freq = 220.0 * (1.0 + 0.01 * (2.0 * PI * 5.5 * t).sin());
+                phase += 2.0 * PI * freq / sample_rate;
+                0.3 * phase.sin() + 0.1 * (2.0 * phase).sin()
+            })
+            .collect();
+        let noisy = voice
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let t = i as f32 / sample_rate;
+                v + [(1.0, 0.1), (2.0, 0.05), (3.0, 0.08), (4.0, 0.03)]
+                    .iter()
+                    .map(|(h, a)| a * (2.0 * PI * mains_hz * h * t).sin())
+                    .sum::<f32>()
+            })
+            .collect();
+        (voice, noisy)
+    }
+
+    /// Amplitude of the component at `freq` (single-bin DFT)
+    fn tone_amplitude(signal: &[f32], freq: f32, sample_rate: f32) -> f32 {
+        let (re, im) = signal.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, x)| {
+            let w = 2.0 * PI * freq * i as f32 / sample_rate;
+            (re + x * w.cos(), im - x * w.sin())
+        });
+        2.0 * (re * re + im * im).sqrt() / signal.len() as f32
+    }
+
+    #[test]
+    fn test_detects_50_and_60_hz() {
+        for (mains, sample_rate) in [(50.0, 16000.0), (60.0, 44100.0), (50.0, 48000.0)] {
+            let (_, noisy) = voice_with_hum(mains, sample_rate, 2.0);
+            let detected = detect_mains_frequency(&noisy, sample_rate).expect("hum not detected");
+            assert!((detected - mains).abs() < 0.2, "Expected {}, got {}", mains, detected);
+        }
+    }
+
+    #[test]
+    fn test_detects_drifted_mains() {
+        let (_, noisy) = voice_with_hum(50.6, 16000.0, 2.0);
+        let detected = detect_mains_frequency(&noisy, 16000.0).unwrap();
+        assert!((detected - 50.6).abs() < 0.15, "Detected {}", detected);
+    }
+
+    #[test]
+    fn test_no_hum_in_clean_voice() {
+        let (voice, _) = voice_with_hum(50.0, 16000.0, 2.0);
+        assert_eq!(detect_mains_frequency(&voice, 16000.0), None);
+
+        // A steady tone at 100 Hz is a low voice, not hum
+        let low_voice: Vec<f32> = (0..32000)
+            .map(|i| {
+                let phase = 2.0 * PI * 100.0 * i as f32 / 16000.0;
+                0.3 * phase.sin() + 0.2 * (2.0 * phase).sin() + 0.1 * (3.0 * phase).sin()
+            })
+            .collect();
+        assert_eq!(detect_mains_frequency(&low_voice, 16000.0), None);
+    }
+
+    #[test]
+    fn test_too_short_for_detection() {
+        let (_, noisy) = voice_with_hum(60.0, 16000.0, 0.2);
+        assert_eq!(detect_mains_frequency(&noisy, 16000.0), None);
+    }
+
+    #[test]
+    fn test_batch_removal_attenuates_hum_and_keeps_voice() {
+        let sample_rate = 16000.0;
+        let (voice, noisy) = voice_with_hum(60.0, sample_rate, 2.0);
+        let mut remover = HumRemover::new(sample_rate, HumConfig::default());
+        let cleaned = remover.process(&noisy);
+        assert!((remover.mains_hz().unwrap() - 60.0).abs() < 0.2);
+
+        let settled = 4000..;
+        for harmonic in [60.0, 120.0, 180.0, 240.0] {
+            let before = tone_amplitude(&noisy[settled.clone()], harmonic, sample_rate);
+            let after = tone_amplitude(&cleaned[settled.clone()], harmonic, sample_rate);
+            assert!(after < before * 0.1, "{} Hz: {} -> {}", harmonic, before, after);
+        }
+        let voice_before = tone_amplitude(&voice[settled.clone()], 440.0, sample_rate);
+        let voice_after = tone_amplitude(&cleaned[settled], 440.0, sample_rate);
+        assert!((voice_after / voice_before - 1.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_streaming_switches_on_after_detection() {
+        let sample_rate = 16000.0;
+        let (_, noisy) = voice_with_hum(50.0, sample_rate, 3.0);
+        let mut remover = HumRemover::new(sample_rate, HumConfig::default());
+        let mut streamed = noisy.clone();
+        for chunk in streamed.chunks_mut(512) {
+            remover.process_block(chunk);
+        }
+        assert!(remover.detection_done());
+        assert!((remover.mains_hz().unwrap() - 50.0).abs() < 0.2);
+
+        // Untouched during the first second, hum removed afterwards
+        assert_eq!(&streamed[..16000], &noisy[..16000]);
+        let tail = 24000..;
+        assert!(
+            tone_amplitude(&streamed[tail.clone()], 150.0, sample_rate)
+                < 0.1 * tone_amplitude(&noisy[tail], 150.0, sample_rate)
+        );
+    }
+
+    #[test]
+    fn test_fixed_mains_streaming_matches_batch() {
+        let sample_rate = 16000.0;
+        let (_, noisy) = voice_with_hum(60.0, sample_rate, 1.0);
+        let config = HumConfig { mains_hz: Some(60.0), ..Default::default() };
+        let batch = remove_hum(&noisy, sample_rate, config);
+
+        let mut remover = HumRemover::new(sample_rate, config);
+        let mut streamed = noisy.clone();
+        for chunk in streamed.chunks_mut(300) {
+            remover.process_block(chunk);
+        }
+        assert_eq!(batch, streamed);
+    }
+
+    #[test]
+    fn test_notches_stay_below_nyquist() {
+        let remover = HumRemover::new(
+            1000.0,
+            HumConfig { mains_hz: Some(60.0), harmonics: 20, ..Default::default() },
+        );
+        assert_eq!(remover.notches.as_ref().unwrap().len(), 8);
+    }
+}
@@ -0,0 +1,482 @@
+//! Spectrogram Image Export
+//!
+//! Renders a [`Spectrogram`] as an image for visual debugging of cleaning and pitch
+//! detection results. Magnitudes are drawn in dB through a colormap on a linear or
+//! logarithmic frequency axis. A pitch track and a cleaning-gate mask can be overlaid.
+//!
+//! Images are written as binary PPM/PGM or as PNG. The PNG encoder stores the pixel
+//! data uncompressed, so no image or compression crates are required.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use crate::types::Spectrogram;
+
+/// Colour used for pitch-track overlays by default (cyan)
+pub const PITCH_TRACK_COLOR: [u8; 3] = [0, 255, 255];
+
+/// Colour that gated (attenuated) regions are tinted towards (blue)
+const GATE_TINT: [u8; 3] = [40, 90, 255];
+
+/// Vertical frequency axis of a rendered spectrogram
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrequencyScale {
+    #[default]
+    Linear,
+    /// Equal height per octave, which suits pitch
+    Log,
+}
+
+/// Mapping from normalized level (0 = floor, 1 = peak) to colour
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Colormap {
+    Grayscale,
+    /// Black through purple and orange to pale yellow
+    #[default]
+    Inferno,
+}
+
+impl Colormap {
+    /// Colour for a level in `[0, 1]`
+    pub fn color(&self, level: f32) -> [u8; 3] {
+        let level = if level.is_finite() { level.clamp(0.0, 1.0) } else { 0.0 };
+        match self {
+            Colormap::Grayscale => {
+                let v = (level * 255.0).round() as u8;
+                [v, v, v]
+            }
+            Colormap::Inferno => {
+                const STOPS: [[f32; 3]; 6] = [
+                    [0.0, 0.0, 4.0],
+                    [40.0, 11.0, 84.0],
+                    [101.0, 21.0, 110.0],
+                    [188.0, 55.0, 84.0],
+                    [249.0, 142.0, 9.0],
+                    [252.0, 255.0, 164.0],
+                ];
+                let position = level * (STOPS.len() - 1) as f32;
+                let i = Ord::min(position as usize, STOPS.len() - 2);
+                let t = position - i as f32;
+                let channel = |c: usize| (STOPS[i][c] + t * (STOPS[i + 1][c] - STOPS[i][c])).round() as u8;
+                [channel(0), channel(1), channel(2)]
+            }
+        }
+    }
+}
+
+/// Configuration for rendering a spectrogram
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpectrogramImageConfig {
+    /// Image height in pixels; the width is one pixel per frame. Default: 256
+    pub height: usize,
+    /// Frequency axis. Default: Linear
+    pub frequency_scale: FrequencyScale,
+    /// Lowest frequency shown in Hz (at least 1 Hz on a log axis).
+    /// Default: 50.0 Hz
+    pub min_hz: f32,
+    /// Highest frequency shown in Hz, or `None` for the Nyquist frequency.
+    /// Default: None
+    pub max_hz: Option<f32>,
+    /// Levels this far below the loudest bin are drawn as the floor colour.
+    /// Default: 80.0 dB
+    pub dynamic_range_db: f32,
+    /// Default: Inferno
+    pub colormap: Colormap,
+}
+
+impl Default for SpectrogramImageConfig {
+    fn default() -> Self {
+        Self {
+            height: 256,
+            frequency_scale: FrequencyScale::Linear,
+            min_hz: 50.0,
+            max_hz: None,
+            dynamic_range_db: 80.0,
+            colormap: Colormap::Inferno,
+        }
+    }
+}
+
+/// A rendered spectrogram: RGB pixels, row 0 at the top (highest frequency)
+#[derive(Debug, Clone)]
+pub struct SpectrogramImage {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major RGB pixels
+    pub pixels: Vec<[u8; 3]>,
+    axis: Axis,
+}
+
+/// Geometry needed to place frequencies and times on the image
+#[derive(Debug, Clone, Copy)]
+struct Axis {
+    sample_rate: f32,
+    window_size: usize,
+    step_size: usize,
+    min_hz: f32,
+    max_hz: f32,
+    scale: FrequencyScale,
+}
+
+impl Axis {
+    /// Frequency at the centre of row `y`
+    fn row_frequency(&self, y: usize, height: usize) -> f32 {
+        let position = if height > 1 { (height - 1 - y) as f32 / (height - 1) as f32 } else { 0.0 };
+        match self.scale {
+            FrequencyScale::Linear => self.min_hz + position * (self.max_hz - self.min_hz),
+            FrequencyScale::Log => self.min_hz * (self.max_hz / self.min_hz).powf(position),
+        }
+    }
+
+    /// Row of a frequency, or `None` if it lies outside the axis
+    fn frequency_row(&self, hz: f32, height: usize) -> Option<usize> {
+        if !(self.min_hz..=self.max_hz).contains(&hz) || height == 0 {
+            return None;
+        }
+        let position = match self.scale {
+            FrequencyScale::Linear => (hz - self.min_hz) / (self.max_hz - self.min_hz),
+            FrequencyScale::Log => (hz / self.min_hz).ln() / (self.max_hz / self.min_hz).ln(),
+        };
+        let from_bottom = (position * (height - 1) as f32).round() as usize;
+        Some(height - 1 - Ord::min(from_bottom, height - 1))
+    }
+
+    /// Fractional FFT bin of a frequency
+    fn frequency_bin(&self, hz: f32) -> f32 {
+        hz * self.window_size as f32 / self.sample_rate
+    }
+
+    /// Fractional frame (column) whose window is centred at `seconds`
+    fn time_column(&self, seconds: f32) -> f32 {
+        (seconds * self.sample_rate - self.window_size as f32 / 2.0) / self.step_size.max(1) as f32
+    }
+}
+
+impl SpectrogramImage {
+    /// Render a spectrogram computed from audio at `sample_rate`
+    pub fn render(spectrogram: &Spectrogram, sample_rate: f32, config: &SpectrogramImageConfig) -> Self {
+        let nyquist = sample_rate / 2.0;
+        let max_hz = config.max_hz.unwrap_or(nyquist).min(nyquist);
+        let floor_hz = match config.frequency_scale {
+            FrequencyScale::Linear => 0.0,
+            FrequencyScale::Log => 1.0,
+        };
+        let min_hz = config.min_hz.clamp(floor_hz, max_hz);
+        let axis = Axis {
+            sample_rate,
+            window_size: spectrogram.window_size,
+            step_size: spectrogram.step_size,
+            min_hz,
+            max_hz,
+            scale: config.frequency_scale,
+        };
+
+        let width = spectrogram.n_time_steps();
+        let height = config.height.max(1);
+        let peak = spectrogram
+            .spectra
+            .iter()
+            .flatten()
+            .fold(0.0f32, |peak, &m| if m.is_finite() { peak.max(m) } else { peak });
+        let range_db = config.dynamic_range_db.max(1.0);
+        let bins: Vec<f32> = (0..height).map(|y| axis.frequency_bin(axis.row_frequency(y, height))).collect();
+
+        let mut pixels = vec![config.colormap.color(0.0); width * height];
+        for (x, spectrum) in spectrogram.spectra.iter().enumerate() {
+            for (y, &bin) in bins.iter().enumerate() {
+                let magnitude = interpolate_bin(spectrum, bin);
+                let level = if peak > 0.0 && magnitude > 0.0 {
+                    1.0 + 20.0 * (magnitude / peak).log10() / range_db
+                } else {
+                    0.0
+                };
+                pixels[y * width + x] = config.colormap.color(level);
+            }
+        }
+
+        Self { width, height, pixels, axis }
+    }
+
+    /// Overlay a pitch track of `(time in seconds, frequency in Hz)` points. Unvoiced
+    /// points (`None`) break the line.
+    pub fn overlay_pitch_track(&mut self, track: &[(f32, Option<f32>)], color: [u8; 3]) {
+        let mut previous: Option<(i64, i64)> = None;
+        for &(time, frequency) in track {
+            let point = frequency.and_then(|hz| {
+                let row = self.axis.frequency_row(hz, self.height)?;
+                Some((self.axis.time_column(time).round() as i64, row as i64))
+            });
+            match (previous, point) {
+                (Some(from), Some(to)) => self.draw_line(from, to, color),
+                (None, Some(to)) => self.draw_line(to, to, color),
+                _ => {}
+            }
+            previous = point;
+        }
+    }
+
+    /// Tint regions removed by a cleaning gate. `mask` holds one gain per frame and
+    /// bin, in the same layout as `Spectrogram::spectra`: 1 means passed unchanged
+    /// and 0 fully gated. A mask with one value per frame gates the whole column.
+    pub fn overlay_gate_mask(&mut self, mask: &[Vec<f32>]) {
+        for (x, gains) in mask.iter().enumerate().take(self.width) {
+            if gains.is_empty() {
+                continue;
+            }
+            for y in 0..self.height {
+                let gain = if gains.len() == 1 {
+                    gains[0]
+                } else {
+                    let bin = self.axis.frequency_bin(self.axis.row_frequency(y, self.height)).round() as usize;
+                    gains[Ord::min(bin, gains.len() - 1)]
+                };
+                let alpha = 0.7 * (1.0 - gain.clamp(0.0, 1.0));
+                let pixel = &mut self.pixels[y * self.width + x];
+                for (channel, tint) in pixel.iter_mut().zip(GATE_TINT) {
+                    *channel = (*channel as f32 * (1.0 - alpha) + tint as f32 * alpha).round() as u8;
+                }
+            }
+        }
+    }
+
+    /// Encode as binary PPM (P6)
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.pixels.iter().flatten());
+        bytes
+    }
+
+    /// Encode as binary PGM (P5), converting colours to luminance
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut bytes = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.pixels.iter().map(|&p| luminance(p)));
+        bytes
+    }
+
+    /// Encode as an 8-bit RGB PNG
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(self.height * (1 + 3 * self.width));
+        for row in self.pixels.chunks(self.width.max(1)).take(self.height) {
+            raw.push(0); // filter type: none
+            raw.extend(row.iter().flatten());
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit RGB, no interlace
+
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_png_chunk(&mut bytes, b"IHDR", &header);
+        write_png_chunk(&mut bytes, b"IDAT", &zlib_stored(&raw));
+        write_png_chunk(&mut bytes, b"IEND", &[]);
+        bytes
+    }
+
+    /// Write the image, choosing the format from the extension (`png`, `ppm` or `pgm`)
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+        let bytes = match extension.as_deref() {
+            Some("png") => self.to_png(),
+            Some("ppm") => self.to_ppm(),
+            Some("pgm") => self.to_pgm(),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported image format: {}", path.display()),
+                ))
+            }
+        };
+        std::fs::write(path, bytes)
+    }
+
+    /// Draw a line between two points, clipping to the image
+    fn draw_line(&mut self, from: (i64, i64), to: (i64, i64), color: [u8; 3]) {
+        let steps = Ord::max((to.0 - from.0).abs(), (to.1 - from.1).abs()).max(1);
+        for step in 0..=steps {
+            let x = from.0 + (to.0 - from.0) * step / steps;
+            let y = from.1 + (to.1 - from.1) * step / steps;
+            if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+                self.pixels[y as usize * self.width + x as usize] = color;
+            }
+        }
+    }
+}
+
+/// Linearly interpolated magnitude at a fractional bin
+fn interpolate_bin(spectrum: &[f32], bin: f32) -> f32 {
+    if spectrum.is_empty() || bin < 0.0 {
+        return 0.0;
+    }
+    let i = bin.floor() as usize;
+    if i + 1 >= spectrum.len() {
+        return spectrum[spectrum.len() - 1];
+    }
+    let t = bin - i as f32;
+    spectrum[i] * (1.0 - t) + spectrum[i + 1] * t
+}
+
+fn luminance([r, g, b]: [u8; 3]) -> u8 {
+    (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32).round() as u8
+}
+
+fn write_png_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = bytes.len();
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(data);
+    let crc = crc32(&bytes[start..]);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// A zlib stream using uncompressed (stored) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 65535;
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(u8::from(blocks.peek().is_none()));
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SpectrogramConfig;
+    use std::f32::consts::PI;
+
+    fn tone_spectrogram(frequency: f32, sample_rate: f32) -> Spectrogram {
+        let signal: Vec<f32> = (0..8192).map(|i| (2.0 * PI * frequency * i as f32 / sample_rate).sin()).collect();
+        Spectrogram::from_waveform(&signal, SpectrogramConfig { window_size: 512, step_size: 256 })
+    }
+
+    fn brightest_row(image: &SpectrogramImage, x: usize) -> usize {
+        (0..image.height)
+            .max_by_key(|&y| luminance(image.pixels[y * image.width + x]))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render_dimensions_and_tone_row() {
+        let sample_rate = 8000.0;
+        let spectrogram = tone_spectrogram(1000.0, sample_rate);
+        let config = SpectrogramImageConfig { height: 101, min_hz: 0.0, max_hz: Some(4000.0), ..Default::default() };
+        let image = SpectrogramImage::render(&spectrogram, sample_rate, &config);
+        assert_eq!(image.width, spectrogram.n_time_steps());
+        assert_eq!(image.pixels.len(), image.width * image.height);
+        // 1000 Hz sits a quarter of the way up a 0-4000 Hz axis
+        assert_eq!(brightest_row(&image, image.width / 2), 75);
+    }
+
+    #[test]
+    fn test_log_axis_gives_octaves_equal_height() {
+        let sample_rate = 8000.0;
+        let config = SpectrogramImageConfig {
+            height: 61,
+            frequency_scale: FrequencyScale::Log,
+            min_hz: 62.5,
+            max_hz: Some(4000.0),
+            ..Default::default()
+        };
+        // Six octaves over 60 rows: 250 Hz is two octaves up, 10 rows per octave
+        let image = SpectrogramImage::render(&tone_spectrogram(250.0, sample_rate), sample_rate, &config);
+        assert!((brightest_row(&image, image.width / 2) as i64 - 40).abs() <= 1);
+    }
+
+    #[test]
+    fn test_pitch_track_overlay() {
+        let sample_rate = 8000.0;
+        let spectrogram = tone_spectrogram(500.0, sample_rate);
+        let config = SpectrogramImageConfig { height: 81, min_hz: 0.0, ..Default::default() };
+        let mut image = SpectrogramImage::render(&spectrogram, sample_rate, &config);
+        let track: Vec<(f32, Option<f32>)> = (0..=40)
+            .map(|i| (i as f32 * 0.025, if i == 20 { None } else { Some(2000.0) }))
+            .collect();
+        image.overlay_pitch_track(&track, PITCH_TRACK_COLOR);
+        // 2000 Hz is half way up the 0-4000 Hz axis
+        let row = &image.pixels[40 * image.width..41 * image.width];
+        assert!(row.iter().filter(|&&p| p == PITCH_TRACK_COLOR).count() > image.width / 2);
+        assert!(image.pixels[..40 * image.width].iter().all(|&p| p != PITCH_TRACK_COLOR));
+    }
+
+    #[test]
+    fn test_gate_mask_tints_gated_columns() {
+        let sample_rate = 8000.0;
+        let spectrogram = tone_spectrogram(500.0, sample_rate);
+        let config = SpectrogramImageConfig { colormap: Colormap::Grayscale, ..Default::default() };
+        let original = SpectrogramImage::render(&spectrogram, sample_rate, &config);
+        let mut image = original.clone();
+        let mask: Vec<Vec<f32>> = (0..image.width).map(|x| vec![if x < 5 { 0.0 } else { 1.0 }]).collect();
+        image.overlay_gate_mask(&mask);
+        assert!(image.pixels[0][2] > image.pixels[0][0]);
+        assert_eq!(image.pixels[10], original.pixels[10]);
+    }
+
+    #[test]
+    fn test_encodings() {
+        let image = SpectrogramImage::render(&tone_spectrogram(440.0, 8000.0), 8000.0, &Default::default());
+        let ppm = image.to_ppm();
+        let header = format!("P6\n{} {}\n255\n", image.width, image.height);
+        assert!(ppm.starts_with(header.as_bytes()));
+        assert_eq!(ppm.len(), header.len() + 3 * image.width * image.height);
+        let pgm = image.to_pgm();
+        assert_eq!(pgm.len(), header.len() + image.width * image.height);
+
+        let png = image.to_png();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), image.width as u32);
+        assert!(png.ends_with(&[0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82]));
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn test_save_rejects_unknown_format() {
+        let image = SpectrogramImage::render(&tone_spectrogram(440.0, 8000.0), 8000.0, &Default::default());
+        let stem = format!("audio_cleaning_spectrogram_test_{}", std::process::id());
+        let path = std::env::temp_dir().join(stem).with_extension("bmp");
+        assert_eq!(image.save(&path).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let path = path.with_extension("png");
+        image.save(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), image.to_png());
+        std::fs::remove_file(path).unwrap();
+    }
+}
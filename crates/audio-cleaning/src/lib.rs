@@ -2,8 +2,19 @@
 //! 
 //! This crate provides audio preprocessing and cleaning operations including:
 //! - Bandpass filtering for vocal frequency range isolation
-//! - Spectral gating for noise reduction
+//! - Noise reduction by spectral gating, spectral subtraction or Wiener filtering
+//! - Mains hum detection and removal
+//! - Declipping and click/pop repair
 //! - Background noise spectrum estimation
+//! - Pitch-adaptive harmonic comb enhancement, with an optional coarse pitch pass
+//! - Harmonic/percussive separation to suppress drums and strums in backing tracks
+//! - NaN-safe spectral peak picking with prominence, spacing and sub-bin interpolation
+//! - Constant-Q and mel spectrograms with note-aligned frequency axes
+//! - A complex STFT with an exact inverse for time-frequency processing
+//! - Cleaning quality reports (SNR, noise floor, flatness, per-band residual noise)
+//! - Spectrogram image export (PNG/PPM/PGM) with pitch-track and gate-mask overlays
+//! - Composable, serializable cleaning pipelines (DC removal, high-pass,
+//!   pre-emphasis, hum removal, bandpass, spectral gating, normalization, AGC)
 //!
 //! These operations are designed to improve audio quality for pitch detection
 //! and other audio analysis tasks.
@@ -12,7 +23,22 @@ mod util;
 pub mod types;
 pub mod cleaning;
 pub mod processing;
+pub mod peaks;
 pub mod spectral_gating;
+pub mod processor;
+pub mod filters;
+pub mod dynamics;
+pub mod pipeline;
+pub mod hum;
+pub mod noise_reduction;
+pub mod repair;
+pub mod cqt;
+pub mod mel;
+pub mod stft;
+pub mod image_export;
+pub mod report;
+pub mod hpss;
+pub mod harmonic_enhancement;
 
 pub use types::{Spectrum, Spectrogram, SpectrogramConfig};
 pub use cleaning::{
@@ -20,4 +46,28 @@ pub use cleaning::{
     estimate_noise_spectrum, create_noise_profile, DEFAULT_VOCAL_LOW_HZ, DEFAULT_VOCAL_HIGH_HZ,
 };
 pub use processing::find_peak;
+pub use peaks::{find_peaks, Peak, PeakConfig, PeakInterpolation};
 pub use spectral_gating::{SpectralGate, SpectralGateConfig};
+pub use processor::AudioProcessor;
+pub use noise_reduction::{
+    NoiseReducer, SpectralSubtraction, SpectralSubtractionConfig, WienerConfig, WienerFilter,
+};
+pub use repair::{
+    declip, detect_clicks, detect_clipping, repair_clicks, ClickRepairConfig, ClickRepairer,
+    DeclipConfig, Declipper, RepairStats,
+};
+pub use cqt::{
+    hz_to_midi, midi_to_hz, ConstantQConfig, ConstantQSpectrogram, ConstantQTransform, A4_HZ,
+};
+pub use mel::{hz_to_mel, mel_to_hz, MelConfig, MelFilterbank, MelSpectrogram};
+pub use stft::{Stft, StftConfig, WindowType};
+pub use image_export::{
+    Colormap, FrequencyScale, SpectrogramImage, SpectrogramImageConfig, PITCH_TRACK_COLOR,
+};
+pub use hpss::{harmonic_component, separate, HarmonicPercussive, HpssConfig};
+pub use harmonic_enhancement::{
+    coarse_pitch_track, enhance_harmonics, enhance_harmonics_two_pass, HarmonicEnhancementConfig,
+};
+pub use report::{BandResidual, CleaningReport, ReportConfig, SignalMetrics};
+pub use hum::{detect_mains_frequency, remove_hum, HumConfig, HumRemover};
+pub use pipeline::{CleaningPipeline, CleaningPipelineConfig, PipelineError, StageConfig};
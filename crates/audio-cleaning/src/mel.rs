@@ -0,0 +1,226 @@
+//! Mel Spectrogram
+//!
+//! The mel scale is roughly linear below 1 kHz and logarithmic above, following the
+//! ear's frequency resolution. A mel spectrogram sums a Hann-windowed STFT through
+//! overlapping triangular filters spaced evenly in mel. The result is a compact,
+//! perceptually weighted view of the spectrum for displays and for analysis
+//! features.
+
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use crate::cqt::hz_to_midi;
+use crate::types::Spectrum;
+
+/// Convert Hz to mel (HTK formula)
+pub fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+/// Convert mel to Hz (HTK formula)
+pub fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Configuration for mel spectrograms
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MelConfig {
+    /// Number of mel bands. Default: 64
+    pub n_mels: usize,
+    /// Lower edge of the lowest band in Hz. Default: 50.0 Hz
+    pub min_hz: f32,
+    /// Upper edge of the highest band in Hz, or `None` for the Nyquist frequency.
+    /// Default: None
+    pub max_hz: Option<f32>,
+    /// Number of samples per FFT window. Default: 1024
+    pub window_size: usize,
+    /// Number of samples between successive frames. Default: 256
+    pub step_size: usize,
+}
+
+impl Default for MelConfig {
+    fn default() -> Self {
+        Self {
+            n_mels: 64,
+            min_hz: 50.0,
+            max_hz: None,
+            window_size: 1024,
+            step_size: 256,
+        }
+    }
+}
+
+/// Triangular filters mapping an FFT magnitude spectrum onto mel bands
+#[derive(Debug, Clone)]
+pub struct MelFilterbank {
+    /// Band edges in Hz: band `m` rises from `edges[m]`, peaks at `edges[m + 1]` and
+    /// falls to `edges[m + 2]`
+    edges: Vec<f32>,
+    /// Per band: index of the first FFT bin and the weights from there on
+    weights: Vec<(usize, Vec<f32>)>,
+}
+
+impl MelFilterbank {
+    /// Build the filterbank for FFTs of `window_size` samples at `sample_rate`
+    pub fn new(sample_rate: f32, config: &MelConfig) -> Self {
+        let nyquist = sample_rate / 2.0;
+        let max_hz = config.max_hz.unwrap_or(nyquist).min(nyquist);
+        let min_hz = config.min_hz.clamp(0.0, max_hz);
+        let (min_mel, max_mel) = (hz_to_mel(min_hz), hz_to_mel(max_hz));
+        let n_edges = config.n_mels + 2;
+        let edges: Vec<f32> = (0..n_edges)
+            .map(|i| mel_to_hz(min_mel + (max_mel - min_mel) * i as f32 / (n_edges - 1) as f32))
+            .collect();
+
+        let bin_hz = sample_rate / config.window_size.max(1) as f32;
+        let n_fft_bins = config.window_size / 2;
+        let weights = edges
+            .windows(3)
+            .map(|band| {
+                let (low, centre, high) = (band[0], band[1], band[2]);
+                let first = ((low / bin_hz).ceil() as usize).min(n_fft_bins);
+                let last = ((high / bin_hz).floor() as usize + 1).min(n_fft_bins);
+                let weights = (first..last.max(first))
+                    .map(|j| {
+                        let hz = j as f32 * bin_hz;
+                        if hz <= centre {
+                            (hz - low) / (centre - low).max(f32::EPSILON)
+                        } else {
+                            (high - hz) / (high - centre).max(f32::EPSILON)
+                        }
+                        .max(0.0)
+                    })
+                    .collect();
+                (first, weights)
+            })
+            .collect();
+
+        Self { edges, weights }
+    }
+
+    /// Number of mel bands
+    pub fn n_mels(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Centre frequency of band `m` in Hz
+    pub fn band_frequency(&self, m: usize) -> f32 {
+        self.edges[m + 1]
+    }
+
+    /// Apply the filterbank to a magnitude spectrum (positive frequencies only)
+    pub fn apply(&self, magnitudes: &[f32]) -> Vec<f32> {
+        self.weights
+            .iter()
+            .map(|(first, weights)| {
+                weights.iter().zip(magnitudes.iter().skip(*first)).map(|(w, m)| w * m).sum()
+            })
+            .collect()
+    }
+}
+
+/// Mel band magnitudes per frame, together with their frequency and time axes
+#[derive(Debug, Clone)]
+pub struct MelSpectrogram {
+    /// One vector of band magnitudes per frame, lowest band first
+    pub frames: Vec<Vec<f32>>,
+    pub sample_rate: f32,
+    pub config: MelConfig,
+    filterbank: MelFilterbank,
+}
+
+impl MelSpectrogram {
+    /// Compute the mel spectrogram of a signal. Frame `t` is centred on sample
+    /// `t * step_size`; the signal is zero-padded at both ends.
+    pub fn from_waveform(signal: &[f32], sample_rate: f32, config: MelConfig) -> Self {
+        let filterbank = MelFilterbank::new(sample_rate, &config);
+        let window_size = config.window_size.max(2);
+        let step = config.step_size.max(1);
+        let half = window_size / 2;
+        let window: Vec<f32> = (0..window_size)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / window_size as f32).cos())
+            .collect();
+        let n_frames = if signal.is_empty() { 0 } else { signal.len() / step + 1 };
+        let mut frame = vec![0.0f32; window_size];
+        let frames = (0..n_frames)
+            .map(|t| {
+                for (i, x) in frame.iter_mut().enumerate() {
+                    let sample = (t * step + i).checked_sub(half).and_then(|n| signal.get(n));
+                    *x = sample.copied().unwrap_or(0.0) * window[i];
+                }
+                filterbank.apply(&Spectrum::from_waveform(&frame).magnitudes())
+            })
+            .collect();
+        Self { frames, sample_rate, config, filterbank }
+    }
+
+    /// Number of frames
+    pub fn n_time_steps(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Number of mel bands in each frame
+    pub fn n_bins(&self) -> usize {
+        self.filterbank.n_mels()
+    }
+
+    /// Centre frequency of band `m` in Hz
+    pub fn bin_frequency(&self, m: usize) -> f32 {
+        self.filterbank.band_frequency(m)
+    }
+
+    /// Fractional MIDI note of the centre of band `m`, for A4 = `reference_hz`
+    pub fn bin_midi(&self, m: usize, reference_hz: f32) -> f32 {
+        hz_to_midi(self.bin_frequency(m), reference_hz)
+    }
+
+    /// Time of the centre of frame `t` in seconds
+    pub fn frame_time(&self, t: usize) -> f32 {
+        (t * self.config.step_size) as f32 / self.sample_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cqt::A4_HZ;
+
+    #[test]
+    fn test_mel_conversions() {
+        assert!((hz_to_mel(1000.0) - 1000.0).abs() < 0.5);
+        assert!(hz_to_mel(0.0).abs() < 1e-6);
+        for hz in [50.0, 440.0, 3000.0] {
+            assert!((mel_to_hz(hz_to_mel(hz)) - hz).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_bands_are_evenly_spaced_in_mel() {
+        let config = MelConfig { n_mels: 20, min_hz: 0.0, max_hz: Some(4000.0), ..Default::default() };
+        let filterbank = MelFilterbank::new(16000.0, &config);
+        assert_eq!(filterbank.n_mels(), 20);
+        let step = hz_to_mel(filterbank.band_frequency(1)) - hz_to_mel(filterbank.band_frequency(0));
+        for m in 1..20 {
+            let spacing = hz_to_mel(filterbank.band_frequency(m)) - hz_to_mel(filterbank.band_frequency(m - 1));
+            assert!((spacing - step).abs() < 1e-2);
+        }
+        assert!(filterbank.band_frequency(19) < 4000.0);
+    }
+
+    #[test]
+    fn test_sine_peaks_in_its_band() {
+        let sample_rate = 16000.0;
+        let signal: Vec<f32> = (0..8000)
+            .map(|i| (2.0 * PI * 440.0 * i as f32 / sample_rate).sin())
+            .collect();
+        let spectrogram = MelSpectrogram::from_waveform(&signal, sample_rate, MelConfig::default());
+        assert_eq!(spectrogram.n_time_steps(), 8000 / 256 + 1);
+        assert_eq!(spectrogram.n_bins(), 64);
+        let frame = &spectrogram.frames[spectrogram.n_time_steps() / 2];
+        let peak = (0..frame.len()).max_by(|&a, &b| frame[a].total_cmp(&frame[b])).unwrap();
+        // The neighbouring band centres bracket the tone
+        assert!(spectrogram.bin_frequency(peak - 1) < 440.0 && spectrogram.bin_frequency(peak + 1) > 440.0);
+        assert!((spectrogram.bin_midi(peak, A4_HZ) - 69.0).abs() < 1.5);
+        assert!((spectrogram.frame_time(10) - 0.16).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,539 @@
+//! Noise Reduction Algorithms
+//!
+//! This module defines the [`NoiseReducer`] trait shared by all noise-profile based
+//! reducers, so they can be swapped per pipeline profile and compared on the same input:
+//! - [`SpectralGate`](crate::SpectralGate): soft gating against the noise floor
+//! - [`SpectralSubtraction`]: power spectral subtraction with oversubtraction and a
+//!   spectral floor
+//! - [`WienerFilter`]: Wiener filter with decision-directed a-priori SNR estimation,
+//!   which produces much less musical noise on broadband hiss
+//!
+//! Spectral subtraction and the Wiener filter work on overlapping frames (square-root
+//! Hann window, 50% overlap), which reconstruct the input exactly when no bin is
+//! attenuated. When streaming, the frames continue across chunks, so the output lags
+//! the input by one frame.
+//!
+//! # Examples
+//!
+//! ```
+//! use audio_cleaning::noise_reduction::{
+//!     compare_noise_reducers, NoiseReducer, SpectralSubtraction, WienerFilter,
+//! };
+//! use audio_cleaning::{SpectralGate, Spectrum};
+//!
+//! let noise = Spectrum::from_waveform(&vec![0.01; 4096]);
+//! let clean: Vec<f32> = (0..4096).map(|i| (i as f32 * 0.05).sin()).collect();
+//! let noisy: Vec<f32> = clean.iter().enumerate().map(|(i, x)| x + 0.01 * ((i * 7919) % 13) as f32 / 13.0).collect();
+//!
+//! let mut reducers: Vec<Box<dyn NoiseReducer>> = vec![
+//!     Box::new(SpectralGate::with_defaults(noise.clone())),
+//!     Box::new(SpectralSubtraction::with_defaults(noise.clone())),
+//!     Box::new(WienerFilter::with_defaults(noise)),
+//! ];
+//! for (name, improvement_db) in compare_noise_reducers(&clean, &noisy, &mut reducers) {
+//!     println!("{}: {:+.1} dB", name, improvement_db);
+//! }
+//! ```
+
+use rustfft::num_complex::Complex32;
+use serde::{Deserialize, Serialize};
+use crate::processor::AudioProcessor;
+use crate::stft::StreamingStft;
+use crate::util::db_to_linear;
+use crate::Spectrum;
+
+/// A noise reduction stage driven by a reference noise profile
+///
+/// Batch and streaming processing come from [`AudioProcessor`]; this trait adds
+/// access to the noise profile so that reducers can be swapped freely.
+pub trait NoiseReducer: AudioProcessor {
+    /// Get a reference to the current noise profile
+    fn noise_profile(&self) -> &Spectrum;
+
+    /// Replace the noise profile, e.g. when the recording environment changes
+    fn update_noise_profile(&mut self, noise_spectrum: Spectrum);
+}
+
+/// Signal-to-noise ratio of `estimate` with respect to the `reference` signal, in dB
+///
+/// Everything in `estimate` that differs from `reference` counts as noise.
+/// Returns `f32::INFINITY` if the two are identical.
+pub fn snr_db(reference: &[f32], estimate: &[f32]) -> f32 {
+    let signal: f32 = reference.iter().map(|x| x * x).sum();
+    let noise: f32 = reference.iter().zip(estimate).map(|(r, e)| (r - e) * (r - e)).sum();
+    if noise <= 0.0 {
+        return f32::INFINITY;
+    }
+    10.0 * (signal / noise).log10()
+}
+
+/// Improvement in SNR (dB) achieved by turning `noisy` into `processed`
+///
+/// # Arguments
+/// * `clean` - The signal without noise
+/// * `noisy` - The signal with noise, as fed to the reducer
+/// * `processed` - The reducer output
+pub fn snr_improvement_db(clean: &[f32], noisy: &[f32], processed: &[f32]) -> f32 {
+    snr_db(clean, processed) - snr_db(clean, noisy)
+}
+
+/// Run several reducers on the same input and report their SNR improvement
+///
+/// Each reducer processes `noisy` in batch mode.
+///
+/// # Returns
+/// Pairs of reducer name and SNR improvement in dB, in the order given
+pub fn compare_noise_reducers(
+    clean: &[f32],
+    noisy: &[f32],
+    reducers: &mut [Box<dyn NoiseReducer>],
+) -> Vec<(&'static str, f32)> {
+    reducers
+        .iter_mut()
+        .map(|reducer| {
+            let processed = reducer.process(noisy);
+            (reducer.name(), snr_improvement_db(clean, noisy, &processed))
+        })
+        .collect()
+}
+
+/// Overlap-add framing shared by the frame-based reducers
+///
+/// Wraps a [`StreamingStft`] with the expected noise power of each bin of a windowed
+/// frame and the per-bin buffers, so streaming carries frames across chunks and
+/// does not allocate.
+struct FrameProcessor {
+    stft: StreamingStft,
+    noise_power: Vec<f32>,
+    power: Vec<f32>,
+    gain: Vec<f32>,
+}
+
+impl FrameProcessor {
+    fn new(frame_size: usize, noise_spectrum: &Spectrum) -> Self {
+        let stft = StreamingStft::new(frame_size);
+        let frame_size = stft.frame_size();
+        let noise_power = Self::noise_power(frame_size, noise_spectrum, stft.window_energy());
+        let n_bins = frame_size / 2 + 1;
+        Self {
+            stft,
+            noise_power,
+            power: vec![0.0; n_bins],
+            gain: vec![1.0; n_bins],
+        }
+    }
+
+    /// Expected power of each frame bin (0..=frame_size/2) due to the noise
+    ///
+    /// The noise profile may have any length; its per-sample power spectral density
+    /// is averaged over the band covered by each frame bin.
+    fn noise_power(frame_size: usize, noise_spectrum: &Spectrum, window_energy: f32) -> Vec<f32> {
+        let n_noise = noise_spectrum.complex.len();
+        if n_noise == 0 {
+            return vec![0.0; frame_size / 2 + 1];
+        }
+        let bins_per_frame_bin = n_noise as f32 / frame_size as f32;
+        (0..=frame_size / 2)
+            .map(|bin| {
+                let center = bin as f32 * bins_per_frame_bin;
+                let start = (center - 0.5 * bins_per_frame_bin).round().max(0.0) as usize;
+                let end = ((center + 0.5 * bins_per_frame_bin).round() as usize)
+                    .max(start + 1)
+                    .min(n_noise / 2 + 1);
+                let start = start.min(end - 1);
+                let psd = noise_spectrum.complex[start..end].iter().map(|c| c.norm_sqr()).sum::<f32>()
+                    / (end - start) as f32
+                    / n_noise as f32;
+                psd * window_energy
+            })
+            .collect()
+    }
+
+    /// Filter the next chunk of a stream in place. `gains` receives the power of each
+    /// bin of the current frame and the expected noise power, and fills in the gain
+    /// per bin. The output lags the input by [`FrameProcessor::latency`] samples.
+    fn process_block(&mut self, samples: &mut [f32], mut gains: impl FnMut(&[f32], &[f32], &mut [f32])) {
+        let Self { stft, noise_power, power, gain } = self;
+        stft.process_block(samples, |spectrum| {
+            for (p, c) in power.iter_mut().zip(spectrum.iter()) {
+                *p = c.norm_sqr();
+            }
+            gains(power, noise_power, gain);
+            apply_symmetric_gain(spectrum, gain);
+        });
+    }
+
+    /// Filter a complete signal, aligned with the input
+    fn process(&mut self, samples: &[f32], mut gains: impl FnMut(&[f32], &[f32], &mut [f32])) -> Vec<f32> {
+        let Self { stft, noise_power, power, gain } = self;
+        stft.process(samples, |spectrum| {
+            for (p, c) in power.iter_mut().zip(spectrum.iter()) {
+                *p = c.norm_sqr();
+            }
+            gains(power, noise_power, gain);
+            apply_symmetric_gain(spectrum, gain);
+        })
+    }
+
+    fn latency(&self) -> usize {
+        self.stft.latency()
+    }
+
+    fn reset(&mut self) {
+        self.stft.reset();
+    }
+}
+
+/// Scale the positive-frequency bins and their mirror images by `gain`
+fn apply_symmetric_gain(spectrum: &mut [Complex32], gain: &[f32]) {
+    let n = spectrum.len();
+    for (bin, &g) in gain.iter().enumerate() {
+        spectrum[bin] *= g;
+        if bin != 0 && n - bin != bin {
+            spectrum[n - bin] *= g;
+        }
+    }
+}
+
+/// Configuration for spectral subtraction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpectralSubtractionConfig {
+    /// Frame length in samples. Default: 1024
+    pub frame_size: usize,
+    /// Multiple of the noise power that is subtracted. Values above 1 remove more
+    /// noise and residual "musical" tones at the cost of some signal. Default: 2.0
+    pub oversubtraction: f32,
+    /// Fraction of the noise power that is always kept, masking musical noise.
+    /// Default: 0.02
+    pub spectral_floor: f32,
+}
+
+impl Default for SpectralSubtractionConfig {
+    fn default() -> Self {
+        Self {
+            frame_size: 1024,
+            oversubtraction: 2.0,
+            spectral_floor: 0.02,
+        }
+    }
+}
+
+/// Power spectral subtraction (Berouti et al.)
+///
+/// For each bin the estimated clean power is `|Y|² - oversubtraction * N²`, but never
+/// less than `spectral_floor * N²`. Streaming carries the overlapping frames across
+/// chunks, so the output lags the input by [`latency`](SpectralSubtraction::latency)
+/// samples; batch processing is aligned with the input.
+pub struct SpectralSubtraction {
+    noise_spectrum: Spectrum,
+    frames: FrameProcessor,
+    config: SpectralSubtractionConfig,
+}
+
+impl SpectralSubtraction {
+    /// Create a spectral subtractor with the given noise profile and configuration
+    pub fn new(noise_spectrum: Spectrum, config: SpectralSubtractionConfig) -> Self {
+        let frames = FrameProcessor::new(config.frame_size, &noise_spectrum);
+        Self { noise_spectrum, frames, config }
+    }
+
+    /// Create a spectral subtractor with default configuration
+    pub fn with_defaults(noise_spectrum: Spectrum) -> Self {
+        Self::new(noise_spectrum, SpectralSubtractionConfig::default())
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &SpectralSubtractionConfig {
+        &self.config
+    }
+
+    /// Delay of the streaming output in samples (one frame)
+    pub fn latency(&self) -> usize {
+        self.frames.latency()
+    }
+
+    /// Per-bin gain of spectral subtraction
+    fn gains(config: &SpectralSubtractionConfig) -> impl FnMut(&[f32], &[f32], &mut [f32]) {
+        let oversubtraction = config.oversubtraction;
+        let floor = config.spectral_floor;
+        move |power, noise, gain| {
+            for ((g, &p), &n) in gain.iter_mut().zip(power).zip(noise) {
+                *g = if p > 0.0 {
+                    ((p - oversubtraction * n).max(floor * n) / p).min(1.0).sqrt()
+                } else {
+                    1.0
+                };
+            }
+        }
+    }
+}
+
+impl AudioProcessor for SpectralSubtraction {
+    fn name(&self) -> &'static str {
+        "spectral_subtraction"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        self.frames.process_block(samples, Self::gains(&self.config));
+    }
+
+    fn reset(&mut self) {
+        self.frames.reset();
+    }
+
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.frames.process(samples, Self::gains(&self.config))
+    }
+}
+
+impl NoiseReducer for SpectralSubtraction {
+    fn noise_profile(&self) -> &Spectrum {
+        &self.noise_spectrum
+    }
+
+    fn update_noise_profile(&mut self, noise_spectrum: Spectrum) {
+        self.frames = FrameProcessor::new(self.config.frame_size, &noise_spectrum);
+        self.noise_spectrum = noise_spectrum;
+    }
+}
+
+/// Configuration for the Wiener filter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WienerConfig {
+    /// Frame length in samples. Default: 1024
+    pub frame_size: usize,
+    /// Weight of the previous frame in the decision-directed a-priori SNR estimate
+    /// (0.0-1.0). Higher values give smoother gains and less musical noise. Default: 0.98
+    pub smoothing: f32,
+    /// Lowest gain applied to any bin, in dB. Default: -25.0 dB
+    pub min_gain_db: f32,
+}
+
+impl Default for WienerConfig {
+    fn default() -> Self {
+        Self {
+            frame_size: 1024,
+            smoothing: 0.98,
+            min_gain_db: -25.0,
+        }
+    }
+}
+
+/// Wiener filter with decision-directed a-priori SNR estimation (Ephraim-Malah)
+///
+/// The a-priori SNR of each bin blends the clean power estimated for the previous
+/// frame with the current excess over the noise floor, and the gain is
+/// `snr / (1 + snr)`. The frames and the previous frame's estimate are carried
+/// between streaming chunks and cleared by [`reset`](AudioProcessor::reset); the
+/// streaming output lags the input by [`latency`](WienerFilter::latency) samples.
+pub struct WienerFilter {
+    noise_spectrum: Spectrum,
+    frames: FrameProcessor,
+    config: WienerConfig,
+    previous_clean_power: Vec<f32>,
+}
+
+impl WienerFilter {
+    /// Create a Wiener filter with the given noise profile and configuration
+    pub fn new(noise_spectrum: Spectrum, config: WienerConfig) -> Self {
+        let frames = FrameProcessor::new(config.frame_size, &noise_spectrum);
+        let previous_clean_power = vec![0.0; frames.noise_power.len()];
+        Self {
+            noise_spectrum,
+            frames,
+            config,
+            previous_clean_power,
+        }
+    }
+
+    /// Create a Wiener filter with default configuration
+    pub fn with_defaults(noise_spectrum: Spectrum) -> Self {
+        Self::new(noise_spectrum, WienerConfig::default())
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &WienerConfig {
+        &self.config
+    }
+
+    /// Delay of the streaming output in samples (one frame)
+    pub fn latency(&self) -> usize {
+        self.frames.latency()
+    }
+
+    /// Per-bin gain of the Wiener filter, updating the clean power estimate
+    fn gains<'a>(config: &WienerConfig, previous: &'a mut [f32]) -> impl FnMut(&[f32], &[f32], &mut [f32]) + 'a {
+        let smoothing = config.smoothing;
+        let min_gain = db_to_linear(config.min_gain_db);
+        move |power, noise, gain| {
+            for (((g, &p), &n), prev) in gain.iter_mut().zip(power).zip(noise).zip(previous.iter_mut()) {
+                if n <= 0.0 {
+                    *g = 1.0;
+                    *prev = p;
+                    continue;
+                }
+                let posterior_snr = p / n;
+                let prior_snr = smoothing * *prev / n + (1.0 - smoothing) * (posterior_snr - 1.0).max(0.0);
+                *g = (prior_snr / (1.0 + prior_snr)).max(min_gain);
+                *prev = *g * *g * p;
+            }
+        }
+    }
+}
+
+impl AudioProcessor for WienerFilter {
+    fn name(&self) -> &'static str {
+        "wiener"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        self.frames.process_block(samples, Self::gains(&self.config, &mut self.previous_clean_power));
+    }
+
+    fn reset(&mut self) {
+        self.frames.reset();
+        self.previous_clean_power.fill(0.0);
+    }
+
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.previous_clean_power.fill(0.0);
+        self.frames.process(samples, Self::gains(&self.config, &mut self.previous_clean_power))
+    }
+}
+
+impl NoiseReducer for WienerFilter {
+    fn noise_profile(&self) -> &Spectrum {
+        &self.noise_spectrum
+    }
+
+    fn update_noise_profile(&mut self, noise_spectrum: Spectrum) {
+        self.frames = FrameProcessor::new(self.config.frame_size, &noise_spectrum);
+        self.noise_spectrum = noise_spectrum;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SpectralGate;
+    use sound_synth::white_noise;
+    use std::f32::consts::PI;
+
+    /// A sung vowel: 220 Hz with a few harmonics
+    fn voice(len: usize, sample_rate: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let phase = 2.0 * PI * 220.0 * i as f32 / sample_rate;
+                0.3 * phase.sin() + 0.15 * (2.0 * phase).sin() + 0.08 * (3.0 * phase).sin()
+            })
+            .collect()
+    }
+
+    fn noisy_voice() -> (Vec<f32>, Vec<f32>, Spectrum) {
+        let len = 16384;
+        let clean = voice(len, 16000.0);
+        let noisy = clean.iter().zip(white_noise(len, 0.1, 7)).map(|(c, n)| c + n).collect();
+        // Profile recorded separately, from a different stretch of the same hiss
+        let profile = Spectrum::from_waveform(&white_noise(len, 0.1, 12345));
+        (clean, noisy, profile)
+    }
+
+    #[test]
+    fn test_snr_db() {
+        let reference = [1.0, -1.0, 1.0, -1.0];
+        let estimate = [1.1, -1.1, 1.1, -1.1];
+        assert!((snr_db(&reference, &estimate) - 20.0).abs() < 1e-3);
+        assert_eq!(snr_db(&reference, &reference), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_frames_reconstruct_input_without_noise() {
+        let signal = voice(5000, 16000.0);
+        let silent_profile = Spectrum::from_waveform(&[0.0; 1024]);
+        let mut subtraction = SpectralSubtraction::with_defaults(silent_profile.clone());
+        let mut wiener = WienerFilter::with_defaults(silent_profile);
+        for output in [subtraction.process(&signal), wiener.process(&signal)] {
+            assert_eq!(output.len(), signal.len());
+            for (a, b) in signal.iter().zip(output.iter()) {
+                assert!((a - b).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_noise_only_is_attenuated() {
+        let profile = Spectrum::from_waveform(&white_noise(8192, 0.1, 99));
+        let noise = white_noise(8192, 0.1, 3);
+        let energy = |s: &[f32]| s.iter().map(|x| x * x).sum::<f32>();
+        let mut subtraction = SpectralSubtraction::with_defaults(profile.clone());
+        let mut wiener = WienerFilter::with_defaults(profile);
+        let subtracted = energy(&subtraction.process(&noise)) / energy(&noise);
+        let filtered = energy(&wiener.process(&noise)) / energy(&noise);
+        // Bins whose noise power happens to exceed twice its mean survive subtraction
+        // as musical noise (about e^-2 of the energy); the Wiener filter suppresses them
+        assert!(subtracted < 0.2, "Spectral subtraction residual: {}", subtracted);
+        assert!(filtered < 0.02, "Wiener residual: {}", filtered);
+    }
+
+    #[test]
+    fn test_reducers_improve_snr() {
+        let (clean, noisy, profile) = noisy_voice();
+        let mut reducers: Vec<Box<dyn NoiseReducer>> = vec![
+            Box::new(SpectralGate::with_defaults(profile.clone())),
+            Box::new(SpectralSubtraction::with_defaults(profile.clone())),
+            Box::new(WienerFilter::with_defaults(profile)),
+        ];
+        let results = compare_noise_reducers(&clean, &noisy, &mut reducers);
+        let names: Vec<&str> = results.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["spectral_gate", "spectral_subtraction", "wiener"]);
+
+        let improvement = |name: &str| results.iter().find(|(n, _)| *n == name).unwrap().1;
+        assert!(improvement("spectral_subtraction") > 5.0, "{:?}", results);
+        assert!(improvement("wiener") > 5.0, "{:?}", results);
+        assert!(improvement("wiener") > improvement("spectral_gate"), "{:?}", results);
+    }
+
+    #[test]
+    fn test_streaming_chunks_improve_snr() {
+        let (clean, noisy, profile) = noisy_voice();
+        let mut wiener = WienerFilter::with_defaults(profile);
+        let mut streamed = noisy.clone();
+        for chunk in streamed.chunks_mut(2048) {
+            wiener.process_block(chunk);
+        }
+        let aligned = &streamed[wiener.latency()..];
+        let len = aligned.len();
+        assert!(snr_improvement_db(&clean[..len], &noisy[..len], aligned) > 5.0);
+    }
+
+    #[test]
+    fn test_streaming_matches_batch_for_any_chunking() {
+        let (_, noisy, profile) = noisy_voice();
+        let mut reducers: Vec<Box<dyn NoiseReducer>> = vec![
+            Box::new(SpectralSubtraction::with_defaults(profile.clone())),
+            Box::new(WienerFilter::with_defaults(profile)),
+        ];
+        for reducer in reducers.iter_mut() {
+            let batch = reducer.process(&noisy);
+            reducer.reset();
+            let mut streamed = noisy.clone();
+            // Odd chunk sizes that do not line up with the frames
+            for chunk in streamed.chunks_mut(333) {
+                reducer.process_block(chunk);
+            }
+            let latency = 1024;
+            for (a, b) in batch.iter().zip(&streamed[latency..]) {
+                assert!((a - b).abs() < 1e-4, "{}: {} vs {}", reducer.name(), a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_update_noise_profile() {
+        let mut wiener = WienerFilter::with_defaults(Spectrum::from_waveform(&[0.01; 64]));
+        NoiseReducer::update_noise_profile(&mut wiener, Spectrum::from_waveform(&[0.02; 128]));
+        assert_eq!(NoiseReducer::noise_profile(&wiener).n, 128);
+    }
+}
@@ -0,0 +1,303 @@
+//! Spectral Peak Picking
+//!
+//! [`find_peaks`] returns the local maxima of a magnitude spectrum (or any other
+//! sequence), strongest first. Peaks can be limited by height, prominence, minimum
+//! distance and count. Each peak carries a sub-bin position and value, interpolated
+//! from its two neighbours, so harmonic frequencies can be measured more precisely
+//! than the bin spacing.
+//!
+//! Non-finite values (NaN, ±∞) are never reported as peaks and never panic; they are
+//! treated as the lowest finite value of the input.
+//!
+//! # Example
+//!
+//! ```
+//! use audio_cleaning::peaks::{find_peaks, PeakConfig};
+//!
+//! let magnitudes = vec![0.0, 1.0, 4.0, 2.0, 0.5, 3.0, 0.2, f32::NAN];
+//! let config = PeakConfig { max_peaks: Some(1), ..Default::default() };
+//! let peaks = find_peaks(&magnitudes, &config);
+//! assert_eq!(peaks[0].index, 2);
+//! assert!(peaks[0].position > 2.0 && peaks[0].position < 2.5);
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// How the position and value of a peak are refined from its neighbours
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeakInterpolation {
+    /// Integer bin position and the sampled value
+    None,
+    /// Fit a parabola through the peak and its neighbours
+    #[default]
+    Quadratic,
+    /// Fit a parabola through the logarithms of the values. Exact for Gaussian
+    /// peaks, and close to exact for the main lobe of Hann- or Gaussian-windowed
+    /// sinusoids. Falls back to quadratic when a value is not positive.
+    Gaussian,
+}
+
+/// Configuration for peak picking
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeakConfig {
+    /// Maximum number of peaks to return, or `None` for all. Default: None
+    pub max_peaks: Option<usize>,
+    /// Minimum peak value, or `None` for no limit. Default: None
+    pub min_height: Option<f32>,
+    /// Minimum prominence: how far the peak rises above the higher of the lowest
+    /// points separating it from higher values on each side. Default: 0.0
+    pub min_prominence: f32,
+    /// Minimum distance in samples between returned peaks. Within this distance,
+    /// only the highest peak is kept. Default: 1 (no restriction)
+    pub min_distance: usize,
+    /// Sub-bin refinement. Default: Quadratic
+    pub interpolation: PeakInterpolation,
+}
+
+impl Default for PeakConfig {
+    fn default() -> Self {
+        Self {
+            max_peaks: None,
+            min_height: None,
+            min_prominence: 0.0,
+            min_distance: 1,
+            interpolation: PeakInterpolation::Quadratic,
+        }
+    }
+}
+
+/// A local maximum of a sequence
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Peak {
+    /// Index of the sampled maximum (the middle of a flat top)
+    pub index: usize,
+    /// Interpolated position, within half a sample of `index`
+    pub position: f32,
+    /// Interpolated peak value
+    pub value: f32,
+    /// Height above the higher of the two bases (see [`PeakConfig::min_prominence`])
+    pub prominence: f32,
+}
+
+impl Peak {
+    /// Frequency of the interpolated position in Hz, for a spectrum of an FFT of
+    /// `n_fft` samples taken at `sample_rate`
+    pub fn frequency(&self, n_fft: usize, sample_rate: f32) -> f32 {
+        self.position * sample_rate / n_fft as f32
+    }
+}
+
+/// Find the peaks of `signal`, highest first
+///
+/// Peaks are local maxima that are not at either end of the signal. Flat tops count
+/// once, at their middle. The height and prominence limits are applied first, then
+/// the minimum distance (keeping the highest), then `max_peaks`.
+pub fn find_peaks(signal: &[f32], config: &PeakConfig) -> Vec<Peak> {
+    let values = sanitize(signal);
+    let mut peaks: Vec<Peak> = local_maxima(signal, &values)
+        .into_iter()
+        .filter(|&i| config.min_height.is_none_or(|min| values[i] >= min))
+        .filter_map(|i| {
+            let prominence = prominence(&values, i);
+            (prominence >= config.min_prominence).then(|| {
+                let (position, value) = interpolate(signal, i, config.interpolation);
+                Peak { index: i, position, value, prominence }
+            })
+        })
+        .collect();
+    peaks.sort_by(|a, b| values[b.index].total_cmp(&values[a.index]).then(a.index.cmp(&b.index)));
+
+    if config.min_distance > 1 {
+        let mut kept: Vec<Peak> = Vec::with_capacity(peaks.len());
+        for peak in peaks {
+            if kept.iter().all(|k| k.index.abs_diff(peak.index) >= config.min_distance) {
+                kept.push(peak);
+            }
+        }
+        peaks = kept;
+    }
+    if let Some(max_peaks) = config.max_peaks {
+        peaks.truncate(max_peaks);
+    }
+    peaks
+}
+
+/// Replace non-finite values by the lowest finite value (or zero if there is none)
+fn sanitize(signal: &[f32]) -> Vec<f32> {
+    let floor = signal.iter().copied().filter(|v| v.is_finite()).fold(f32::INFINITY, f32::min);
+    let floor = if floor.is_finite() { floor } else { 0.0 };
+    signal.iter().map(|&v| if v.is_finite() { v } else { floor }).collect()
+}
+
+/// Indices of the finite local maxima, taking the middle of flat tops
+fn local_maxima(signal: &[f32], values: &[f32]) -> Vec<usize> {
+    let mut maxima = Vec::new();
+    let mut i = 1;
+    while i + 1 < values.len() {
+        if values[i - 1] < values[i] && signal[i].is_finite() {
+            // Extend over a flat top
+            let mut end = i;
+            while end + 1 < values.len() && values[end + 1] == values[i] && signal[end + 1].is_finite() {
+                end += 1;
+            }
+            if end + 1 < values.len() && values[end + 1] < values[i] {
+                maxima.push((i + end) / 2);
+            }
+            i = end + 1;
+        } else {
+            i += 1;
+        }
+    }
+    maxima
+}
+
+/// Height of the peak at `i` above the higher of its two bases. Each base is the
+/// lowest value between the peak and the nearest higher value on that side (or the
+/// end of the signal).
+fn prominence(values: &[f32], i: usize) -> f32 {
+    let height = values[i];
+    let base = |range: &mut dyn Iterator<Item = usize>| {
+        range.map(|j| values[j]).take_while(|&v| v <= height).fold(height, f32::min)
+    };
+    let left = base(&mut (0..i).rev());
+    let right = base(&mut (i + 1..values.len()));
+    height - left.max(right)
+}
+
+/// Interpolated position and value of the peak at `i`
+fn interpolate(signal: &[f32], i: usize, interpolation: PeakInterpolation) -> (f32, f32) {
+    let (a, b, c) = (signal[i - 1], signal[i], signal[i + 1]);
+    let unrefined = (i as f32, b);
+    if interpolation == PeakInterpolation::None || !(a.is_finite() && c.is_finite()) {
+        return unrefined;
+    }
+    if interpolation == PeakInterpolation::Gaussian && a > 0.0 && b > 0.0 && c > 0.0 {
+        return match parabolic_vertex(a.ln(), b.ln(), c.ln()) {
+            Some((offset, log_value)) => (i as f32 + offset, log_value.exp()),
+            None => unrefined,
+        };
+    }
+    match parabolic_vertex(a, b, c) {
+        Some((offset, value)) => (i as f32 + offset, value),
+        None => unrefined,
+    }
+}
+
+/// Offset (within ±0.5) and value of the vertex of the parabola through
+/// `(-1, a)`, `(0, b)` and `(1, c)`
+fn parabolic_vertex(a: f32, b: f32, c: f32) -> Option<(f32, f32)> {
+    let curvature = a - 2.0 * b + c;
+    if curvature >= 0.0 {
+        return None;
+    }
+    let offset = (0.5 * (a - c) / curvature).clamp(-0.5, 0.5);
+    Some((offset, b - 0.25 * (a - c) * offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stft::WindowType;
+    use crate::types::Spectrum;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_nan_safe() {
+        let signal = [f32::NAN, 1.0, f32::NAN, 2.0, f32::INFINITY, 0.5, f32::NEG_INFINITY];
+        let peaks = find_peaks(&signal, &PeakConfig::default());
+        let indices: Vec<usize> = peaks.iter().map(|p| p.index).collect();
+        assert_eq!(indices, vec![3, 1]);
+        assert!(peaks.iter().all(|p| p.value.is_finite() && p.prominence.is_finite()));
+        assert!(find_peaks(&[f32::NAN; 5], &PeakConfig::default()).is_empty());
+        assert!(find_peaks(&[], &PeakConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_top_n_highest_first() {
+        let signal = [0.0, 3.0, 0.0, 5.0, 0.0, 1.0, 0.0, 4.0, 0.0];
+        let config = PeakConfig { max_peaks: Some(3), interpolation: PeakInterpolation::None, ..Default::default() };
+        let peaks = find_peaks(&signal, &config);
+        let indices: Vec<usize> = peaks.iter().map(|p| p.index).collect();
+        assert_eq!(indices, vec![3, 7, 1]);
+        assert_eq!(peaks[0].value, 5.0);
+    }
+
+    #[test]
+    fn test_flat_top_and_edges() {
+        // Rising edge at the start and end are not peaks; the flat top counts once
+        let signal = [5.0, 1.0, 2.0, 2.0, 2.0, 1.0, 6.0];
+        let peaks = find_peaks(&signal, &PeakConfig::default());
+        assert_eq!(peaks.len(), 1);
+        assert_eq!(peaks[0].index, 3);
+    }
+
+    #[test]
+    fn test_prominence() {
+        // A small ripple on the flank of a large peak
+        let signal = [0.0, 1.0, 2.0, 2.2, 2.1, 6.0, 3.0, 0.0];
+        let peaks = find_peaks(&signal, &PeakConfig::default());
+        assert_eq!(peaks.len(), 2);
+        assert!((peaks[0].prominence - 6.0).abs() < 1e-6);
+        assert!((peaks[1].prominence - 0.1).abs() < 1e-5);
+
+        let config = PeakConfig { min_prominence: 0.5, ..Default::default() };
+        let peaks = find_peaks(&signal, &config);
+        assert_eq!(peaks.len(), 1);
+        assert_eq!(peaks[0].index, 5);
+    }
+
+    #[test]
+    fn test_min_distance_keeps_highest() {
+        let signal = [0.0, 2.0, 0.0, 3.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.5, 0.0];
+        let config = PeakConfig { min_distance: 3, ..Default::default() };
+        let indices: Vec<usize> = find_peaks(&signal, &config).iter().map(|p| p.index).collect();
+        assert_eq!(indices, vec![3, 9]);
+    }
+
+    #[test]
+    fn test_interpolation_is_exact_for_its_model() {
+        let parabola: Vec<f32> = (0..7).map(|i| 10.0 - (i as f32 - 3.3).powi(2)).collect();
+        let peak = find_peaks(&parabola, &PeakConfig::default())[0];
+        assert!((peak.position - 3.3).abs() < 1e-4);
+        assert!((peak.value - 10.0).abs() < 1e-4);
+
+        let gaussian: Vec<f32> = (0..7).map(|i| 2.0 * (-(i as f32 - 2.8).powi(2) / 2.0).exp()).collect();
+        let config = PeakConfig { interpolation: PeakInterpolation::Gaussian, ..Default::default() };
+        let peak = find_peaks(&gaussian, &config)[0];
+        assert!((peak.position - 2.8).abs() < 1e-4);
+        assert!((peak.value - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_sub_bin_harmonic_frequencies() {
+        // Harmonics of 147.3 Hz fall between bins (7.8 Hz apart)
+        let sample_rate = 8000.0;
+        let n_fft = 1024;
+        let f0 = 147.3;
+        let window = WindowType::Hann.coefficients(n_fft);
+        let signal: Vec<f32> = (0..n_fft)
+            .map(|i| {
+                let t = i as f32 / sample_rate;
+                let tone: f32 = (1..=4).map(|h| (2.0 * PI * f0 * h as f32 * t).sin() / h as f32).sum();
+                tone * window[i]
+            })
+            .collect();
+        let magnitudes = Spectrum::from_waveform(&signal).magnitudes();
+        let config = PeakConfig {
+            max_peaks: Some(4),
+            min_prominence: 1.0,
+            interpolation: PeakInterpolation::Gaussian,
+            ..Default::default()
+        };
+        let mut peaks = find_peaks(&magnitudes, &config);
+        assert_eq!(peaks.len(), 4);
+        peaks.sort_by(|a, b| a.position.total_cmp(&b.position));
+        for (h, peak) in peaks.iter().enumerate() {
+            let expected = f0 * (h + 1) as f32;
+            let error = (peak.frequency(n_fft, sample_rate) - expected).abs();
+            assert!(error < 0.5, "harmonic {}: error {} Hz", h + 1, error);
+        }
+    }
+}
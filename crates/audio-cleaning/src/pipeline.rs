@@ -0,0 +1,813 @@
+//! Composable Cleaning Pipeline
+//!
+//! This module chains cleaning stages in a user-defined order. A pipeline is
+//! described by a [`CleaningPipelineConfig`], a plain list of stage parameters that
+//! can be saved to and loaded from TOML or JSON, and turned into a runnable
+//! [`CleaningPipeline`] for a given sample rate.
+//!
+//! # Examples
+//!
+//! ```
+//! use audio_cleaning::pipeline::{CleaningPipeline, CleaningPipelineConfig};
+//! use audio_cleaning::filters::{DcRemovalConfig, HighPassConfig, BandpassConfig};
+//! use audio_cleaning::dynamics::NormalizeConfig;
+//! use audio_cleaning::AudioProcessor;
+//!
+//! let config = CleaningPipelineConfig::new()
+//!     .with_stage(DcRemovalConfig::default())
+//!     .with_stage(HighPassConfig::default())
+//!     .with_stage(BandpassConfig { low_hz: 100.0, high_hz: 1000.0, ..Default::default() })
+//!     .with_stage(NormalizeConfig::default());
+//!
+//! // Round-trip through TOML
+//! let toml = config.to_toml().unwrap();
+//! let config = CleaningPipelineConfig::from_toml(&toml).unwrap();
+//!
+//! // Batch processing
+//! let mut pipeline = CleaningPipeline::from_config(&config, 44100.0, None).unwrap();
+//! let cleaned = pipeline.process(&vec![0.1; 1024]);
+//!
+//! // Streaming processing: state carries over between chunks
+//! pipeline.reset();
+//! let mut chunk = vec![0.1; 256];
+//! pipeline.process_block(&mut chunk);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use audio_utils::MonoAudio;
+use crate::processor::AudioProcessor;
+use crate::filters::{
+    BandpassConfig, BandpassFilter, DcBlocker, DcRemovalConfig, DeEmphasis, DeEmphasisConfig, HighPassConfig,
+    HighPassFilter, PreEmphasis, PreEmphasisConfig,
+};
+use crate::hum::{HumConfig, HumRemover};
+use crate::repair::{ClickRepairConfig, ClickRepairer, DeclipConfig, Declipper};
+use crate::dynamics::{AgcConfig, AutomaticGainControl, NormalizeConfig, Normalizer};
+use crate::spectral_gating::{SpectralGate, SpectralGateConfig};
+use crate::noise_reduction::{SpectralSubtraction, SpectralSubtractionConfig, WienerConfig, WienerFilter};
+use crate::Spectrum;
+
+/// Error type for building and (de)serializing cleaning pipelines
+#[derive(Debug)]
+pub enum PipelineError {
+    /// A noise-reduction stage (spectral gate, spectral subtraction or Wiener filter)
+    /// was requested but no noise profile was supplied
+    MissingNoiseProfile,
+    /// A stage parameter is out of range
+    InvalidParameter(String),
+    /// The configuration could not be serialized or parsed
+    Serialization(String),
+}
+
+impl std::fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineError::MissingNoiseProfile => write!(f, "Noise-reduction stage requires a noise profile"),
+            PipelineError::InvalidParameter(msg) => write!(f, "Invalid parameter: {}", msg),
+            PipelineError::Serialization(msg) => write!(f, "Serialization error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+/// Parameters of a single pipeline stage
+///
+/// Serialized with a `stage` tag, e.g. in TOML:
+///
+/// ```toml
+/// [[stages]]
+/// stage = "high_pass"
+/// cutoff_hz = 60.0
+/// q = 0.7071
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum StageConfig {
+    DcRemoval(DcRemovalConfig),
+    HighPass(HighPassConfig),
+    PreEmphasis(PreEmphasisConfig),
+    DeEmphasis(DeEmphasisConfig),
+    Declip(DeclipConfig),
+    ClickRepair(ClickRepairConfig),
+    HumRemoval(HumConfig),
+    Bandpass(BandpassConfig),
+    SpectralGate(SpectralGateConfig),
+    SpectralSubtraction(SpectralSubtractionConfig),
+    Wiener(WienerConfig),
+    Normalize(NormalizeConfig),
+    Agc(AgcConfig),
+}
+
+impl StageConfig {
+    /// Check that the stage parameters make sense at the given sample rate
+    pub fn validate(&self, sample_rate: f32) -> Result<(), PipelineError> {
+        let nyquist = sample_rate / 2.0;
+        let check_frequency = |name: &str, hz: f32| {
+            if hz > 0.0 && hz < nyquist {
+                Ok(())
+            } else {
+                Err(PipelineError::InvalidParameter(format!(
+                    "{} must be between 0 and {} Hz, got {}", name, nyquist, hz
+                )))
+            }
+        };
+
+        match self {
+            StageConfig::DcRemoval(config) => check_frequency("dc_removal.cutoff_hz", config.cutoff_hz),
+            StageConfig::HighPass(config) => {
+                check_frequency("high_pass.cutoff_hz", config.cutoff_hz)?;
+                if config.q <= 0.0 {
+                    return Err(PipelineError::InvalidParameter(format!("high_pass.q must be positive, got {}", config.q)));
+                }
+                Ok(())
+            }
+            StageConfig::PreEmphasis(config) => {
+                if !(0.0..1.0).contains(&config.coefficient) {
+                    return Err(PipelineError::InvalidParameter(format!(
+                        "pre_emphasis.coefficient must be in [0, 1), got {}", config.coefficient
+                    )));
+                }
+                Ok(())
+            }
+            StageConfig::DeEmphasis(config) => {
+                if !(0.0..1.0).contains(&config.coefficient) {
+                    return Err(PipelineError::InvalidParameter(format!(
+                        "de_emphasis.coefficient must be in [0, 1), got {}", config.coefficient
+                    )));
+                }
+                Ok(())
+            }
+            StageConfig::Declip(config) => {
+                if let Some(level) = config.clip_level {
+                    if level <= 0.0 {
+                        return Err(PipelineError::InvalidParameter(format!("declip.clip_level must be positive, got {}", level)));
+                    }
+                }
+                if !(0.0..1.0).contains(&config.tolerance) || config.min_run == 0 {
+                    return Err(PipelineError::InvalidParameter(
+                        "declip.tolerance must be in [0, 1) and declip.min_run positive".to_string()
+                    ));
+                }
+                if config.ar_order == 0 || config.context <= config.ar_order {
+                    return Err(PipelineError::InvalidParameter(
+                        "declip.ar_order must be positive and smaller than declip.context".to_string()
+                    ));
+                }
+                Ok(())
+            }
+            StageConfig::ClickRepair(config) => {
+                if config.threshold <= 0.0 || config.max_click_len == 0 {
+                    return Err(PipelineError::InvalidParameter(
+                        "click_repair.threshold and click_repair.max_click_len must be positive".to_string()
+                    ));
+                }
+                if config.ar_order == 0 || config.context <= config.ar_order {
+                    return Err(PipelineError::InvalidParameter(
+                        "click_repair.ar_order must be positive and smaller than click_repair.context".to_string()
+                    ));
+                }
+                Ok(())
+            }
+            StageConfig::HumRemoval(config) => {
+                if let Some(mains_hz) = config.mains_hz {
+                    check_frequency("hum_removal.mains_hz", mains_hz)?;
+                }
+                if config.harmonics == 0 || config.notch_width_hz <= 0.0 || config.detection_seconds <= 0.0 {
+                    return Err(PipelineError::InvalidParameter(
+                        "hum_removal.harmonics, notch_width_hz and detection_seconds must be positive".to_string()
+                    ));
+                }
+                Ok(())
+            }
+            StageConfig::Bandpass(config) => {
+                check_frequency("bandpass.low_hz", config.low_hz)?;
+                check_frequency("bandpass.high_hz", config.high_hz)?;
+                if config.low_hz >= config.high_hz {
+                    return Err(PipelineError::InvalidParameter(format!(
+                        "bandpass.low_hz ({}) must be below bandpass.high_hz ({})", config.low_hz, config.high_hz
+                    )));
+                }
+                config.design.validate().map_err(PipelineError::InvalidParameter)
+            }
+            StageConfig::SpectralGate(config) => {
+                if config.pre_emphasis && !(0.0..1.0).contains(&config.pre_emphasis_coefficient) {
+                    return Err(PipelineError::InvalidParameter(format!(
+                        "spectral_gate.pre_emphasis_coefficient must be in [0, 1), got {}", config.pre_emphasis_coefficient
+                    )));
+                }
+                Ok(())
+            }
+            StageConfig::SpectralSubtraction(config) => {
+                if config.frame_size < 2 || config.oversubtraction < 0.0 || config.spectral_floor < 0.0 {
+                    return Err(PipelineError::InvalidParameter(
+                        "spectral_subtraction.frame_size must be at least 2 and oversubtraction and spectral_floor non-negative".to_string()
+                    ));
+                }
+                Ok(())
+            }
+            StageConfig::Wiener(config) => {
+                if config.frame_size < 2 || !(0.0..1.0).contains(&config.smoothing) {
+                    return Err(PipelineError::InvalidParameter(
+                        "wiener.frame_size must be at least 2 and wiener.smoothing in [0, 1)".to_string()
+                    ));
+                }
+                Ok(())
+            }
+            StageConfig::Normalize(config) => {
+                if config.target_peak <= 0.0 {
+                    return Err(PipelineError::InvalidParameter(format!(
+                        "normalize.target_peak must be positive, got {}", config.target_peak
+                    )));
+                }
+                Ok(())
+            }
+            StageConfig::Agc(config) => {
+                if config.target_rms <= 0.0 || config.attack_ms <= 0.0 || config.release_ms <= 0.0 {
+                    return Err(PipelineError::InvalidParameter(
+                        "agc.target_rms, agc.attack_ms and agc.release_ms must be positive".to_string()
+                    ));
+                }
+                if config.max_gain_db.is_nan() || config.max_gain_db < 0.0 {
+                    return Err(PipelineError::InvalidParameter(format!(
+                        "agc.max_gain_db must be non-negative, got {}", config.max_gain_db
+                    )));
+                }
+                if config.compression_ratio < 1.0 || config.noise_floor_rms < 0.0 {
+                    return Err(PipelineError::InvalidParameter(format!(
+                        "agc.compression_ratio must be at least 1 and agc.noise_floor_rms non-negative, got {} and {}",
+                        config.compression_ratio, config.noise_floor_rms
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Instantiate the processor described by this stage
+    ///
+    /// # Arguments
+    /// * `sample_rate` - Sample rate of the audio to be processed
+    /// * `noise_profile` - Noise profile, required by spectral gate stages
+    pub fn build(
+        &self,
+        sample_rate: f32,
+        noise_profile: Option<&Spectrum>,
+    ) -> Result<Box<dyn AudioProcessor>, PipelineError> {
+        self.validate(sample_rate)?;
+        let processor: Box<dyn AudioProcessor> = match self {
+            StageConfig::DcRemoval(config) => Box::new(DcBlocker::new(sample_rate, *config)),
+            StageConfig::HighPass(config) => Box::new(HighPassFilter::new(sample_rate, *config)),
+            StageConfig::PreEmphasis(config) => Box::new(PreEmphasis::new(*config)),
+            StageConfig::DeEmphasis(config) => Box::new(DeEmphasis::new(*config)),
+            StageConfig::Declip(config) => Box::new(Declipper::new(*config)),
+            StageConfig::ClickRepair(config) => Box::new(ClickRepairer::new(*config)),
+            StageConfig::HumRemoval(config) => Box::new(HumRemover::new(sample_rate, *config)),
+            StageConfig::Bandpass(config) => Box::new(BandpassFilter::new(sample_rate, *config)),
+            StageConfig::SpectralGate(config) => {
+                let noise = noise_profile.ok_or(PipelineError::MissingNoiseProfile)?;
+                Box::new(SpectralGate::new(noise.clone(), config.clone()))
+            }
+            StageConfig::SpectralSubtraction(config) => {
+                let noise = noise_profile.ok_or(PipelineError::MissingNoiseProfile)?;
+                Box::new(SpectralSubtraction::new(noise.clone(), config.clone()))
+            }
+            StageConfig::Wiener(config) => {
+                let noise = noise_profile.ok_or(PipelineError::MissingNoiseProfile)?;
+                Box::new(WienerFilter::new(noise.clone(), config.clone()))
+            }
+            StageConfig::Normalize(config) => Box::new(Normalizer::new(*config)),
+            StageConfig::Agc(config) => Box::new(AutomaticGainControl::new(sample_rate, *config)),
+        };
+        Ok(processor)
+    }
+}
+
+impl From<DcRemovalConfig> for StageConfig {
+    fn from(config: DcRemovalConfig) -> Self {
+        StageConfig::DcRemoval(config)
+    }
+}
+
+impl From<HighPassConfig> for StageConfig {
+    fn from(config: HighPassConfig) -> Self {
+        StageConfig::HighPass(config)
+    }
+}
+
+impl From<PreEmphasisConfig> for StageConfig {
+    fn from(config: PreEmphasisConfig) -> Self {
+        StageConfig::PreEmphasis(config)
+    }
+}
+
+impl From<DeEmphasisConfig> for StageConfig {
+    fn from(config: DeEmphasisConfig) -> Self {
+        StageConfig::DeEmphasis(config)
+    }
+}
+
+impl From<DeclipConfig> for StageConfig {
+    fn from(config: DeclipConfig) -> Self {
+        StageConfig::Declip(config)
+    }
+}
+
+impl From<ClickRepairConfig> for StageConfig {
+    fn from(config: ClickRepairConfig) -> Self {
+        StageConfig::ClickRepair(config)
+    }
+}
+
+impl From<HumConfig> for StageConfig {
+    fn from(config: HumConfig) -> Self {
+        StageConfig::HumRemoval(config)
+    }
+}
+
+impl From<BandpassConfig> for StageConfig {
+    fn from(config: BandpassConfig) -> Self {
+        StageConfig::Bandpass(config)
+    }
+}
+
+impl From<SpectralGateConfig> for StageConfig {
+    fn from(config: SpectralGateConfig) -> Self {
+        StageConfig::SpectralGate(config)
+    }
+}
+
+impl From<SpectralSubtractionConfig> for StageConfig {
+    fn from(config: SpectralSubtractionConfig) -> Self {
+        StageConfig::SpectralSubtraction(config)
+    }
+}
+
+impl From<WienerConfig> for StageConfig {
+    fn from(config: WienerConfig) -> Self {
+        StageConfig::Wiener(config)
+    }
+}
+
+impl From<NormalizeConfig> for StageConfig {
+    fn from(config: NormalizeConfig) -> Self {
+        StageConfig::Normalize(config)
+    }
+}
+
+impl From<AgcConfig> for StageConfig {
+    fn from(config: AgcConfig) -> Self {
+        StageConfig::Agc(config)
+    }
+}
+
+/// Serializable description of a cleaning pipeline: an ordered list of stages
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CleaningPipelineConfig {
+    /// Stages in processing order
+    #[serde(default)]
+    pub stages: Vec<StageConfig>,
+}
+
+impl CleaningPipelineConfig {
+    /// Create an empty pipeline configuration (passes audio through unchanged)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a stage to the end of the pipeline
+    pub fn with_stage(mut self, stage: impl Into<StageConfig>) -> Self {
+        self.stages.push(stage.into());
+        self
+    }
+
+    /// Whether any stage needs a noise profile to be built
+    pub fn requires_noise_profile(&self) -> bool {
+        self.stages.iter().any(|stage| {
+            matches!(
+                stage,
+                StageConfig::SpectralGate(_) | StageConfig::SpectralSubtraction(_) | StageConfig::Wiener(_)
+            )
+        })
+    }
+
+    /// Serialize the configuration to TOML
+    pub fn to_toml(&self) -> Result<String, PipelineError> {
+        toml::to_string(self).map_err(|e| PipelineError::Serialization(e.to_string()))
+    }
+
+    /// Parse a configuration from TOML
+    pub fn from_toml(s: &str) -> Result<Self, PipelineError> {
+        toml::from_str(s).map_err(|e| PipelineError::Serialization(e.to_string()))
+    }
+
+    /// Serialize the configuration to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, PipelineError> {
+        serde_json::to_string_pretty(self).map_err(|e| PipelineError::Serialization(e.to_string()))
+    }
+
+    /// Parse a configuration from JSON
+    pub fn from_json(s: &str) -> Result<Self, PipelineError> {
+        serde_json::from_str(s).map_err(|e| PipelineError::Serialization(e.to_string()))
+    }
+}
+
+/// Ordered chain of audio processors
+///
+/// The pipeline is itself an [`AudioProcessor`]: `process` cleans a complete
+/// recording, `process_block` cleans consecutive chunks of a live stream.
+#[derive(Default)]
+pub struct CleaningPipeline {
+    stages: Vec<Box<dyn AudioProcessor>>,
+}
+
+impl CleaningPipeline {
+    /// Create an empty pipeline
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a pipeline from its configuration
+    ///
+    /// # Arguments
+    /// * `config` - Stage list and parameters
+    /// * `sample_rate` - Sample rate of the audio to be processed
+    /// * `noise_profile` - Noise profile for spectral gate stages, if any
+    ///
+    /// # Returns
+    /// The pipeline, or an error if a stage is misconfigured or a required
+    /// noise profile is missing
+    pub fn from_config(
+        config: &CleaningPipelineConfig,
+        sample_rate: f32,
+        noise_profile: Option<&Spectrum>,
+    ) -> Result<Self, PipelineError> {
+        let stages = config
+            .stages
+            .iter()
+            .map(|stage| stage.build(sample_rate, noise_profile))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { stages })
+    }
+
+    /// Append a custom processor to the end of the pipeline
+    pub fn push(&mut self, processor: Box<dyn AudioProcessor>) {
+        self.stages.push(processor);
+    }
+
+    /// Number of stages in the pipeline
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Whether the pipeline has no stages
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Names of the stages in processing order
+    pub fn stage_names(&self) -> Vec<&'static str> {
+        self.stages.iter().map(|stage| stage.name()).collect()
+    }
+
+    /// Clean a complete recording (batch mode)
+    pub fn process_audio(&mut self, audio: &MonoAudio) -> MonoAudio {
+        MonoAudio {
+            samples: self.process(&audio.samples),
+            sample_rate: audio.sample_rate,
+        }
+    }
+}
+
+impl AudioProcessor for CleaningPipeline {
+    fn name(&self) -> &'static str {
+        "pipeline"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        for stage in self.stages.iter_mut() {
+            stage.process_block(samples);
+        }
+    }
+
+    fn reset(&mut self) {
+        for stage in self.stages.iter_mut() {
+            stage.reset();
+        }
+    }
+
+    /// Total gain applied by the level-changing stages; `None` if no stage reports one
+    fn gain_db(&self) -> Option<f32> {
+        self.stages
+            .iter()
+            .filter_map(|stage| stage.gain_db())
+            .reduce(|total, gain| total + gain)
+    }
+
+    /// Total number of samples repaired by the repair stages; `None` if there are none
+    fn repaired_samples(&self) -> Option<usize> {
+        self.stages
+            .iter()
+            .filter_map(|stage| stage.repaired_samples())
+            .reduce(|total, repaired| total + repaired)
+    }
+
+    /// Run each stage's batch mode in turn, so that offline-only behaviour such as
+    /// zero-phase bandpass filtering is honoured
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.stages
+            .iter_mut()
+            .fold(samples.to_vec(), |signal, stage| stage.process(&signal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::FilterDesign;
+
+    fn full_config() -> CleaningPipelineConfig {
+        CleaningPipelineConfig::new()
+            .with_stage(DcRemovalConfig::default())
+            .with_stage(HighPassConfig::default())
+            .with_stage(PreEmphasisConfig::default())
+            .with_stage(DeEmphasisConfig::default())
+            .with_stage(DeclipConfig::default())
+            .with_stage(ClickRepairConfig::default())
+            .with_stage(HumConfig::default())
+            .with_stage(BandpassConfig::default())
+            .with_stage(SpectralGateConfig::default())
+            .with_stage(NormalizeConfig::default())
+            .with_stage(AgcConfig::default())
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let config = full_config();
+        let toml = config.to_toml().unwrap();
+        assert!(toml.contains("stage = \"high_pass\""));
+        assert_eq!(CleaningPipelineConfig::from_toml(&toml).unwrap(), config);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let config = full_config();
+        let json = config.to_json().unwrap();
+        assert_eq!(CleaningPipelineConfig::from_json(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn test_parse_with_defaults() {
+        let toml = r#"
+            [[stages]]
+            stage = "bandpass"
+            low_hz = 100.0
+
+            [[stages]]
+            stage = "normalize"
+        "#;
+        let config = CleaningPipelineConfig::from_toml(toml).unwrap();
+        assert_eq!(config.stages[0], StageConfig::Bandpass(BandpassConfig { low_hz: 100.0, ..Default::default() }));
+        assert_eq!(config.stages[1], StageConfig::Normalize(NormalizeConfig::default()));
+    }
+
+    #[test]
+    fn test_parse_unknown_stage_fails() {
+        let toml = "[[stages]]\nstage = \"reverb\"\n";
+        assert!(matches!(
+            CleaningPipelineConfig::from_toml(toml),
+            Err(PipelineError::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn test_spectral_gate_requires_noise_profile() {
+        let config = full_config();
+        assert!(config.requires_noise_profile());
+        assert!(matches!(
+            CleaningPipeline::from_config(&config, 44100.0, None),
+            Err(PipelineError::MissingNoiseProfile)
+        ));
+
+        assert_eq!(PipelineError::MissingNoiseProfile.to_string(), "Noise-reduction stage requires a noise profile");
+
+        let noise = Spectrum::from_waveform(&[0.01; 256]);
+        let pipeline = CleaningPipeline::from_config(&config, 44100.0, Some(&noise)).unwrap();
+        assert_eq!(
+            pipeline.stage_names(),
+            vec!["dc_removal", "high_pass", "pre_emphasis", "de_emphasis", "declip", "click_repair", "hum_removal", "bandpass", "spectral_gate", "normalize", "agc"]
+        );
+    }
+
+    #[test]
+    fn test_noise_reducer_stages_are_interchangeable() {
+        let noise = Spectrum::from_waveform(&[0.01; 1024]);
+        for reducer in [
+            StageConfig::SpectralGate(SpectralGateConfig::default()),
+            StageConfig::SpectralSubtraction(SpectralSubtractionConfig::default()),
+            StageConfig::Wiener(WienerConfig::default()),
+        ] {
+            let config = CleaningPipelineConfig::new()
+                .with_stage(HighPassConfig::default())
+                .with_stage(reducer.clone());
+            assert!(config.requires_noise_profile());
+            assert!(matches!(
+                CleaningPipeline::from_config(&config, 16000.0, None),
+                Err(PipelineError::MissingNoiseProfile)
+            ));
+            let parsed = CleaningPipelineConfig::from_toml(&config.to_toml().unwrap()).unwrap();
+            let mut pipeline = CleaningPipeline::from_config(&parsed, 16000.0, Some(&noise)).unwrap();
+            assert_eq!(pipeline.process(&[0.1; 2048]).len(), 2048);
+        }
+    }
+
+    #[test]
+    fn test_invalid_parameters_rejected() {
+        let above_nyquist = CleaningPipelineConfig::new().with_stage(HighPassConfig { cutoff_hz: 5000.0, ..Default::default() });
+        assert!(matches!(
+            CleaningPipeline::from_config(&above_nyquist, 8000.0, None),
+            Err(PipelineError::InvalidParameter(_))
+        ));
+
+        let inverted = CleaningPipelineConfig::new().with_stage(BandpassConfig { low_hz: 1000.0, high_hz: 100.0, ..Default::default() });
+        assert!(matches!(
+            CleaningPipeline::from_config(&inverted, 8000.0, None),
+            Err(PipelineError::InvalidParameter(_))
+        ));
+
+        for max_gain_db in [-6.0, f32::NAN] {
+            let agc = CleaningPipelineConfig::new().with_stage(AgcConfig { max_gain_db, ..Default::default() });
+            assert!(matches!(
+                CleaningPipeline::from_config(&agc, 8000.0, None),
+                Err(PipelineError::InvalidParameter(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_stage_order_matters() {
+        let signal: Vec<f32> = (0..2000).map(|i| (i as f32 * 0.1).sin() * 0.2 + 0.3).collect();
+        let normalize_then_dc = CleaningPipelineConfig::new()
+            .with_stage(NormalizeConfig::default())
+            .with_stage(DcRemovalConfig::default());
+        let dc_then_normalize = CleaningPipelineConfig::new()
+            .with_stage(DcRemovalConfig::default())
+            .with_stage(NormalizeConfig::default());
+
+        let a = CleaningPipeline::from_config(&normalize_then_dc, 8000.0, None).unwrap().process(&signal);
+        let b = CleaningPipeline::from_config(&dc_then_normalize, 8000.0, None).unwrap().process(&signal);
+        let peak_b = b.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        assert!((peak_b - 0.9).abs() < 1e-4);
+        assert!(a.iter().zip(b.iter()).any(|(x, y)| (x - y).abs() > 1e-3));
+    }
+
+    #[test]
+    fn test_streaming_matches_batch_for_filters() {
+        let config = CleaningPipelineConfig::new()
+            .with_stage(DcRemovalConfig::default())
+            .with_stage(HighPassConfig::default())
+            .with_stage(PreEmphasisConfig::default())
+            .with_stage(BandpassConfig::default())
+            .with_stage(DeEmphasisConfig::default())
+            .with_stage(AgcConfig::default());
+        let signal: Vec<f32> = (0..4000).map(|i| (i as f32 * 0.07).sin() * 0.05).collect();
+
+        let mut pipeline = CleaningPipeline::from_config(&config, 16000.0, None).unwrap();
+        let batch = pipeline.process(&signal);
+
+        pipeline.reset();
+        let mut streamed = signal.clone();
+        for chunk in streamed.chunks_mut(512) {
+            pipeline.process_block(chunk);
+        }
+        for (a, b) in batch.iter().zip(streamed.iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_live_gating_independent_of_callback_size() {
+        // The GUI gates each audio callback block, whatever its size, with frames of
+        // the pitch window size
+        let config = CleaningPipelineConfig::new()
+            .with_stage(SpectralGateConfig { block_size: Some(2048), ..Default::default() })
+            .with_stage(BandpassConfig::default())
+            .with_stage(AgcConfig::default());
+        let noise = sound_synth::white_noise(8192, 0.01, 3);
+        let profile = Spectrum::from_waveform(&noise);
+        let signal: Vec<f32> = sound_synth::white_noise(20000, 0.01, 5)
+            .iter()
+            .enumerate()
+            .map(|(i, n)| n + 0.2 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 44100.0).sin())
+            .collect();
+
+        let mut pipeline = CleaningPipeline::from_config(&config, 44100.0, Some(&profile)).unwrap();
+        let mut whole_windows = signal.clone();
+        for chunk in whole_windows.chunks_mut(2048) {
+            pipeline.process_block(chunk);
+        }
+
+        pipeline.reset();
+        let mut callbacks = signal.clone();
+        let mut rest = callbacks.as_mut_slice();
+        for &size in [441, 512, 64, 1000, 7].iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, tail) = rest.split_at_mut(size.min(rest.len()));
+            pipeline.process_block(chunk);
+            rest = tail;
+        }
+        for (a, b) in whole_windows.iter().zip(&callbacks) {
+            assert!((a - b).abs() < 1e-4, "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_parse_filter_design() {
+        let toml = r#"
+            [[stages]]
+            stage = "bandpass"
+            zero_phase = true
+            design = { kind = "chebyshev", order = 6, ripple_db = 0.5 }
+        "#;
+        let config = CleaningPipelineConfig::from_toml(toml).unwrap();
+        let expected = BandpassConfig {
+            design: FilterDesign::Chebyshev { order: 6, ripple_db: 0.5 },
+            zero_phase: true,
+            ..Default::default()
+        };
+        assert_eq!(config.stages[0], StageConfig::Bandpass(expected));
+        assert_eq!(CleaningPipelineConfig::from_toml(&config.to_toml().unwrap()).unwrap(), config);
+
+        let odd_linkwitz_riley = CleaningPipelineConfig::new()
+            .with_stage(BandpassConfig { design: FilterDesign::LinkwitzRiley { order: 3 }, ..Default::default() });
+        assert!(matches!(
+            CleaningPipeline::from_config(&odd_linkwitz_riley, 16000.0, None),
+            Err(PipelineError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_zero_phase_applies_in_batch_only() {
+        let config = CleaningPipelineConfig::new().with_stage(BandpassConfig {
+            design: FilterDesign::Butterworth { order: 2 },
+            zero_phase: true,
+            ..Default::default()
+        });
+        let signal: Vec<f32> = (0..4000).map(|i| (i as f32 * 0.07).sin() * 0.05).collect();
+        let mut pipeline = CleaningPipeline::from_config(&config, 16000.0, None).unwrap();
+        let batch = pipeline.process(&signal);
+        let direct = BandpassFilter::new(16000.0, BandpassConfig {
+            design: FilterDesign::Butterworth { order: 2 },
+            zero_phase: true,
+            ..Default::default()
+        })
+        .process(&signal);
+        assert_eq!(batch, direct);
+
+        pipeline.reset();
+        let mut streamed = signal.clone();
+        pipeline.process_block(&mut streamed);
+        assert_ne!(batch, streamed);
+    }
+
+    #[test]
+    fn test_pipeline_reports_agc_gain() {
+        let without_agc = CleaningPipelineConfig::new().with_stage(HighPassConfig::default());
+        assert_eq!(CleaningPipeline::from_config(&without_agc, 8000.0, None).unwrap().gain_db(), None);
+
+        let config = CleaningPipelineConfig::new()
+            .with_stage(HighPassConfig::default())
+            .with_stage(AgcConfig::default());
+        let mut pipeline = CleaningPipeline::from_config(&config, 8000.0, None).unwrap();
+        assert_eq!(pipeline.gain_db(), Some(0.0));
+        let mut quiet: Vec<f32> = (0..16000).map(|i| 0.01 * (i as f32 * 0.2).sin()).collect();
+        pipeline.process_block(&mut quiet);
+        assert!(pipeline.gain_db().unwrap() > 15.0);
+    }
+
+    #[test]
+    fn test_pipeline_reports_repaired_samples() {
+        let config = CleaningPipelineConfig::new()
+            .with_stage(DeclipConfig { clip_level: Some(0.5), ..Default::default() })
+            .with_stage(ClickRepairConfig::default());
+        let mut pipeline = CleaningPipeline::from_config(&config, 16000.0, None).unwrap();
+        let clipped: Vec<f32> = (0..4000).map(|i| (i as f32 * 0.05).sin().clamp(-0.5, 0.5)).collect();
+        pipeline.process(&clipped);
+        assert!(pipeline.repaired_samples().unwrap() > 0);
+
+        let without_repair = CleaningPipelineConfig::new().with_stage(HighPassConfig::default());
+        assert_eq!(CleaningPipeline::from_config(&without_repair, 16000.0, None).unwrap().repaired_samples(), None);
+    }
+
+    #[test]
+    fn test_empty_pipeline_is_identity() {
+        let mut pipeline = CleaningPipeline::new();
+        assert!(pipeline.is_empty());
+        let audio = MonoAudio::new(vec![0.1, -0.2, 0.3], 8000);
+        let cleaned = pipeline.process_audio(&audio);
+        assert_eq!(cleaned.samples, audio.samples);
+        assert_eq!(cleaned.sample_rate, 8000);
+    }
+}
@@ -4,13 +4,16 @@
 //! 
 
 
-/// Find the index and value of the peak in a signal
-/// FIXME There must be a better way to do this (library function, or more robust)
+/// Find the index and value of the largest finite value in a signal
+///
+/// Non-finite values are ignored. For several local maxima with prominence,
+/// spacing and sub-bin interpolation, see [`crate::peaks::find_peaks`].
 pub fn find_peak(signal: &[f32]) -> Option<(usize, f32)> {
     signal
         .iter()
         .enumerate()
-        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+        .filter(|(_, v)| v.is_finite())
+        .max_by(|a, b| a.1.total_cmp(b.1))
         .map(|(i, &v)| (i, v))
 }
 
@@ -65,4 +68,12 @@ mod tests {
         let peak = find_peak(&waveform);
         assert_eq!(peak, Some((2, 2.0)));
     }
+
+    #[test]
+    fn test_find_peak_ignores_nan() {
+        let waveform = vec![0.1, f32::NAN, 0.7, f32::INFINITY, 0.3];
+        assert_eq!(find_peak(&waveform), Some((2, 0.7)));
+        assert_eq!(find_peak(&[f32::NAN, f32::NAN]), None);
+        assert_eq!(find_peak(&[]), None);
+    }
 }
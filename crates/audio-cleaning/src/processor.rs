@@ -0,0 +1,49 @@
+//! Audio Processor Trait
+//!
+//! This module defines the common interface shared by all cleaning stages, so that
+//! they can be chained in a [`CleaningPipeline`](crate::pipeline::CleaningPipeline)
+//! and used interchangeably in batch and streaming code.
+
+/// A stateful audio cleaning stage
+///
+/// Processors keep whatever state they need (filter memories, gain trackers, ...)
+/// between calls to [`process_block`](AudioProcessor::process_block), so a live
+/// stream can be fed chunk by chunk without discontinuities at chunk boundaries.
+/// [`process`](AudioProcessor::process) resets that state first and treats its
+/// input as a complete recording.
+pub trait AudioProcessor: Send {
+    /// Short identifier of the stage, used for diagnostics
+    fn name(&self) -> &'static str;
+
+    /// Process the next chunk of a stream in-place (streaming mode)
+    fn process_block(&mut self, samples: &mut [f32]);
+
+    /// Clear all internal state, as if no audio had been processed yet
+    fn reset(&mut self);
+
+    /// Gain currently applied by the stage in dB, for stages that change the level
+    /// (e.g. to drive a gain meter in the UI). Returns `None` for other stages.
+    fn gain_db(&self) -> Option<f32> {
+        None
+    }
+
+    /// Number of samples the stage has repaired since the last reset, for stages that
+    /// replace damaged samples (declipping, click repair). Returns `None` for other stages.
+    fn repaired_samples(&self) -> Option<usize> {
+        None
+    }
+
+    /// Process a complete signal (batch mode)
+    ///
+    /// # Arguments
+    /// * `samples` - The whole signal to process
+    ///
+    /// # Returns
+    /// Processed samples with the same length as input
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.reset();
+        let mut output = samples.to_vec();
+        self.process_block(&mut output);
+        output
+    }
+}
@@ -0,0 +1,833 @@
+//! Declipping and Click Repair
+//!
+//! This module repairs damaged samples before analysis:
+//! - Clipping detection: runs of samples stuck at the converter's full-scale level
+//! - Declipping: reconstruction of clipped runs by cubic or autoregressive (AR)
+//!   interpolation, constrained to stay beyond the clip level
+//! - Click/pop repair: impulsive disturbances found as outliers of the AR prediction
+//!   error and replaced by AR interpolation
+//!
+//! Clipped peaks add strong false harmonics that confuse pitch detection and the
+//! spectral gate, so these stages belong early in a cleaning pipeline. Both keep a
+//! count of the samples they repaired.
+//!
+//! When streaming, each block is analysed together with the end of the previous
+//! block as left context. Damage that straddles a block boundary is repaired from
+//! the left side only. The declipper reuses its buffers, so once they have grown to
+//! the block and gap lengths of the stream it does not allocate.
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use crate::processor::AudioProcessor;
+
+/// Block length the streaming buffers are sized for up front; longer blocks grow
+/// them once
+const INITIAL_BLOCK_CAPACITY: usize = 4096;
+
+/// Number of samples repaired by a stage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RepairStats {
+    /// Number of separate damaged regions that were repaired
+    pub regions: usize,
+    /// Total number of samples that were replaced
+    pub samples: usize,
+}
+
+impl RepairStats {
+    fn add(&mut self, regions: impl Iterator<Item = Range<usize>>) {
+        for region in regions {
+            self.regions += 1;
+            self.samples += region.len();
+        }
+    }
+}
+
+/// Interpolation used to reconstruct damaged samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterpolationMethod {
+    /// Cubic Hermite spline through the neighbouring samples and their slopes.
+    /// Cheap, good for short runs.
+    Cubic,
+    /// Forward and backward AR predictions from the surrounding context, crossfaded.
+    /// Follows the waveform through longer runs.
+    #[default]
+    Autoregressive,
+}
+
+/// Configuration for clipping detection and declipping
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeclipConfig {
+    /// Absolute level at which the signal clips, or `None` to use the largest
+    /// absolute sample value seen. An auto-detected level only counts as clipping
+    /// once runs at it have been found on both sides of zero, so a DC offset or a
+    /// value held at the peak is left alone. Default: None
+    pub clip_level: Option<f32>,
+    /// Relative tolerance below the clip level that still counts as clipped.
+    /// Default: 1e-5
+    pub tolerance: f32,
+    /// Shortest run of samples at the clip level that is treated as clipping.
+    /// Isolated peaks of a clean waveform are shorter than this. Default: 3
+    pub min_run: usize,
+    /// Reconstruction method. Default: Autoregressive
+    pub method: InterpolationMethod,
+    /// Order of the AR model. Default: 16
+    pub ar_order: usize,
+    /// Samples on each side of a run used to fit the AR model. Default: 128
+    pub context: usize,
+}
+
+impl Default for DeclipConfig {
+    fn default() -> Self {
+        Self {
+            clip_level: None,
+            tolerance: 1e-5,
+            min_run: 3,
+            method: InterpolationMethod::Autoregressive,
+            ar_order: 16,
+            context: 128,
+        }
+    }
+}
+
+/// Find clipped runs in a signal
+///
+/// A run is a sequence of at least `min_run` consecutive samples with the same sign
+/// whose magnitude is within `tolerance` of the clip level. With an auto-detected
+/// level, there are only runs if some are positive and some negative.
+///
+/// # Arguments
+/// * `samples` - Audio samples
+/// * `config` - Detection parameters (`clip_level`, `tolerance`, `min_run`)
+///
+/// # Returns
+/// Index ranges of the clipped runs, in order
+pub fn detect_clipping(samples: &[f32], config: &DeclipConfig) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let level = config.clip_level.unwrap_or_else(|| peak_level(samples));
+    clipped_runs(samples, level, config, &mut runs);
+    if config.clip_level.is_none() && polarities(samples, &runs) != (true, true) {
+        runs.clear();
+    }
+    runs
+}
+
+fn peak_level(samples: &[f32]) -> f32 {
+    samples
+        .iter()
+        .filter(|x| x.is_finite())
+        .fold(0.0f32, |peak, x| peak.max(x.abs()))
+}
+
+/// Write the clipped runs of `samples` at `level` to `runs`
+fn clipped_runs(samples: &[f32], level: f32, config: &DeclipConfig, runs: &mut Vec<Range<usize>>) {
+    runs.clear();
+    if level <= 0.0 {
+        return;
+    }
+    let threshold = level * (1.0 - config.tolerance);
+    let min_run = config.min_run.max(1);
+    let mut start = 0;
+    while start < samples.len() {
+        if samples[start].abs() < threshold {
+            start += 1;
+            continue;
+        }
+        let sign = samples[start].signum();
+        let end = samples[start..]
+            .iter()
+            .position(|x| x.abs() < threshold || x.signum() != sign)
+            .map_or(samples.len(), |offset| start + offset);
+        if end - start >= min_run {
+            runs.push(start..end);
+        }
+        start = end;
+    }
+}
+
+/// Whether any of `runs` are positive, and whether any are negative
+fn polarities(samples: &[f32], runs: &[Range<usize>]) -> (bool, bool) {
+    let positive = runs.iter().any(|run| samples[run.start] > 0.0);
+    let negative = runs.iter().any(|run| samples[run.start] < 0.0);
+    (positive, negative)
+}
+
+/// Reconstruct clipped runs in a complete recording
+///
+/// # Returns
+/// The repaired samples and the number of samples that were replaced
+pub fn declip(samples: &[f32], config: DeclipConfig) -> (Vec<f32>, RepairStats) {
+    let mut declipper = Declipper::new(config);
+    let output = declipper.process(samples);
+    (output, declipper.stats())
+}
+
+/// Clipping detector and declipper
+///
+/// Reconstructed samples keep the sign of the clipped run and never fall below
+/// the clip level, since the true waveform exceeded it.
+pub struct Declipper {
+    config: DeclipConfig,
+    /// End of the previous block as context, followed by the current block
+    buffer: Vec<f32>,
+    runs: Vec<Range<usize>>,
+    /// Clipped values of a run still open at the end of the block
+    open_values: Vec<f32>,
+    scratch: ArScratch,
+    running_peak: f32,
+    /// Whether positive and negative runs have been seen at `running_peak`
+    clipped_polarities: (bool, bool),
+    stats: RepairStats,
+}
+
+impl Declipper {
+    /// Create a declipper
+    pub fn new(config: DeclipConfig) -> Self {
+        let capacity = config.context + INITIAL_BLOCK_CAPACITY;
+        Self {
+            config,
+            buffer: Vec::with_capacity(capacity),
+            // Runs are at least `min_run` long and separated by at least one sample
+            runs: Vec::with_capacity(capacity / (config.min_run.max(1) + 1)),
+            open_values: Vec::with_capacity(capacity),
+            scratch: ArScratch::with_capacity(config.ar_order, config.context),
+            running_peak: 0.0,
+            clipped_polarities: (false, false),
+            stats: RepairStats::default(),
+        }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &DeclipConfig {
+        &self.config
+    }
+
+    /// Samples repaired since the last reset
+    pub fn stats(&self) -> RepairStats {
+        self.stats
+    }
+}
+
+impl AudioProcessor for Declipper {
+    fn name(&self) -> &'static str {
+        "declip"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        let peak = peak_level(samples);
+        if peak > self.running_peak {
+            self.running_peak = peak;
+            self.clipped_polarities = (false, false);
+        }
+        let level = self.config.clip_level.unwrap_or(self.running_peak);
+
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(samples);
+
+        clipped_runs(&self.buffer, level, &self.config, &mut self.runs);
+        if self.config.clip_level.is_none() {
+            let (positive, negative) = polarities(&self.buffer, &self.runs);
+            self.clipped_polarities.0 |= positive;
+            self.clipped_polarities.1 |= negative;
+            if self.clipped_polarities != (true, true) {
+                self.runs.clear();
+            }
+        }
+        // Runs that started in the history were still open at the end of the last
+        // block, so the history holds their clipped values and they are repaired
+        // again as a whole now that more right context is available
+        self.runs.retain(|run| run.end > offset);
+        let open_run = self.runs.last().filter(|run| run.end == self.buffer.len()).cloned();
+        self.open_values.clear();
+        if let Some(run) = open_run.clone() {
+            self.open_values.extend_from_slice(&self.buffer[run]);
+        }
+        for run in &self.runs {
+            let sign = self.buffer[run.start].signum();
+            interpolate(
+                &mut self.buffer, run.clone(), self.config.method, self.config.ar_order, self.config.context, &mut self.scratch,
+            );
+            for x in self.buffer[run.clone()].iter_mut() {
+                *x = sign * x.abs().max(level);
+            }
+        }
+        self.stats.add(self.runs.iter().map(|run| run.start.max(offset)..run.end));
+
+        samples.copy_from_slice(&self.buffer[offset..]);
+        if let Some(run) = open_run {
+            self.buffer[run].copy_from_slice(&self.open_values);
+        }
+        keep_tail(&mut self.buffer, self.config.context);
+    }
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.running_peak = 0.0;
+        self.clipped_polarities = (false, false);
+        self.stats = RepairStats::default();
+    }
+
+    fn repaired_samples(&self) -> Option<usize> {
+        Some(self.stats.samples)
+    }
+}
+
+/// Configuration for click and pop repair
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClickRepairConfig {
+    /// Detection threshold, as a multiple of the robust standard deviation of the
+    /// AR prediction error. Default: 8.0
+    pub threshold: f32,
+    /// Order of the AR model. Default: 16
+    pub ar_order: usize,
+    /// Samples on each side of a click used to fit the interpolating AR model.
+    /// Default: 128
+    pub context: usize,
+    /// Longest disturbance treated as a click; longer outliers are left alone as
+    /// genuine transients. Default: 32
+    pub max_click_len: usize,
+    /// Extra samples repaired on each side of a detected click. Default: 2
+    pub padding: usize,
+}
+
+impl Default for ClickRepairConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 8.0,
+            ar_order: 16,
+            context: 128,
+            max_click_len: 32,
+            padding: 2,
+        }
+    }
+}
+
+/// Find clicks and pops in a signal
+///
+/// Fits an AR model to the signal and flags samples whose prediction error is far
+/// outside the typical error. Smooth, predictable material such as a sung note
+/// produces small errors, while an impulse produces a large one.
+///
+/// # Returns
+/// Index ranges of the detected clicks (including padding), in order
+pub fn detect_clicks(samples: &[f32], config: &ClickRepairConfig) -> Vec<Range<usize>> {
+    let order = config.ar_order.min(samples.len() / 4);
+    if order == 0 {
+        return Vec::new();
+    }
+    let mut fit = ArFit::default();
+    if !ar_coefficients(samples, order, &mut fit) {
+        return Vec::new();
+    }
+    let coefficients = &fit.coefficients;
+
+    let residual: Vec<f32> = (order..samples.len())
+        .map(|n| samples[n] - predict(coefficients, |k| samples[n - k]))
+        .collect();
+    let mut magnitudes: Vec<f32> = residual.iter().map(|e| e.abs()).collect();
+    magnitudes.sort_by(|a, b| a.total_cmp(b));
+    // Median absolute deviation, floored so that near-perfect prediction of a pure
+    // tone does not turn rounding noise into clicks
+    let signal_rms = (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt();
+    let sigma = (magnitudes[magnitudes.len() / 2] / 0.6745).max(1e-3 * signal_rms);
+    if sigma <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut clicks: Vec<Range<usize>> = Vec::new();
+    for (i, e) in residual.iter().enumerate() {
+        if e.abs() <= config.threshold * sigma {
+            continue;
+        }
+        let n = i + order;
+        let region = n.saturating_sub(config.padding)..(n + config.padding + 1).min(samples.len());
+        match clicks.last_mut() {
+            Some(last) if region.start <= last.end => last.end = last.end.max(region.end),
+            _ => clicks.push(region),
+        }
+    }
+    clicks.retain(|click| click.len() <= config.max_click_len + 2 * config.padding);
+    clicks
+}
+
+/// Detect and repair clicks in a complete recording
+///
+/// # Returns
+/// The repaired samples and the number of samples that were replaced
+pub fn repair_clicks(samples: &[f32], config: ClickRepairConfig) -> (Vec<f32>, RepairStats) {
+    let mut repairer = ClickRepairer::new(config);
+    let output = repairer.process(samples);
+    (output, repairer.stats())
+}
+
+/// Click and pop detector with AR interpolation repair
+pub struct ClickRepairer {
+    config: ClickRepairConfig,
+    history: Vec<f32>,
+    scratch: ArScratch,
+    stats: RepairStats,
+}
+
+impl ClickRepairer {
+    /// Create a click repairer
+    pub fn new(config: ClickRepairConfig) -> Self {
+        Self {
+            config,
+            history: Vec::new(),
+            scratch: ArScratch::with_capacity(config.ar_order, config.context),
+            stats: RepairStats::default(),
+        }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &ClickRepairConfig {
+        &self.config
+    }
+
+    /// Samples repaired since the last reset
+    pub fn stats(&self) -> RepairStats {
+        self.stats
+    }
+}
+
+impl AudioProcessor for ClickRepairer {
+    fn name(&self) -> &'static str {
+        "click_repair"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        let offset = self.history.len();
+        let mut buffer = std::mem::take(&mut self.history);
+        buffer.extend_from_slice(samples);
+
+        let clicks: Vec<Range<usize>> = detect_clicks(&buffer, &self.config)
+            .into_iter()
+            .filter(|click| click.end > offset)
+            .map(|click| click.start.max(offset)..click.end)
+            .collect();
+        for click in &clicks {
+            interpolate(
+                &mut buffer, click.clone(), InterpolationMethod::Autoregressive, self.config.ar_order, self.config.context,
+                &mut self.scratch,
+            );
+        }
+        self.stats.add(clicks.into_iter());
+
+        samples.copy_from_slice(&buffer[offset..]);
+        keep_tail(&mut buffer, self.config.context);
+        self.history = buffer;
+    }
+
+    fn reset(&mut self) {
+        self.history.clear();
+        self.stats = RepairStats::default();
+    }
+
+    fn repaired_samples(&self) -> Option<usize> {
+        Some(self.stats.samples)
+    }
+}
+
+/// Keep the last `len` samples of a buffer as context for the next block
+fn keep_tail(buffer: &mut Vec<f32>, len: usize) {
+    let excess = buffer.len().saturating_sub(len);
+    buffer.drain(..excess);
+}
+
+/// Buffers for fitting an AR model, reused from one fit to the next
+#[derive(Default)]
+struct ArFit {
+    /// The signal in double precision
+    x: Vec<f64>,
+    /// Normal equations matrix, `order` by `order`, row-major
+    phi: Vec<f64>,
+    psi: Vec<f64>,
+    coefficients: Vec<f64>,
+}
+
+/// Buffers for AR interpolation, reused from one gap to the next
+#[derive(Default)]
+struct ArScratch {
+    fit: ArFit,
+    forward: Vec<f32>,
+    backward: Vec<f32>,
+    /// Right context in reverse order
+    reversed: Vec<f32>,
+}
+
+impl ArScratch {
+    /// Buffers sized for models of `order` fitted to `context` samples, and gaps of
+    /// up to a block
+    fn with_capacity(order: usize, context: usize) -> Self {
+        let extended = context + INITIAL_BLOCK_CAPACITY;
+        Self {
+            fit: ArFit {
+                x: Vec::with_capacity(context),
+                phi: Vec::with_capacity(order * order),
+                psi: Vec::with_capacity(order),
+                coefficients: Vec::with_capacity(order),
+            },
+            forward: Vec::with_capacity(extended),
+            backward: Vec::with_capacity(extended),
+            reversed: Vec::with_capacity(context),
+        }
+    }
+}
+
+/// Replace `gap` in `buffer` by interpolating from its surroundings
+fn interpolate(
+    buffer: &mut [f32],
+    gap: Range<usize>,
+    method: InterpolationMethod,
+    ar_order: usize,
+    context: usize,
+    scratch: &mut ArScratch,
+) {
+    match method {
+        InterpolationMethod::Cubic => interpolate_cubic(buffer, gap),
+        InterpolationMethod::Autoregressive => interpolate_ar(buffer, gap, ar_order, context, scratch),
+    }
+}
+
+/// Cubic Hermite spline across the gap, with slopes from the neighbouring samples
+fn interpolate_cubic(buffer: &mut [f32], gap: Range<usize>) {
+    let n = buffer.len();
+    let (start, end) = (gap.start, gap.end);
+    let (left, right) = match (start.checked_sub(1), (end < n).then_some(end)) {
+        (Some(left), Some(right)) => (left, right),
+        (Some(left), None) => (left, left),
+        (None, Some(right)) => (right, right),
+        (None, None) => return,
+    };
+    let slope = |i: usize, towards_left: bool| -> f32 {
+        if towards_left && i >= 1 {
+            buffer[i] - buffer[i - 1]
+        } else if !towards_left && i + 1 < n {
+            buffer[i + 1] - buffer[i]
+        } else {
+            0.0
+        }
+    };
+    let (y0, y1) = (buffer[left], buffer[right]);
+    let span = (right - left).max(1) as f32;
+    // Slopes per sample, scaled to the unit interval of the spline
+    let m0 = slope(left, true) * span;
+    let m1 = slope(right, false) * span;
+    for (i, x) in buffer[start..end].iter_mut().enumerate() {
+        let t = (start + i - left) as f32 / span;
+        let (t2, t3) = (t * t, t * t * t);
+        *x = (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * m0
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * m1;
+    }
+}
+
+/// Crossfade of a forward AR prediction from the left context and a backward AR
+/// prediction from the right context
+fn interpolate_ar(buffer: &mut [f32], gap: Range<usize>, order: usize, context: usize, scratch: &mut ArScratch) {
+    let ArScratch { fit, forward, backward, reversed } = scratch;
+    let (start, end) = (gap.start, gap.end);
+    let len = end - start;
+    let has_forward = extrapolate(&buffer[start.saturating_sub(context)..start], order, len, fit, forward);
+    reversed.clear();
+    reversed.extend(buffer[end..(end + context).min(buffer.len())].iter().rev());
+    let has_backward = extrapolate(reversed, order, len, fit, backward);
+    backward.reverse();
+
+    match (has_forward, has_backward) {
+        (true, true) => {
+            for i in 0..len {
+                let w = (i + 1) as f32 / (len + 1) as f32;
+                buffer[start + i] = (1.0 - w) * forward[i] + w * backward[i];
+            }
+        }
+        (true, false) => buffer[start..end].copy_from_slice(forward),
+        (false, true) => buffer[start..end].copy_from_slice(backward),
+        (false, false) => interpolate_cubic(buffer, gap),
+    }
+}
+
+/// Predict `len` samples following `context` with an AR model fitted to it, into
+/// `prediction`. Returns `false` if no stable model could be fitted.
+fn extrapolate(context: &[f32], order: usize, len: usize, fit: &mut ArFit, prediction: &mut Vec<f32>) -> bool {
+    prediction.clear();
+    // Keep the least-squares fit well overdetermined so short contexts stay stable
+    let order = order.min(context.len() / 3);
+    if order == 0 || !ar_coefficients(context, order, fit) {
+        return false;
+    }
+    prediction.extend_from_slice(context);
+    for _ in 0..len {
+        let n = prediction.len();
+        let next = predict(&fit.coefficients, |k| prediction[n - k]);
+        prediction.push(next);
+    }
+    prediction.drain(..context.len());
+    // An unstable fit grows without bound; discard it rather than write garbage
+    let bound = 4.0 * peak_level(context);
+    prediction.iter().all(|x| x.is_finite() && x.abs() <= bound)
+}
+
+/// AR prediction `sum_k a_k x[n-k]`, where `past(k)` returns `x[n-k]`
+fn predict(coefficients: &[f64], past: impl Fn(usize) -> f32) -> f32 {
+    coefficients
+        .iter()
+        .enumerate()
+        .map(|(k, a)| a * past(k + 1) as f64)
+        .sum::<f64>() as f32
+}
+
+/// AR coefficients `a_1..a_p` (x[n] ≈ sum a_k x[n-k]) fitted by least squares over
+/// the signal (covariance method). Unlike the autocorrelation method this does not
+/// window the data, so predictions of steady tones do not decay. The coefficients
+/// are written to `fit.coefficients`; returns `false` for a silent signal.
+fn ar_coefficients(signal: &[f32], order: usize, fit: &mut ArFit) -> bool {
+    fit.coefficients.clear();
+    if signal.len() <= order {
+        return false;
+    }
+    fit.x.clear();
+    fit.x.extend(signal.iter().map(|&v| v as f64));
+    // Normal equations: phi[j][k] a_k = psi[j], with j, k the lags 1..=order
+    fit.phi.clear();
+    fit.phi.resize(order * order, 0.0);
+    fit.psi.clear();
+    fit.psi.resize(order, 0.0);
+    let ArFit { x, phi, psi, coefficients } = fit;
+    for n in order..x.len() {
+        for j in 0..order {
+            let xj = x[n - 1 - j];
+            psi[j] += x[n] * xj;
+            for k in j..order {
+                phi[j * order + k] += xj * x[n - 1 - k];
+            }
+        }
+    }
+    for j in 1..order {
+        for k in 0..j {
+            phi[j * order + k] = phi[k * order + j];
+        }
+    }
+    let trace: f64 = (0..order).map(|j| phi[j * order + j]).sum();
+    if trace <= 0.0 {
+        return false;
+    }
+    // Light ridge regularization keeps the system solvable for pure tones
+    for j in 0..order {
+        phi[j * order + j] += 1e-9 * trace / order as f64;
+    }
+    solve_linear_system(phi, psi, coefficients)
+}
+
+/// Solve `matrix * x = rhs` by Gaussian elimination with partial pivoting, for a
+/// square row-major `matrix`. The solution is written to `solution`; returns
+/// `false` if the matrix is singular.
+fn solve_linear_system(matrix: &mut [f64], rhs: &mut [f64], solution: &mut Vec<f64>) -> bool {
+    let n = rhs.len();
+    for col in 0..n {
+        let Some(pivot) = (col..n).max_by(|&a, &b| matrix[a * n + col].abs().total_cmp(&matrix[b * n + col].abs())) else {
+            return false;
+        };
+        if matrix[pivot * n + col].abs() < 1e-300 {
+            return false;
+        }
+        if pivot != col {
+            for k in 0..n {
+                matrix.swap(col * n + k, pivot * n + k);
+            }
+            rhs.swap(col, pivot);
+        }
+        for row in col + 1..n {
+            let factor = matrix[row * n + col] / matrix[col * n + col];
+            for k in col..n {
+                matrix[row * n + k] -= factor * matrix[col * n + k];
+            }
+            rhs[row] -= factor * rhs[col];
+        }
+    }
+    solution.clear();
+    solution.resize(n, 0.0);
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| matrix[row * n + k] * solution[k]).sum();
+        solution[row] = (rhs[row] - sum) / matrix[row * n + row];
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// A sung note: 220 Hz with a few harmonics
+    fn voice(len: usize, sample_rate: f32, amplitude: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let phase = 2.0 * PI * 220.0 * i as f32 / sample_rate;
+                amplitude * (0.7 * phase.sin() + 0.2 * (2.0 * phase).sin() + 0.1 * (3.0 * phase + 0.5).sin())
+            })
+            .collect()
+    }
+
+    fn clip(signal: &[f32], level: f32) -> Vec<f32> {
+        signal.iter().map(|x| x.clamp(-level, level)).collect()
+    }
+
+    fn rms_error(a: &[f32], b: &[f32]) -> f32 {
+        (a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f32>() / a.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_detects_clipped_runs_only() {
+        let sample_rate = 48000.0;
+        let clean = voice(4800, sample_rate, 1.0);
+        assert!(detect_clipping(&clean, &DeclipConfig::default()).is_empty());
+
+        let clipped = clip(&clean, 0.6);
+        let runs = detect_clipping(&clipped, &DeclipConfig::default());
+        assert!(!runs.is_empty());
+        for run in &runs {
+            assert!(run.len() >= 3);
+            assert!(clipped[run.clone()].iter().all(|x| x.abs() == 0.6));
+        }
+
+        let fixed_level = DeclipConfig { clip_level: Some(0.6), ..Default::default() };
+        assert_eq!(detect_clipping(&clipped, &fixed_level), runs);
+    }
+
+    #[test]
+    fn test_declip_reduces_error() {
+        let sample_rate = 16000.0;
+        let clean = voice(8000, sample_rate, 1.0);
+        let clipped = clip(&clean, 0.7);
+        let clipped_error = rms_error(&clean, &clipped);
+
+        for method in [InterpolationMethod::Cubic, InterpolationMethod::Autoregressive] {
+            let (repaired, stats) = declip(&clipped, DeclipConfig { method, ..Default::default() });
+            let repaired_error = rms_error(&clean, &repaired);
+            assert!(
+                repaired_error < 0.3 * clipped_error,
+                "{:?}: error {} -> {}", method, clipped_error, repaired_error
+            );
+            let clipped_samples = clipped.iter().filter(|x| x.abs() == 0.7).count();
+            assert!(stats.samples > 0 && stats.samples <= clipped_samples);
+            // Reconstructed peaks rise above the clip level
+            assert!(repaired.iter().any(|x| x.abs() > 0.75));
+        }
+    }
+
+    #[test]
+    fn test_declip_leaves_clean_signal_untouched() {
+        let clean = voice(8000, 16000.0, 0.5);
+        let (output, stats) = declip(&clean, DeclipConfig::default());
+        assert_eq!(output, clean);
+        assert_eq!(stats, RepairStats::default());
+    }
+
+    #[test]
+    fn test_auto_level_leaves_dc_and_held_values_alone() {
+        // Both sit at the signal peak, but only on one side of zero
+        let dc = vec![0.3; 2000];
+        let mut held = voice(4000, 16000.0, 0.5);
+        held[1000..1100].fill(0.8);
+        for signal in [dc, held] {
+            assert!(detect_clipping(&signal, &DeclipConfig::default()).is_empty());
+            let (output, stats) = declip(&signal, DeclipConfig::default());
+            assert_eq!(output, signal);
+            assert_eq!(stats, RepairStats::default());
+
+            let mut declipper = Declipper::new(DeclipConfig::default());
+            let mut streamed = signal.clone();
+            for chunk in streamed.chunks_mut(256) {
+                declipper.process_block(chunk);
+            }
+            assert_eq!(streamed, signal);
+        }
+    }
+
+    #[test]
+    fn test_declip_streaming_with_auto_level() {
+        let clean = voice(8000, 16000.0, 1.0);
+        let clipped = clip(&clean, 0.7);
+        let mut declipper = Declipper::new(DeclipConfig::default());
+        let mut streamed = clipped.clone();
+        for chunk in streamed.chunks_mut(512) {
+            declipper.process_block(chunk);
+        }
+        assert!(rms_error(&clean, &streamed) < 0.5 * rms_error(&clean, &clipped));
+    }
+
+    #[test]
+    fn test_declip_streaming_with_fixed_level() {
+        let sample_rate = 16000.0;
+        let clean = voice(8000, sample_rate, 1.0);
+        let clipped = clip(&clean, 0.7);
+        let mut declipper = Declipper::new(DeclipConfig { clip_level: Some(0.7), ..Default::default() });
+        let mut streamed = clipped.clone();
+        for chunk in streamed.chunks_mut(512) {
+            declipper.process_block(chunk);
+        }
+        assert!(rms_error(&clean, &streamed) < 0.5 * rms_error(&clean, &clipped));
+        assert_eq!(declipper.repaired_samples(), Some(declipper.stats().samples));
+    }
+
+    #[test]
+    fn test_click_repair() {
+        let sample_rate = 16000.0;
+        let clean = voice(8000, sample_rate, 0.5);
+        let mut damaged = clean.clone();
+        let click_positions = [1000, 3333, 6001];
+        for &position in &click_positions {
+            damaged[position] += 0.6;
+            damaged[position + 1] -= 0.4;
+        }
+
+        let clicks = detect_clicks(&damaged, &ClickRepairConfig::default());
+        assert_eq!(clicks.len(), click_positions.len(), "{:?}", clicks);
+        for (click, &position) in clicks.iter().zip(&click_positions) {
+            assert!(click.contains(&position) && click.contains(&(position + 1)));
+        }
+
+        let (repaired, stats) = repair_clicks(&damaged, ClickRepairConfig::default());
+        assert_eq!(stats.regions, 3);
+        assert!(rms_error(&clean, &repaired) < 0.05 * rms_error(&clean, &damaged));
+    }
+
+    #[test]
+    fn test_no_clicks_in_clean_voice() {
+        let clean = voice(8000, 16000.0, 0.5);
+        assert!(detect_clicks(&clean, &ClickRepairConfig::default()).is_empty());
+        let (output, stats) = repair_clicks(&clean, ClickRepairConfig::default());
+        assert_eq!(output, clean);
+        assert_eq!(stats.samples, 0);
+    }
+
+    #[test]
+    fn test_click_repair_streaming() {
+        let clean = voice(8000, 16000.0, 0.5);
+        let mut damaged = clean.clone();
+        damaged[2500] = 0.9;
+        let mut repairer = ClickRepairer::new(ClickRepairConfig::default());
+        for chunk in damaged.chunks_mut(1024) {
+            repairer.process_block(chunk);
+        }
+        assert_eq!(repairer.stats().regions, 1);
+        assert!((damaged[2500] - clean[2500]).abs() < 0.02);
+
+        repairer.reset();
+        assert_eq!(repairer.repaired_samples(), Some(0));
+    }
+
+    #[test]
+    fn test_ar_extrapolation_follows_sine() {
+        let sine: Vec<f32> = (0..300).map(|i| (i as f32 * 0.1).sin()).collect();
+        let mut predicted = Vec::new();
+        assert!(extrapolate(&sine[..256], 8, 44, &mut ArFit::default(), &mut predicted));
+        for (p, expected) in predicted.iter().zip(&sine[256..]) {
+            assert!((p - expected).abs() < 1e-2);
+        }
+    }
+}
@@ -25,6 +25,7 @@
 //! let config = SpectralGateConfig {
 //!     noise_threshold_db: 6.0, // Attenuate signals 6 dB below noise floor
 //!     smoothing_window: 3,       // Smooth gating decisions across 3 frequency bins
+//!     ..Default::default()
 //! };
 //!
 //! // Create the gate
@@ -37,29 +38,46 @@
 //!
 //! ## Streaming/Real-time Usage
 //!
-//! For real-time applications, process audio in fixed-size chunks:
+//! For real-time applications, feed chunks of any size as they arrive. The gate works
+//! on overlapping windowed frames carried across chunks, so the output lags the input
+//! by [`SpectralGate::latency`] samples:
 //!
 //! ```
 //! use audio_cleaning::spectral_gating::{SpectralGate, SpectralGateConfig};
 //! use audio_cleaning::Spectrum;
 //!
 //! let noise_profile = Spectrum::from_waveform(&vec![0.01; 1024]);
-//! let config = SpectralGateConfig::default();
-//! let gate = SpectralGate::new(noise_profile, config);
+//! let config = SpectralGateConfig { block_size: Some(512), ..Default::default() };
+//! let mut gate = SpectralGate::new(noise_profile, config);
 //!
 //! // Process chunks as they arrive
-//! let chunk1 = vec![0.1; 1024];
-//! let chunk2 = vec![0.2; 1024];
+//! let chunk1 = vec![0.1; 256];
+//! let chunk2 = vec![0.2; 300];
 //!
-//! let cleaned1 = gate.process(&chunk1);
-//! let cleaned2 = gate.process(&chunk2);
+//! let mut cleaned1 = vec![0.0; chunk1.len()];
+//! let mut cleaned2 = vec![0.0; chunk2.len()];
+//! gate.process_into(&chunk1, &mut cleaned1);
+//! gate.process_into(&chunk2, &mut cleaned2);
+//! assert_eq!(gate.latency(), 512);
 //! ```
 
 use rustfft::num_complex::Complex32;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use crate::filters::{de_emphasis, pre_emphasis, DeEmphasis, DeEmphasisConfig, PreEmphasis, PreEmphasisConfig};
+use crate::noise_reduction::NoiseReducer;
+use crate::processor::AudioProcessor;
+use crate::stft::StreamingStft;
+use crate::util::db_to_linear;
 use crate::Spectrum;
 
+/// Largest pre-emphasis coefficient the gate uses; de-emphasis has its pole at the
+/// coefficient, so 1 and above never decays
+const MAX_EMPHASIS_COEFFICIENT: f32 = 0.999;
+
 /// Configuration for spectral gating
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SpectralGateConfig {
     /// Threshold in dB below noise floor for attenuation.
     /// Signals below (noise_level * threshold_db) will be attenuated.
@@ -70,6 +88,26 @@ pub struct SpectralGateConfig {
     /// Higher values provide smoother transitions but less precise gating.
     /// Default: 1 (no smoothing)
     pub smoothing_window: usize,
+
+    /// Frame length and FFT size used when streaming (`process_block` /
+    /// `process_into`). Frames are windowed and overlap by half, and the streaming
+    /// output lags the input by one frame. Rounded down to an even size. `None` uses
+    /// the length of the noise profile.
+    /// Default: None
+    pub block_size: Option<usize>,
+
+    /// Gate in the pre-emphasized domain: pre-emphasize the signal (and weight the
+    /// noise profile by the same response), gate, then de-emphasize. Flattening the
+    /// spectral tilt reduces leakage of the strong low harmonics of low voices into
+    /// the weaker upper bins; the windowed streaming frames already keep most of it out.
+    /// Default: false
+    pub pre_emphasis: bool,
+
+    /// Pre-emphasis coefficient used when `pre_emphasis` is set (see
+    /// [`PreEmphasisConfig`]). The gate clamps it to 0.0-0.999, since de-emphasis is
+    /// unstable for coefficients of 1 or more.
+    /// Default: 0.97
+    pub pre_emphasis_coefficient: f32,
 }
 
 impl Default for SpectralGateConfig {
@@ -77,6 +115,9 @@ impl Default for SpectralGateConfig {
         Self {
             noise_threshold_db: 6.0,
             smoothing_window: 1,
+            block_size: None,
+            pre_emphasis: false,
+            pre_emphasis_coefficient: 0.97,
         }
     }
 }
@@ -86,10 +127,21 @@ impl Default for SpectralGateConfig {
 /// A spectral gate attenuates frequency components that fall below a threshold
 /// relative to a noise profile. This is useful for removing background noise
 /// while preserving the desired signal.
+///
+/// The noise profile may have any length; its noise level is mapped onto the bins
+/// of each frame. Streaming uses a windowed STFT with overlap-add whose frames carry
+/// across calls, so chunk edges do not click. Its FFT plans and buffers are allocated
+/// once, so [`SpectralGate::process_into`] and `process_block` never allocate.
 pub struct SpectralGate {
     noise_spectrum: Spectrum,
+    /// Smoothed noise magnitudes at the resolution of the noise profile
     noise_magnitudes: Vec<f32>,
     config: SpectralGateConfig,
+    stft: StreamingStft,
+    /// Expected noise magnitude of each bin of a windowed streaming frame
+    frame_noise_magnitudes: Vec<f32>,
+    /// Streaming pre-/de-emphasis filters, when gating in the pre-emphasized domain
+    emphasis: Option<(PreEmphasis, DeEmphasis)>,
 }
 
 impl SpectralGate {
@@ -102,11 +154,30 @@ impl SpectralGate {
     /// # Returns
     /// A new `SpectralGate` instance
     pub fn new(noise_spectrum: Spectrum, config: SpectralGateConfig) -> Self {
+        let config = SpectralGateConfig {
+            pre_emphasis_coefficient: stable_emphasis_coefficient(config.pre_emphasis_coefficient),
+            ..config
+        };
         let noise_magnitudes = Self::compute_noise_magnitudes_static(&noise_spectrum, &config);
+        let stft = StreamingStft::new(config.block_size.unwrap_or(noise_spectrum.n));
+        let frame_size = stft.frame_size();
+        // The window scales noise magnitudes by its RMS relative to a rectangular block
+        let window_rms = (stft.window_energy() / frame_size as f32).sqrt();
+        let frame_noise_magnitudes = map_noise_magnitudes(&noise_magnitudes, frame_size)
+            .into_iter()
+            .map(|m| m * window_rms)
+            .collect();
+        let emphasis = config.pre_emphasis.then(|| {
+            let coefficient = config.pre_emphasis_coefficient;
+            (PreEmphasis::new(PreEmphasisConfig { coefficient }), DeEmphasis::new(DeEmphasisConfig { coefficient }))
+        });
         Self {
             noise_spectrum,
             noise_magnitudes,
             config,
+            stft,
+            frame_noise_magnitudes,
+            emphasis,
         }
     }
 
@@ -125,6 +196,9 @@ impl SpectralGate {
     /// 2. Applies gating based on noise profile
     /// 3. Returns time-domain result
     ///
+    /// The whole input is gated as one block. This allocates; for real-time use see
+    /// [`SpectralGate::process_into`].
+    ///
     /// # Arguments
     /// * `samples` - Input audio samples to process
     ///
@@ -139,45 +213,76 @@ impl SpectralGate {
         let threshold_multiplier = db_to_linear(self.config.noise_threshold_db);
 
         // Transform to frequency domain
-        let mut spectrum = Spectrum::from_waveform(samples);
+        let emphasized = self.config.pre_emphasis.then(|| {
+            pre_emphasis(samples, PreEmphasisConfig { coefficient: self.config.pre_emphasis_coefficient })
+        });
+        let mut spectrum = Spectrum::from_waveform(emphasized.as_deref().unwrap_or(samples));
 
         // Apply spectral gating to each frequency bin
-        self.apply_gate(&mut spectrum, threshold_multiplier);
+        let noise = map_noise_magnitudes(&self.noise_magnitudes, spectrum.n);
+        apply_gate(&mut spectrum.complex, &noise, threshold_multiplier);
 
         // Transform back to time domain and trim to original length
         let output = spectrum.to_time_domain();
-        output[..samples.len()].to_vec()
-    }
-
-    /// Apply gating to a spectrum in-place
-    fn apply_gate(&self, spectrum: &mut Spectrum, threshold_multiplier: f32) {
-        for (i, complex_sample) in spectrum.complex.iter_mut().enumerate() {
-            let noise_level = self.noise_magnitudes.get(i).copied().unwrap_or(0.0);
-            let signal_magnitude = complex_sample.norm();
-            
-            // Attenuation threshold
-            let threshold = noise_level * threshold_multiplier;
-            
-            if signal_magnitude < threshold {
-                // Apply soft gating: gradually reduce gain
-                let gain = if noise_level > 0.0 {
-                    (signal_magnitude / threshold).max(0.0).min(1.0)
-                } else {
-                    1.0
-                };
-                *complex_sample = Complex32::new(
-                    complex_sample.re * gain,
-                    complex_sample.im * gain,
-                );
-            }
+        let output = &output[..samples.len()];
+        if self.config.pre_emphasis {
+            de_emphasis(output, DeEmphasisConfig { coefficient: self.config.pre_emphasis_coefficient })
+        } else {
+            output.to_vec()
+        }
+    }
+
+    /// Gate the next chunk of a stream from `input` into `output` without allocating
+    ///
+    /// Chunks may have any length. The output lags the input by
+    /// [`SpectralGate::latency`] samples.
+    ///
+    /// # Panics
+    /// If `input` and `output` differ in length
+    pub fn process_into(&mut self, input: &[f32], output: &mut [f32]) {
+        assert_eq!(input.len(), output.len(), "input and output lengths differ");
+        output.copy_from_slice(input);
+        self.process_in_place(output);
+    }
+
+    /// Frame length and FFT size used by [`SpectralGate::process_into`] and `process_block`
+    pub fn block_size(&self) -> usize {
+        self.stft.frame_size()
+    }
+
+    /// Delay of the streaming output in samples (one frame)
+    pub fn latency(&self) -> usize {
+        self.stft.latency()
+    }
+
+    fn process_in_place(&mut self, samples: &mut [f32]) {
+        let threshold_multiplier = db_to_linear(self.config.noise_threshold_db);
+        if let Some((pre, _)) = self.emphasis.as_mut() {
+            pre.process_block(samples);
+        }
+        let noise_magnitudes = &self.frame_noise_magnitudes;
+        self.stft.process_block(samples, |spectrum| {
+            apply_gate(spectrum, noise_magnitudes, threshold_multiplier);
+        });
+        if let Some((_, de)) = self.emphasis.as_mut() {
+            de.process_block(samples);
         }
     }
 
-    /// Compute magnitude spectrum from noise profile with optional smoothing
+    /// Compute magnitude spectrum from noise profile with optional pre-emphasis
+    /// weighting and smoothing
     fn compute_noise_magnitudes_static(noise_spectrum: &Spectrum, config: &SpectralGateConfig) -> Vec<f32> {
+        let n = noise_spectrum.n.max(1) as f32;
         let magnitudes: Vec<f32> = noise_spectrum.complex
             .iter()
-            .map(|c| c.norm())
+            .enumerate()
+            .map(|(k, c)| {
+                if config.pre_emphasis {
+                    c.norm() * pre_emphasis_response(config.pre_emphasis_coefficient, 2.0 * PI * k as f32 / n)
+                } else {
+                    c.norm()
+                }
+            })
             .collect();
 
         if config.smoothing_window <= 1 {
@@ -207,12 +312,12 @@ impl SpectralGate {
     /// Update the noise profile
     ///
     /// This allows adapting the gate to changing noise conditions in real-time applications.
+    /// It allocates, so call it outside the audio callback.
     ///
     /// # Arguments
     /// * `noise_spectrum` - New noise profile
     pub fn update_noise_profile(&mut self, noise_spectrum: Spectrum) {
-        self.noise_magnitudes = Self::compute_noise_magnitudes_static(&noise_spectrum, &self.config);
-        self.noise_spectrum = noise_spectrum;
+        *self = Self::new(noise_spectrum, self.config.clone());
     }
 
     /// Get a reference to the current noise profile
@@ -225,23 +330,113 @@ impl SpectralGate {
         &self.config
     }
 
-    /// Update the configuration
+    /// Update the configuration. It allocates, so call it outside the audio callback.
     pub fn update_config(&mut self, config: SpectralGateConfig) {
-        self.noise_magnitudes = Self::compute_noise_magnitudes_static(&self.noise_spectrum, &config);
-        self.config = config;
+        let noise_spectrum = std::mem::replace(&mut self.noise_spectrum, Spectrum { complex: Vec::new(), n: 0 });
+        *self = Self::new(noise_spectrum, config);
+    }
+}
+
+/// Streaming gates overlapping frames with the preallocated FFTs (see
+/// [`SpectralGate::process_into`]); batch processing gates the whole signal at once.
+impl AudioProcessor for SpectralGate {
+    fn name(&self) -> &'static str {
+        "spectral_gate"
+    }
+
+    fn process_block(&mut self, samples: &mut [f32]) {
+        self.process_in_place(samples);
+    }
+
+    fn reset(&mut self) {
+        self.stft.reset();
+        if let Some((pre, de)) = self.emphasis.as_mut() {
+            pre.reset();
+            de.reset();
+        }
+    }
+
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        SpectralGate::process(self, samples)
     }
 }
 
-/// Convert decibels to linear scale
+impl NoiseReducer for SpectralGate {
+    fn noise_profile(&self) -> &Spectrum {
+        SpectralGate::noise_profile(self)
+    }
+
+    fn update_noise_profile(&mut self, noise_spectrum: Spectrum) {
+        SpectralGate::update_noise_profile(self, noise_spectrum);
+    }
+}
+
+/// Apply gating to the bins of a full (two-sided) spectrum in place
+fn apply_gate(spectrum: &mut [Complex32], noise_magnitudes: &[f32], threshold_multiplier: f32) {
+    for (i, complex_sample) in spectrum.iter_mut().enumerate() {
+        let noise_level = noise_magnitudes.get(i).copied().unwrap_or(0.0);
+        let signal_magnitude = complex_sample.norm();
+
+        // Attenuation threshold
+        let threshold = noise_level * threshold_multiplier;
+
+        if signal_magnitude < threshold {
+            // Apply soft gating: gradually reduce gain
+            let gain = if noise_level > 0.0 {
+                (signal_magnitude / threshold).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            *complex_sample *= gain;
+        }
+    }
+}
+
+/// Clamp a pre-emphasis coefficient to the range where de-emphasis is stable
+fn stable_emphasis_coefficient(coefficient: f32) -> f32 {
+    if coefficient.is_nan() {
+        return 0.0;
+    }
+    coefficient.clamp(0.0, MAX_EMPHASIS_COEFFICIENT)
+}
+
+/// Magnitude response of pre-emphasis with the given coefficient at angular
+/// frequency `omega` (radians per sample): `|1 - alpha * e^(-j omega)|`
+fn pre_emphasis_response(coefficient: f32, omega: f32) -> f32 {
+    (1.0 + coefficient * coefficient - 2.0 * coefficient * omega.cos()).max(0.0).sqrt()
+}
+
+/// Map noise magnitudes of a profile of any length onto the `n` bins of a block
 ///
-/// Formula: linear = 10^(db/20)
-fn db_to_linear(db: f32) -> f32 {
-    10.0_f32.powf(db / 20.0)
+/// Each block bin takes the mean noise power of the profile bins it covers, scaled
+/// for the block length (the FFT magnitude of noise grows with the square root of
+/// the length). A profile of length `n` maps onto itself.
+fn map_noise_magnitudes(profile: &[f32], n: usize) -> Vec<f32> {
+    let n_profile = profile.len();
+    if n_profile == 0 || n == 0 {
+        return vec![0.0; n];
+    }
+    if n_profile == n {
+        return profile.to_vec();
+    }
+    let ratio = n_profile as f32 / n as f32;
+    let positive: Vec<f32> = (0..=n / 2)
+        .map(|bin| {
+            let center = bin as f32 * ratio;
+            let start = (center - 0.5 * ratio).round().max(0.0) as usize;
+            let end = ((center + 0.5 * ratio).round() as usize).max(start + 1).min(n_profile / 2 + 1);
+            let start = start.min(end - 1);
+            let power = profile[start..end].iter().map(|m| m * m).sum::<f32>() / (end - start) as f32;
+            (power / ratio).sqrt()
+        })
+        .collect();
+    (0..n).map(|bin| positive[Ord::min(bin, n - bin)]).collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sound_synth::white_noise;
 
     #[test]
     fn test_spectral_gate_empty_input() {
@@ -341,6 +536,7 @@ mod tests {
         let new_config = SpectralGateConfig {
             noise_threshold_db: 12.0,
             smoothing_window: 5,
+            ..Default::default()
         };
         
         gate.update_config(new_config);
@@ -357,11 +553,13 @@ mod tests {
         let config_no_smoothing = SpectralGateConfig {
             noise_threshold_db: 6.0,
             smoothing_window: 1,
+            ..Default::default()
         };
         
         let config_with_smoothing = SpectralGateConfig {
             noise_threshold_db: 6.0,
             smoothing_window: 5,
+            ..Default::default()
         };
         
         let noise1 = Spectrum::from_waveform(&noise_samples);
@@ -413,4 +611,174 @@ mod tests {
             output_energy
         );
     }
+
+    #[test]
+    fn test_process_into_independent_of_chunking() {
+        let noise: Vec<f32> = (0..256).map(|i| 0.05 * ((i * 7919 % 97) as f32 / 48.0 - 1.0)).collect();
+        let signal: Vec<f32> = (0..2000).map(|i| (i as f32 * 0.3).sin() + 0.02 * ((i * 31 % 13) as f32 - 6.0)).collect();
+        let mut gate = SpectralGate::with_defaults(Spectrum::from_waveform(&noise));
+        assert_eq!(gate.block_size(), 256);
+        assert_eq!(gate.latency(), 256);
+
+        let mut whole = vec![0.0; signal.len()];
+        gate.process_into(&signal, &mut whole);
+        gate.reset();
+        let mut chunked = signal.clone();
+        for chunk in chunked.chunks_mut(97) {
+            gate.process_block(chunk);
+        }
+        for (a, b) in whole.iter().zip(&chunked) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_streaming_has_no_discontinuities_at_block_edges() {
+        // A steady tone over noise: every frame gates the noise bins similarly, so the
+        // error should be spread evenly rather than concentrated at frame edges
+        let sample_rate = 8000.0;
+        let noise = white_noise(8192, 0.05, 5);
+        let config = SpectralGateConfig { block_size: Some(256), ..Default::default() };
+        let mut gate = SpectralGate::new(Spectrum::from_waveform(&noise), config);
+        let tone: Vec<f32> = (0..8000).map(|i| 0.5 * (2.0 * PI * 440.0 * i as f32 / sample_rate).sin()).collect();
+        let mut output: Vec<f32> = tone.iter().zip(white_noise(8000, 0.05, 11)).map(|(a, b)| a + b).collect();
+        for chunk in output.chunks_mut(256) {
+            gate.process_block(chunk);
+        }
+
+        // The largest step between neighbouring samples stays close to the tone's own
+        let max_step = |signal: &[f32]| signal.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0f32, f32::max);
+        let latency = gate.latency();
+        let settled = &output[2 * latency..];
+        let tone_step = max_step(&tone);
+        assert!(max_step(settled) < 1.5 * tone_step, "{} vs {}", max_step(settled), tone_step);
+    }
+
+    #[test]
+    fn test_noise_profile_mapped_to_block_size() {
+        // A profile of the same length maps onto itself
+        let profile: Vec<f32> = (0..16).map(|i| i as f32).collect();
+        assert_eq!(map_noise_magnitudes(&profile, 16), profile);
+
+        // White noise magnitudes scale with the square root of the FFT length
+        let flat = vec![2.0; 4096];
+        let mapped = map_noise_magnitudes(&flat, 1024);
+        assert_eq!(mapped.len(), 1024);
+        assert!(mapped.iter().all(|m| (m - 1.0).abs() < 1e-5));
+
+        // Spectral shape is kept: the bin of a tonal noise peak lines up
+        let mut peaked = vec![0.1; 4096];
+        peaked[400] = 10.0;
+        peaked[4096 - 400] = 10.0;
+        let mapped = map_noise_magnitudes(&peaked, 1024);
+        let peak = (0..=512).max_by(|&a, &b| mapped[a].total_cmp(&mapped[b])).unwrap();
+        assert_eq!(peak, 100);
+        assert_eq!(mapped[1024 - 100], mapped[100]);
+    }
+
+    #[test]
+    fn test_batch_process_maps_profile_to_input_length() {
+        // The profile is rescaled to the FFT length of the whole input, so noise is
+        // gated as much in longer or shorter inputs as in one of the profile's length
+        let gate = SpectralGate::with_defaults(Spectrum::from_waveform(&white_noise(1024, 0.05, 3)));
+        let residual = |len: usize| {
+            let noise = white_noise(len, 0.05, 7);
+            let tone: Vec<f32> = (0..len).map(|i| 0.5 * (2.0 * PI * 500.0 * i as f32 / 8000.0).sin()).collect();
+            let noisy: Vec<f32> = tone.iter().zip(&noise).map(|(a, b)| a + b).collect();
+            let error: f32 = gate.process(&noisy).iter().zip(&tone).map(|(a, b)| (a - b).powi(2)).sum();
+            error / noise.iter().map(|x| x * x).sum::<f32>()
+        };
+        let reference = residual(1024);
+        assert!(reference < 0.7, "{}", reference);
+        for len in [256, 8192] {
+            assert!((residual(len) - reference).abs() < 0.1, "{}: {} vs {}", len, residual(len), reference);
+        }
+    }
+
+    #[test]
+    fn test_process_into_in_blocks() {
+        // A long noise profile and short blocks, as in a live audio callback
+        let noise: Vec<f32> = (0..8192).map(|i| 0.01 * ((i * 7919 % 101) as f32 / 50.0 - 1.0)).collect();
+        let config = SpectralGateConfig { block_size: Some(512), ..Default::default() };
+        let mut gate = SpectralGate::new(Spectrum::from_waveform(&noise), config);
+        assert_eq!(gate.block_size(), 512);
+
+        let latency = gate.latency();
+        let signal: Vec<f32> = (0..2000).map(|i| (2.0 * PI * 500.0 * i as f32 / 8000.0).sin()).collect();
+        let mut output = vec![0.0; signal.len()];
+        gate.process_into(&signal, &mut output);
+        let input_energy: f32 = signal[..signal.len() - latency].iter().map(|x| x * x).sum();
+        let output_energy: f32 = output[latency..].iter().map(|x| x * x).sum();
+        assert!(output_energy > 0.9 * input_energy);
+
+        // Noise alone is attenuated, once the tone has left the frames
+        gate.reset();
+        let mut gated_noise = vec![0.0; 2048];
+        gate.process_into(&noise[..2048], &mut gated_noise);
+        let noise_energy: f32 = noise[..2048 - latency].iter().map(|x| x * x).sum();
+        let gated_energy: f32 = gated_noise[latency..].iter().map(|x| x * x).sum();
+        assert!(gated_energy < 0.5 * noise_energy);
+    }
+
+    #[test]
+    fn test_pre_emphasis_transparent_without_noise() {
+        let noise = Spectrum::from_waveform(&[0.0; 512]);
+        let config = SpectralGateConfig { pre_emphasis: true, ..Default::default() };
+        let mut gate = SpectralGate::new(noise, config);
+        let signal: Vec<f32> = (0..2000).map(|i| (i as f32 * 0.05).sin() + 0.2 * (i as f32 * 0.9).sin()).collect();
+
+        let batch = gate.process(&signal);
+        assert!(signal.iter().zip(&batch).all(|(a, b)| (a - b).abs() < 1e-3));
+        let mut streamed = vec![0.0; signal.len()];
+        gate.process_into(&signal, &mut streamed);
+        assert!(signal.iter().zip(&streamed[gate.latency()..]).all(|(a, b)| (a - b).abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_pre_emphasis_coefficient_clamped() {
+        let noise = Spectrum::from_waveform(&[0.0; 256]);
+        for (coefficient, expected) in [(1.5, MAX_EMPHASIS_COEFFICIENT), (-0.5, 0.0), (f32::NAN, 0.0), (0.9, 0.9)] {
+            let config = SpectralGateConfig { pre_emphasis: true, pre_emphasis_coefficient: coefficient, ..Default::default() };
+            let mut gate = SpectralGate::new(noise.clone(), config);
+            assert_eq!(gate.config().pre_emphasis_coefficient, expected);
+
+            // Without noise the gate is transparent, so the output stays bounded
+            let mut output = vec![0.5; 4096];
+            gate.process_block(&mut output);
+            assert!(output.iter().all(|x| x.is_finite() && x.abs() < 1.0), "{}", coefficient);
+        }
+    }
+
+    #[test]
+    fn test_pre_emphasis_does_not_raise_high_band_residual() {
+        // Low voice: 98.7 Hz with harmonics falling at 12 dB/octave, plus white noise
+        let sample_rate = 16000.0;
+        let len = 16384;
+        let clean: Vec<f32> = (0..len)
+            .map(|i| {
+                let t = i as f32 / sample_rate;
+                (1..=20).map(|h| (2.0 * PI * 98.7 * h as f32 * t).sin() / (h * h) as f32).sum::<f32>()
+            })
+            .collect();
+        let noisy: Vec<f32> = clean.iter().zip(white_noise(len, 0.01, 7)).map(|(a, b)| a + b).collect();
+        let profile = Spectrum::from_waveform(&white_noise(len, 0.01, 99));
+
+        // Residual noise energy above 1.5 kHz, where leakage of the strong low
+        // harmonics could hold the gate open. The windowed frames already keep most
+        // of it out, so pre-emphasis must at least not make it worse.
+        let residual_above_1500_hz = |pre_emphasis: bool, block_size: usize| {
+            let config = SpectralGateConfig { block_size: Some(block_size), pre_emphasis, ..Default::default() };
+            let mut gate = SpectralGate::new(profile.clone(), config);
+            let mut output = vec![0.0; len];
+            gate.process_into(&noisy, &mut output);
+            let error: Vec<f32> = output[gate.latency()..].iter().zip(&clean).map(|(a, b)| a - b).collect();
+            let first_bin = (1500.0 / sample_rate * error.len() as f32) as usize;
+            Spectrum::from_waveform(&error).magnitudes()[first_bin..].iter().map(|m| m * m).sum::<f32>()
+        };
+        for block_size in [256, 1024] {
+            let plain = residual_above_1500_hz(false, block_size);
+            let emphasized = residual_above_1500_hz(true, block_size);
+            assert!(emphasized < 1.01 * plain, "{}: {} vs {}", block_size, emphasized, plain);
+        }
+    }
 }
@@ -0,0 +1,412 @@
+//! Short-Time Fourier Transform
+//!
+//! [`Spectrogram`](crate::Spectrogram) keeps only magnitudes, so nothing can be
+//! resynthesised from it, and [`Spectrum`] does not know its sample rate. [`Stft`]
+//! keeps the complex frames together with the window, hop size and sample rate. It
+//! converts bins and frames to Hz and seconds, and its inverse reconstructs the signal.
+//! A time-frequency cleaning stage can modify `frames` and call [`Stft::inverse`].
+//!
+//! Real-time stages use the crate-internal `StreamingStft`, which carries its frames
+//! across blocks instead.
+
+use rustfft::num_complex::{Complex, Complex32};
+use rustfft::{Fft, FftDirection};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::sync::Arc;
+use crate::types::{cached_fft, Spectrum};
+
+/// Analysis window applied to each frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowType {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+}
+
+impl WindowType {
+    /// Periodic window coefficients of the given length. Periodic windows overlap-add
+    /// to a constant at hops of `len / 2` (Hann, Hamming) or `len` (rectangular).
+    pub fn coefficients(&self, len: usize) -> Vec<f32> {
+        let phase = |n: usize| 2.0 * PI * n as f32 / len as f32;
+        (0..len)
+            .map(|n| match self {
+                WindowType::Rectangular => 1.0,
+                WindowType::Hann => 0.5 - 0.5 * phase(n).cos(),
+                WindowType::Hamming => 0.54 - 0.46 * phase(n).cos(),
+            })
+            .collect()
+    }
+}
+
+/// Configuration for the STFT
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StftConfig {
+    /// Number of samples per frame (and FFT size). Default: 1024
+    pub window_size: usize,
+    /// Number of samples between successive frames. It must not exceed `window_size`
+    /// for the inverse to cover every sample. Default: 256
+    pub hop_size: usize,
+    /// Analysis (and synthesis) window. Default: Hann
+    pub window: WindowType,
+}
+
+impl Default for StftConfig {
+    fn default() -> Self {
+        Self {
+            window_size: 1024,
+            hop_size: 256,
+            window: WindowType::Hann,
+        }
+    }
+}
+
+/// Complex short-time spectra of a real signal, with their time and frequency axes
+///
+/// Frame `t` is centred on sample `t * hop_size`, and the signal is zero-padded at
+/// both ends. Each frame holds the `window_size / 2 + 1` non-negative frequency bins.
+#[derive(Debug, Clone)]
+pub struct Stft {
+    /// One complex spectrum per frame, lowest bin first
+    pub frames: Vec<Vec<Complex<f32>>>,
+    pub sample_rate: f32,
+    pub config: StftConfig,
+    /// Length of the analysed signal, restored by the inverse
+    signal_len: usize,
+}
+
+impl Stft {
+    /// Compute the STFT of a signal
+    pub fn from_waveform(signal: &[f32], sample_rate: f32, config: StftConfig) -> Self {
+        let window_size = config.window_size.max(2);
+        let hop = config.hop_size.clamp(1, window_size);
+        let config = StftConfig { window_size, hop_size: hop, ..config };
+        let window = config.window.coefficients(window_size);
+        let half = window_size / 2;
+        let n_bins = window_size / 2 + 1;
+
+        // Enough frames that the last one is centred at or beyond the final sample
+        let n_frames = if signal.is_empty() { 0 } else { (signal.len() - 1).div_ceil(hop) + 1 };
+        let mut frame = vec![0.0f32; window_size];
+        let frames = (0..n_frames)
+            .map(|t| {
+                for (i, x) in frame.iter_mut().enumerate() {
+                    let sample = (t * hop + i).checked_sub(half).and_then(|n| signal.get(n));
+                    *x = sample.copied().unwrap_or(0.0) * window[i];
+                }
+                let mut spectrum = Spectrum::from_waveform(&frame).complex;
+                spectrum.truncate(n_bins);
+                spectrum
+            })
+            .collect();
+
+        Self { frames, sample_rate, config, signal_len: signal.len() }
+    }
+
+    /// Compute the STFT with the default configuration
+    pub fn with_defaults(signal: &[f32], sample_rate: f32) -> Self {
+        Self::from_waveform(signal, sample_rate, StftConfig::default())
+    }
+
+    /// Number of frames
+    pub fn n_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Number of frequency bins per frame (`window_size / 2 + 1`)
+    pub fn n_bins(&self) -> usize {
+        self.config.window_size / 2 + 1
+    }
+
+    /// Length of the analysed signal in samples
+    pub fn signal_len(&self) -> usize {
+        self.signal_len
+    }
+
+    /// Centre frequency of bin `k` in Hz
+    pub fn bin_frequency(&self, k: usize) -> f32 {
+        k as f32 * self.sample_rate / self.config.window_size as f32
+    }
+
+    /// Time of the centre of frame `t` in seconds
+    pub fn frame_time(&self, t: usize) -> f32 {
+        (t * self.config.hop_size) as f32 / self.sample_rate
+    }
+
+    /// The window coefficients
+    pub fn window(&self) -> Vec<f32> {
+        self.config.window.coefficients(self.config.window_size)
+    }
+
+    /// Magnitude of every bin, frame by frame
+    pub fn magnitudes(&self) -> Vec<Vec<f32>> {
+        self.frames.iter().map(|frame| frame.iter().map(|c| c.norm()).collect()).collect()
+    }
+
+    /// Resynthesise the signal by weighted overlap-add
+    ///
+    /// Each inverse-transformed frame is windowed again and the sum is divided by the
+    /// overlap-added squared window. Unmodified frames therefore give back the
+    /// original signal (to rounding) for any window whose overlap covers every sample,
+    /// including all COLA windows. Modified frames give the least-squares estimate.
+    pub fn inverse(&self) -> Vec<f32> {
+        let window_size = self.config.window_size;
+        let hop = self.config.hop_size;
+        let half = window_size / 2;
+        let window = self.window();
+        let mut output = vec![0.0f32; self.signal_len];
+        let mut norm = vec![0.0f32; self.signal_len];
+
+        for (t, frame) in self.frames.iter().enumerate() {
+            let time = hermitian_spectrum(frame, window_size).to_time_domain();
+            for (i, (&x, &w)) in time.iter().zip(&window).enumerate() {
+                let Some(n) = (t * hop + i).checked_sub(half).filter(|&n| n < self.signal_len) else {
+                    continue;
+                };
+                output[n] += x * w;
+                norm[n] += w * w;
+            }
+        }
+        for (x, n) in output.iter_mut().zip(&norm) {
+            *x = if *n > 1e-8 { *x / n } else { 0.0 };
+        }
+        output
+    }
+}
+
+/// Streaming overlap-add analysis and resynthesis for real-time stages
+///
+/// Frames of `frame_size` samples advance by half a frame and are weighted by a
+/// periodic square-root Hann window before the FFT and again after the inverse, so
+/// an unmodified spectrum reconstructs the input exactly. Input and overlapping
+/// output are carried across calls, so blocks of any length join without seams; the
+/// output lags the input by `frame_size` samples. FFT plans and buffers are allocated
+/// up front, so [`StreamingStft::process_block`] does not allocate.
+pub(crate) struct StreamingStft {
+    frame_size: usize,
+    window: Vec<f32>,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    spectrum: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+    /// The latest `frame_size` input samples
+    input: Vec<f32>,
+    /// Overlap-added output of the frames so far, from the start of the current frame
+    overlap: Vec<f32>,
+    /// Finished output, handed out while the next hop of input arrives
+    ready: Vec<f32>,
+    /// Input samples received since the last frame
+    fill: usize,
+}
+
+impl StreamingStft {
+    /// Create a streaming STFT; `frame_size` is rounded down to an even size of at least 2
+    pub(crate) fn new(frame_size: usize) -> Self {
+        let frame_size = frame_size.max(2) & !1;
+        let fft = cached_fft(frame_size, FftDirection::Forward);
+        let ifft = cached_fft(frame_size, FftDirection::Inverse);
+        let scratch_len = fft.get_inplace_scratch_len().max(ifft.get_inplace_scratch_len());
+        // Periodic square-root Hann: the squared windows of overlapping frames sum to one
+        let window = WindowType::Hann.coefficients(frame_size).iter().map(|w| w.sqrt()).collect();
+        Self {
+            frame_size,
+            window,
+            fft,
+            ifft,
+            spectrum: vec![Complex32::new(0.0, 0.0); frame_size],
+            scratch: vec![Complex32::new(0.0, 0.0); scratch_len],
+            input: vec![0.0; frame_size],
+            overlap: vec![0.0; frame_size],
+            ready: vec![0.0; frame_size / 2],
+            fill: 0,
+        }
+    }
+
+    /// Frame length and FFT size in samples
+    pub(crate) fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// Sum of the squared window coefficients, i.e. the power gain of a windowed
+    /// frame for white noise relative to one sample
+    pub(crate) fn window_energy(&self) -> f32 {
+        self.window.iter().map(|w| w * w).sum()
+    }
+
+    /// Delay of the streaming output in samples
+    pub(crate) fn latency(&self) -> usize {
+        self.frame_size
+    }
+
+    /// Process the next block of a stream in place
+    ///
+    /// `modify` receives the full (two-sided) spectrum of each completed frame and
+    /// may change it in place; it should keep the spectrum Hermitian.
+    pub(crate) fn process_block(&mut self, samples: &mut [f32], mut modify: impl FnMut(&mut [Complex32])) {
+        let hop = self.frame_size / 2;
+        for x in samples.iter_mut() {
+            self.input[hop + self.fill] = *x;
+            *x = self.ready[self.fill];
+            self.fill += 1;
+            if self.fill == hop {
+                self.process_frame(&mut modify);
+                self.fill = 0;
+            }
+        }
+    }
+
+    /// Process a complete signal, compensating the latency so the output lines up
+    /// with the input. It allocates.
+    pub(crate) fn process(&mut self, samples: &[f32], mut modify: impl FnMut(&mut [Complex32])) -> Vec<f32> {
+        self.reset();
+        let latency = self.latency();
+        let mut output = samples.to_vec();
+        output.resize(samples.len() + latency, 0.0);
+        self.process_block(&mut output, &mut modify);
+        output.drain(..latency);
+        output
+    }
+
+    /// Forget all buffered audio
+    pub(crate) fn reset(&mut self) {
+        self.input.fill(0.0);
+        self.overlap.fill(0.0);
+        self.ready.fill(0.0);
+        self.fill = 0;
+    }
+
+    fn process_frame(&mut self, modify: &mut impl FnMut(&mut [Complex32])) {
+        let hop = self.frame_size / 2;
+        for ((bin, x), w) in self.spectrum.iter_mut().zip(&self.input).zip(&self.window) {
+            *bin = Complex32::new(x * w, 0.0);
+        }
+        self.fft.process_with_scratch(&mut self.spectrum, &mut self.scratch);
+        modify(&mut self.spectrum);
+        self.ifft.process_with_scratch(&mut self.spectrum, &mut self.scratch);
+
+        let scale = 1.0 / self.frame_size as f32;
+        for ((y, bin), w) in self.overlap.iter_mut().zip(&self.spectrum).zip(&self.window) {
+            *y += bin.re * scale * w;
+        }
+        // The first hop now has both of its frames and is finished
+        self.ready.copy_from_slice(&self.overlap[..hop]);
+        self.overlap.copy_within(hop.., 0);
+        self.overlap[hop..].fill(0.0);
+        self.input.copy_within(hop.., 0);
+    }
+}
+
+/// Rebuild the full spectrum of a real signal from its non-negative frequency bins
+fn hermitian_spectrum(bins: &[Complex<f32>], n: usize) -> Spectrum {
+    let mut complex = vec![Complex::new(0.0, 0.0); n];
+    for (k, &c) in bins.iter().enumerate().take(n / 2 + 1) {
+        complex[k] = c;
+        if k > 0 && k < n - k {
+            complex[n - k] = c.conj();
+        }
+    }
+    Spectrum { complex, n }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_signal(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / 16000.0;
+                0.5 * (2.0 * PI * 220.0 * t).sin() + 0.2 * (2.0 * PI * 1375.0 * t).cos() + 0.05 * ((i * 7919 % 101) as f32 / 50.0 - 1.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_perfect_reconstruction() {
+        let signal = test_signal(5000);
+        let configs = [
+            StftConfig::default(),
+            StftConfig { window_size: 512, hop_size: 256, window: WindowType::Hann },
+            StftConfig { window_size: 512, hop_size: 128, window: WindowType::Hamming },
+            StftConfig { window_size: 256, hop_size: 256, window: WindowType::Rectangular },
+            // Not a power of two, and the length is not a multiple of the hop
+            StftConfig { window_size: 300, hop_size: 75, window: WindowType::Hann },
+        ];
+        for config in configs {
+            let stft = Stft::from_waveform(&signal, 16000.0, config);
+            let recovered = stft.inverse();
+            assert_eq!(recovered.len(), signal.len());
+            let max_error = signal.iter().zip(&recovered).map(|(a, b)| (a - b).abs()).fold(0.0f32, f32::max);
+            assert!(max_error < 1e-4, "{:?}: max error {}", config, max_error);
+        }
+    }
+
+    #[test]
+    fn test_axes() {
+        let stft = Stft::from_waveform(&test_signal(16000), 16000.0, StftConfig::default());
+        assert_eq!(stft.n_bins(), 513);
+        assert!(stft.frames.iter().all(|frame| frame.len() == 513));
+        assert_eq!(stft.n_frames(), 64);
+        assert_eq!(stft.bin_frequency(64), 1000.0);
+        assert_eq!(stft.bin_frequency(512), 8000.0);
+        assert!((stft.frame_time(10) - 0.16).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sine_peaks_at_its_bin() {
+        let sample_rate = 16000.0;
+        let signal: Vec<f32> = (0..8000).map(|i| (2.0 * PI * 1000.0 * i as f32 / sample_rate).sin()).collect();
+        let stft = Stft::with_defaults(&signal, sample_rate);
+        let magnitudes = &stft.magnitudes()[stft.n_frames() / 2];
+        let peak = (0..magnitudes.len()).max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b])).unwrap();
+        assert_eq!(stft.bin_frequency(peak), 1000.0);
+    }
+
+    #[test]
+    fn test_modified_frames_resynthesise() {
+        // Zeroing every bin above 800 Hz removes the 1375 Hz component
+        let sample_rate = 16000.0;
+        let mut stft = Stft::with_defaults(&test_signal(8000), sample_rate);
+        let cutoff = (800.0 / stft.bin_frequency(1)) as usize;
+        for frame in stft.frames.iter_mut() {
+            frame[cutoff..].iter_mut().for_each(|c| *c = Complex::new(0.0, 0.0));
+        }
+        let filtered = stft.inverse();
+        let high = Stft::with_defaults(&filtered, sample_rate);
+        let bin_1375 = (1375.0 / high.bin_frequency(1)).round() as usize;
+        let bin_220 = (220.0 / high.bin_frequency(1)).round() as usize;
+        let frame = &high.magnitudes()[high.n_frames() / 2];
+        assert!(frame[bin_1375] < 0.01 * frame[bin_220]);
+    }
+
+    #[test]
+    fn test_streaming_reconstructs_delayed_input() {
+        let signal = test_signal(5000);
+        let mut stft = StreamingStft::new(512);
+        let mut streamed = signal.clone();
+        for chunk in streamed.chunks_mut(100) {
+            stft.process_block(chunk, |_| {});
+        }
+        let latency = stft.latency();
+        for (a, b) in signal.iter().zip(&streamed[latency..]) {
+            assert!((a - b).abs() < 1e-4);
+        }
+        assert!(streamed[..latency].iter().all(|x| x.abs() < 1e-6));
+
+        let batch = stft.process(&signal, |_| {});
+        assert_eq!(batch.len(), signal.len());
+        for (a, b) in signal.iter().zip(&batch) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_empty_signal() {
+        let stft = Stft::with_defaults(&[], 16000.0);
+        assert_eq!(stft.n_frames(), 0);
+        assert!(stft.inverse().is_empty());
+    }
+}
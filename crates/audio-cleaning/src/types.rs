@@ -36,19 +36,15 @@ impl Spectrum {
     /// Invert the spectrum back to the time domain (real part only)
     pub fn to_time_domain(&self) -> Vec<f32> {
         let mut buffer = self.complex.clone();
-        IFFT_CACHE.with(|cache| {
-            let mut cache_map = cache.borrow_mut();
-            let ifft = cache_map.entry(self.n).or_insert_with(|| {
-                FFT_PLANNER.with(|planner| {
-                    let mut planner = planner.borrow_mut();
-                    planner.plan_fft(self.n, FftDirection::Inverse)
-                })
-            });
-            ifft.process(&mut buffer);
-        });
+        cached_fft(self.n, FftDirection::Inverse).process(&mut buffer);
         buffer.iter().map(|c| c.re / self.n as f32).collect()
     }
 
+    /// Frequency of bin `k` in Hz, given the sample rate of the analysed signal
+    pub fn bin_frequency(&self, k: usize, sample_rate: f32) -> f32 {
+        k as f32 * sample_rate / self.n as f32
+    }
+
     // Get the complex value at index i
     pub fn get(&self, i: usize) -> Option<&Complex<f32>> {
         self.complex.get(i)
@@ -57,19 +53,25 @@ impl Spectrum {
 
 fn compute_spectrum(signal: &[f32], n_fft: usize) -> Vec<Complex<f32>> {
     let mut buffer: Vec<Complex<f32>> = signal.iter().map(|&x| Complex::new(x, 0.0)).collect();
-    FFT_CACHE.with(|cache| {
-        let mut cache_map = cache.borrow_mut();
-        let fft = cache_map.entry(n_fft).or_insert_with(|| {
-            FFT_PLANNER.with(|planner| {
-                let mut planner = planner.borrow_mut();
-                planner.plan_fft_forward(n_fft)
-            })
-        });
-        fft.process(&mut buffer);
-    });
+    cached_fft(n_fft, FftDirection::Forward).process(&mut buffer);
     buffer
 }
 
+/// FFT plan of size `n` from the per-thread cache, planning it on first use
+pub(crate) fn cached_fft(n: usize, direction: FftDirection) -> Arc<dyn Fft<f32>> {
+    let cache = match direction {
+        FftDirection::Forward => &FFT_CACHE,
+        FftDirection::Inverse => &IFFT_CACHE,
+    };
+    cache.with(|cache| {
+        cache
+            .borrow_mut()
+            .entry(n)
+            .or_insert_with(|| FFT_PLANNER.with(|planner| planner.borrow_mut().plan_fft(n, direction)))
+            .clone()
+    })
+}
+
 // TODO add frequency axis
 pub struct Spectrogram {
     pub spectra: Vec<Vec<f32>>, // Vec of spectra (each spectrum is Vec<f32>)
@@ -174,6 +176,13 @@ mod tests {
         assert!(spectrum.get(signal.len()).is_none());
     }
 
+    #[test]
+    fn test_spectrum_bin_frequency() {
+        let spectrum = Spectrum::from_waveform(&[0.0; 1024]);
+        assert_eq!(spectrum.bin_frequency(0, 16000.0), 0.0);
+        assert_eq!(spectrum.bin_frequency(64, 16000.0), 1000.0);
+    }
+
     #[test]
     fn test_spectrogram_from_waveform() {
         let signal = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
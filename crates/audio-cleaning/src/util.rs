@@ -26,6 +26,11 @@ pub fn mean_std_deviation(data: &[f32]) -> Option<(f32, f32)> {
     Some((mean_value, std_dev))
 }
 
+/// Convert decibels to a linear amplitude factor: 10^(db/20)
+pub fn db_to_linear(db: f32) -> f32 {
+    10.0_f32.powf(db / 20.0)
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -66,4 +71,11 @@ mod tests {
         assert!((mean - 5.0).abs() < 1e-6);
         assert!((stddev - 2.0).abs() < 1e-6);
     }
+
+    #[test]
+    fn test_db_to_linear() {
+        assert!((db_to_linear(0.0) - 1.0).abs() < 1e-6);
+        assert!((db_to_linear(6.0) - 2.0).abs() < 0.01);
+        assert!((db_to_linear(-6.0) - 0.5).abs() < 0.01);
+    }
 }
\ No newline at end of file
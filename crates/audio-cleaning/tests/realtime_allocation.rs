@@ -0,0 +1,165 @@
+//! Checks that the real-time processors do not allocate once they are constructed.
+//!
+//! The whole test binary runs under a counting allocator. Only allocations made on
+//! the test's own thread while counting is switched on are recorded, so other tests
+//! and the harness do not interfere.
+
+use audio_cleaning::{
+    AudioProcessor, DeclipConfig, Declipper, HumConfig, HumRemover, SpectralGate, SpectralGateConfig,
+    SpectralSubtraction, Spectrum, WienerFilter,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record_allocation() {
+    // `try_with` because the allocator can be called while thread locals are torn down
+    let _ = COUNTING.try_with(|counting| {
+        if counting.get() {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_allocation();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Number of allocations made on this thread while running `f`
+fn count_allocations(f: impl FnOnce()) -> usize {
+    ALLOCATIONS.with(|count| count.set(0));
+    COUNTING.with(|counting| counting.set(true));
+    f();
+    COUNTING.with(|counting| counting.set(false));
+    ALLOCATIONS.with(|count| count.get())
+}
+
+fn test_signal(len: usize, seed: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| 0.5 * (i as f32 * 0.07).sin() + 0.02 * (((i + seed) * 7919 % 101) as f32 / 50.0 - 1.0))
+        .collect()
+}
+
+#[test]
+fn spectral_gate_does_not_allocate_in_steady_state() {
+    let noise: Vec<f32> = test_signal(8192, 3).iter().map(|x| 0.05 * x).collect();
+    let config = SpectralGateConfig {
+        block_size: Some(1024),
+        smoothing_window: 3,
+        pre_emphasis: true,
+        ..Default::default()
+    };
+    let mut gate = SpectralGate::new(Spectrum::from_waveform(&noise), config);
+
+    let blocks: Vec<Vec<f32>> = (0..8).map(|seed| test_signal(1024, seed)).collect();
+    let mut output = vec![0.0f32; 1024];
+    // Chunks of other lengths, which the streaming STFT buffers across calls
+    let mut odd_block = test_signal(2500, 11);
+
+    // Warm up
+    gate.process_into(&blocks[0], &mut output);
+
+    let allocations = count_allocations(|| {
+        for _ in 0..10 {
+            for block in &blocks {
+                gate.process_into(block, &mut output);
+            }
+            gate.process_block(&mut odd_block);
+        }
+    });
+    assert_eq!(allocations, 0, "spectral gate allocated {} times in steady state", allocations);
+    assert!(output.iter().all(|x| x.is_finite()));
+}
+
+#[test]
+fn frame_based_reducers_do_not_allocate_when_streaming() {
+    let noise: Vec<f32> = test_signal(8192, 3).iter().map(|x| 0.05 * x).collect();
+    let profile = Spectrum::from_waveform(&noise);
+    let mut reducers: Vec<Box<dyn AudioProcessor>> = vec![
+        Box::new(SpectralSubtraction::with_defaults(profile.clone())),
+        Box::new(WienerFilter::with_defaults(profile)),
+    ];
+    let mut stream = test_signal(10_000, 5);
+
+    for reducer in reducers.iter_mut() {
+        let allocations = count_allocations(|| {
+            for chunk in stream.chunks_mut(441) {
+                reducer.process_block(chunk);
+            }
+        });
+        assert_eq!(allocations, 0, "{} allocated {} times while streaming", reducer.name(), allocations);
+    }
+    assert!(stream.iter().all(|x| x.is_finite()));
+}
+
+#[test]
+fn hum_detection_does_not_allocate_in_the_callback() {
+    let sample_rate = 16000.0;
+    let mut signal: Vec<f32> = (0..2 * 16000)
+        .map(|i| {
+            let t = i as f32 / sample_rate;
+            0.3 * (2.0 * std::f32::consts::PI * 220.0 * t).sin()
+                + [(1.0, 0.1), (2.0, 0.05), (3.0, 0.08)]
+                    .iter()
+                    .map(|(h, a)| a * (2.0 * std::f32::consts::PI * 50.0 * h * t).sin())
+                    .sum::<f32>()
+        })
+        .collect();
+    let mut remover = HumRemover::new(sample_rate, HumConfig::default());
+
+    // Detection runs once the first second has been buffered, inside this loop
+    let allocations = count_allocations(|| {
+        for chunk in signal.chunks_mut(512) {
+            remover.process_block(chunk);
+        }
+    });
+    assert!(remover.detection_done() && remover.mains_hz().is_some());
+    assert_eq!(allocations, 0, "hum remover allocated {} times while streaming", allocations);
+}
+
+#[test]
+fn declipper_does_not_allocate_when_streaming() {
+    let mut signal: Vec<f32> = (0..20_000).map(|i| (0.9 * (i as f32 * 0.03).sin()).clamp(-0.6, 0.6)).collect();
+    let mut declipper = Declipper::new(DeclipConfig::default());
+
+    // Warm up
+    let (warm_up, rest) = signal.split_at_mut(4096);
+    for chunk in warm_up.chunks_mut(512) {
+        declipper.process_block(chunk);
+    }
+
+    let allocations = count_allocations(|| {
+        for chunk in rest.chunks_mut(512) {
+            declipper.process_block(chunk);
+        }
+    });
+    assert_eq!(allocations, 0, "declipper allocated {} times while streaming", allocations);
+    assert!(declipper.stats().samples > 0);
+    assert!(signal.iter().all(|x| x.is_finite()));
+}
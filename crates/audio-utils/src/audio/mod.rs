@@ -32,4 +32,4 @@
 
 pub mod types;
 
-pub use types::{MonoAudio, Audio, MonoAudioSource, IterableAudio};
+pub use types::{MonoAudio, MonoAudioSlice, Audio, MonoAudioSource, IterableAudio};
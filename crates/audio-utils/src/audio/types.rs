@@ -85,6 +85,38 @@ impl MonoAudioSource for MonoAudio {
     }
 }
 
+/// A borrowed view of mono samples
+///
+/// Lets code holding a plain `&[f32]` call APIs that take a `MonoAudioSource`
+/// without copying the samples into a `MonoAudio`.
+#[derive(Debug, Clone, Copy)]
+pub struct MonoAudioSlice<'a> {
+    pub samples: &'a [f32],
+    pub sample_rate: u32,
+}
+
+impl<'a> MonoAudioSlice<'a> {
+    /// Create a view of `samples` at `sample_rate` Hz
+    pub fn new(samples: &'a [f32], sample_rate: u32) -> Self {
+        MonoAudioSlice {
+            samples,
+            sample_rate,
+        }
+    }
+}
+
+impl Audio for MonoAudioSlice<'_> {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+impl MonoAudioSource for MonoAudioSlice<'_> {
+    fn mono_samples(&self) -> &[f32] {
+        self.samples
+    }
+}
+
 impl IterableAudio for MonoAudio {
     fn sliding_windows(&self, window_size: usize, step_size: usize) -> SlidingWindows<'_> {
         SlidingWindows {
@@ -154,6 +186,15 @@ mod tests {
         assert_eq!(audio.mono_samples(), &samples[..]);
     }
 
+    #[test]
+    fn test_mono_audio_slice() {
+        let samples = [0.1, 0.2, 0.3];
+        let view = MonoAudioSlice::new(&samples[1..], 16000);
+
+        assert_eq!(view.sample_rate(), 16000);
+        assert_eq!(view.mono_samples(), &[0.2, 0.3]);
+    }
+
     #[test]
     fn test_audio_trait() {
         let audio = MonoAudio::new(vec![1.0], 22050);
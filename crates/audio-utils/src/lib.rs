@@ -8,6 +8,8 @@ pub mod audio;
 pub mod io;
 pub mod recording;
 pub mod latency;
+pub mod pitch;
+pub mod stats;
 
-pub use audio::{Audio, MonoAudio, MonoAudioSource, IterableAudio};
+pub use audio::{Audio, MonoAudio, MonoAudioSlice, MonoAudioSource, IterableAudio};
 pub use latency::LatencyMetrics;
@@ -0,0 +1,33 @@
+//! Conversions between frequencies and MIDI note numbers
+
+/// Standard concert pitch of A4 in Hz
+pub const A4_HZ: f32 = 440.0;
+
+/// MIDI note number of A4
+pub const A4_MIDI: f32 = 69.0;
+
+/// Convert a frequency in Hz to a fractional MIDI note number, given the frequency of
+/// A4 (e.g. 440.0 or 442.0)
+pub fn hz_to_midi(hz: f32, reference_hz: f32) -> f32 {
+    A4_MIDI + 12.0 * (hz / reference_hz).log2()
+}
+
+/// Convert a (fractional) MIDI note number to Hz, given the frequency of A4
+pub fn midi_to_hz(midi: f32, reference_hz: f32) -> f32 {
+    reference_hz * 2f32.powf((midi - A4_MIDI) / 12.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_midi_conversions() {
+        assert!((hz_to_midi(440.0, A4_HZ) - 69.0).abs() < 1e-5);
+        assert!((hz_to_midi(261.6256, A4_HZ) - 60.0).abs() < 1e-3);
+        assert!((midi_to_hz(57.0, A4_HZ) - 220.0).abs() < 1e-3);
+        // A4 = 442 Hz shifts every note by the same number of cents
+        assert!((hz_to_midi(442.0, 442.0) - 69.0).abs() < 1e-5);
+        assert!((midi_to_hz(hz_to_midi(123.4, 432.0), 432.0) - 123.4).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,29 @@
+//! Small statistics helpers shared by the analysis crates
+
+/// Median of `values`, reordering them in place; the mean of the middle two for an
+/// even count. Returns `None` if `values` is empty.
+pub fn median(values: &mut [f32]) -> Option<f32> {
+    let len = values.len();
+    if len == 0 {
+        return None;
+    }
+    let (lower, &mut upper, _) = values.select_nth_unstable_by(len / 2, f32::total_cmp);
+    if len % 2 == 1 {
+        return Some(upper);
+    }
+    let below = lower.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    Some((below + upper) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [5.0]), Some(5.0));
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), Some(2.5));
+    }
+}
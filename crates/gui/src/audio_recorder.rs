@@ -1,17 +1,25 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, Stream, StreamConfig, Sample};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::Sender;
 use std::time::{Duration, Instant};
 
 use audio_utils::LatencyMetrics;
-use crate::pitch_processor::{PitchProcessor, PitchResult};
-use pitch_detection_utils::ThreadSafeYinDetector;
+use audio_cleaning::{CleaningPipeline, CleaningPipelineConfig};
+use crate::pitch_processor::{AudioUpdate, PitchProcessor};
+use pitch_detection_utils::{DetectorKind, DetectorSettings, DynPitchDetector, StreamingPitchTracker, Tuning};
 
 /// Delay in milliseconds to wait after pausing a stream before dropping it.
 /// This gives ALSA time to process the pause command and transition to a stable state.
 const ALSA_PAUSE_DELAY_MS: u64 = 10;
 
+/// Pitch tracker owned by the audio thread, tagged with the recording it belongs to
+type AudioThreadTracker = (u64, StreamingPitchTracker<Box<dyn DynPitchDetector>>);
+
+/// Identifies each recording, so the audio thread starts it with a fresh tracker
+static NEXT_RECORDING_ID: AtomicU64 = AtomicU64::new(0);
+
 pub struct AudioRecorder {
     stream: Option<Stream>,
 }
@@ -23,15 +31,15 @@ impl AudioRecorder {
         }
     }
     
+    #[allow(clippy::too_many_arguments)]
     pub fn start(
         &mut self,
-        pitch_sender: Sender<PitchResult>,
-        power_threshold: f32,
-        clarity_threshold: f32,
-        window_size: usize,
+        update_sender: Sender<AudioUpdate>,
+        detector_kind: DetectorKind,
+        detector_settings: DetectorSettings,
         hop_size: usize,
-        enable_bandpass: bool,
-        enable_spectral_gating: bool,
+        tuning: Tuning,
+        cleaning: CleaningPipelineConfig,
         noise_profile: Option<Arc<audio_cleaning::Spectrum>>,
         save_to_file: bool,
         save_path: String,
@@ -54,13 +62,12 @@ impl AudioRecorder {
             cpal::SampleFormat::F32 => self.build_stream::<f32>(
                 &device,
                 &config.into(),
-                pitch_sender,
-                power_threshold,
-                clarity_threshold,
-                window_size,
+                update_sender,
+                detector_kind,
+                detector_settings,
                 hop_size,
-                enable_bandpass,
-                enable_spectral_gating,
+                tuning,
+                cleaning,
                 noise_profile,
                 save_to_file,
                 save_path,
@@ -68,13 +75,12 @@ impl AudioRecorder {
             cpal::SampleFormat::I16 => self.build_stream::<i16>(
                 &device,
                 &config.into(),
-                pitch_sender,
-                power_threshold,
-                clarity_threshold,
-                window_size,
+                update_sender,
+                detector_kind,
+                detector_settings,
                 hop_size,
-                enable_bandpass,
-                enable_spectral_gating,
+                tuning,
+                cleaning,
                 noise_profile,
                 save_to_file,
                 save_path,
@@ -82,13 +88,12 @@ impl AudioRecorder {
             cpal::SampleFormat::U16 => self.build_stream::<u16>(
                 &device,
                 &config.into(),
-                pitch_sender,
-                power_threshold,
-                clarity_threshold,
-                window_size,
+                update_sender,
+                detector_kind,
+                detector_settings,
                 hop_size,
-                enable_bandpass,
-                enable_spectral_gating,
+                tuning,
+                cleaning,
                 noise_profile,
                 save_to_file,
                 save_path,
@@ -119,17 +124,17 @@ impl AudioRecorder {
         drop(stream);
     }
     
+    #[allow(clippy::too_many_arguments)]
     fn build_stream<T>(
         &self,
         device: &Device,
         config: &StreamConfig,
-        pitch_sender: Sender<PitchResult>,
-        power_threshold: f32,
-        clarity_threshold: f32,
-        window_size: usize,
+        update_sender: Sender<AudioUpdate>,
+        detector_kind: DetectorKind,
+        detector_settings: DetectorSettings,
         hop_size: usize,
-        enable_bandpass: bool,
-        enable_spectral_gating: bool,
+        tuning: Tuning,
+        cleaning: CleaningPipelineConfig,
         noise_profile: Option<Arc<audio_cleaning::Spectrum>>,
         save_to_file: bool,
         save_path: String,
@@ -140,9 +145,18 @@ impl AudioRecorder {
     {
         let sample_rate = config.sample_rate.0;
         let channels = config.channels as usize;
+        let window_size = detector_settings.window_size;
+        let recording_id = NEXT_RECORDING_ID.fetch_add(1, Ordering::Relaxed);
         
-        // Create circular buffer for audio samples
-        let audio_buffer = Arc::new(Mutex::new(Vec::<f32>::new()));
+        // Build the cleaning pipeline once; it keeps its filter state across callbacks
+        let mut pipeline = if cleaning.stages.is_empty() {
+            None
+        } else {
+            Some(
+                CleaningPipeline::from_config(&cleaning, sample_rate as f32, noise_profile.as_deref())
+                    .map_err(|e| format!("Failed to build cleaning pipeline: {}", e))?,
+            )
+        };
         
         // Setup file writer if saving is enabled
         let wav_writer = if save_to_file {
@@ -164,8 +178,6 @@ impl AudioRecorder {
             None
         };
         
-        let buffer_clone = Arc::clone(&audio_buffer);
-        
         // Process audio in chunks
         let err_fn = |err| eprintln!("Stream error: {}", err);
         
@@ -175,25 +187,14 @@ impl AudioRecorder {
                 // Create latency metrics and capture callback timestamp
                 let latency = LatencyMetrics::with_callback_timestamp(Instant::now());
                 
-                // Create detector locally in the audio thread
+                // Create the tracker locally in the audio thread
                 // This avoids Send issues with Rc in the detector
                 thread_local! {
-                    static DETECTOR: std::cell::RefCell<Option<ThreadSafeYinDetector>> = std::cell::RefCell::new(None);
+                    static TRACKER: std::cell::RefCell<Option<AudioThreadTracker>> = const { std::cell::RefCell::new(None) };
                 }
                 
-                DETECTOR.with(|detector_cell| {
-                    let mut detector = detector_cell.borrow_mut();
-                    if detector.is_none() {
-                        *detector = Some(ThreadSafeYinDetector::new(
-                            power_threshold,
-                            clarity_threshold,
-                            window_size,
-                            hop_size,
-                        ));
-                    }
-                    let detector = detector.as_mut().unwrap();
                 // Convert samples to f32 and mix to mono
-                let mono_samples: Vec<f32> = if channels == 1 {
+                let mut mono_samples: Vec<f32> = if channels == 1 {
                     data.iter()
                         .map(|&s| f32::from_sample(s))
                         .collect()
@@ -218,44 +219,29 @@ impl AudioRecorder {
                     }
                 }
                 
-                    // Add to buffer
-                    if let Ok(mut buffer) = buffer_clone.lock() {
-                        buffer.extend_from_slice(&mono_samples);
-                        
-                        // Process when we have enough samples for pitch detection
-                        // Only track latency for the first chunk in this callback to avoid
-                        // misleading measurements where later chunks include earlier processing time
-                        let mut is_first_chunk = true;
-                        
-                        while buffer.len() >= window_size {
-                            // Take exactly window_size samples for processing
-                            let samples_to_process: Vec<f32> = buffer.drain(..window_size).collect();
-                            
-                            // Create latency metrics with current timestamp for each chunk
-                            // This ensures accurate per-chunk measurements
-                            let chunk_latency = if is_first_chunk {
-                                // First chunk uses the callback timestamp
-                                latency.clone()
-                            } else {
-                                // Subsequent chunks get a fresh timestamp
-                                LatencyMetrics::with_callback_timestamp(Instant::now())
-                            };
-                            is_first_chunk = false;
-                            
-                            // Process pitch detection directly on audio thread
-                            if let Some(pitch_result) = PitchProcessor::process_audio_chunk(
-                                detector,
-                                samples_to_process,
-                                sample_rate,
-                                enable_bandpass,
-                                enable_spectral_gating,
-                                noise_profile.as_ref().map(|p| p.as_ref()),
-                                chunk_latency,
-                            ) {
-                                // Send result to main thread
-                                let _ = pitch_sender.send(pitch_result);
-                            }
-                        }
+                TRACKER.with(|tracker_cell| {
+                    let mut tracker = tracker_cell.borrow_mut();
+                    // A new recording gets a fresh tracker with its chosen detector
+                    if tracker.as_ref().is_none_or(|(id, _)| *id != recording_id) {
+                        let detector = detector_kind.create_boxed(&detector_settings);
+                        *tracker = Some((
+                            recording_id,
+                            StreamingPitchTracker::new(detector, window_size, hop_size, sample_rate),
+                        ));
+                    }
+                    let tracker = &mut tracker.as_mut().unwrap().1;
+                    
+                    // Process pitch detection directly on audio thread; the tracker
+                    // emits a frame every hop
+                    for update in PitchProcessor::process_samples(
+                        tracker,
+                        &mut mono_samples,
+                        pipeline.as_mut(),
+                        &tuning,
+                        latency,
+                    ) {
+                        // Send result to main thread
+                        let _ = update_sender.send(update);
                     }
                 });
             },
@@ -267,6 +253,12 @@ impl AudioRecorder {
     }
 }
 
+impl Default for AudioRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for AudioRecorder {
     fn drop(&mut self) {
         // Ensure stream is properly cleaned up even if stop() wasn't called
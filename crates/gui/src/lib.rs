@@ -6,7 +6,7 @@
 //! - Real-time audio recording from microphone
 //! - Live pitch detection and display
 //! - Configurable audio cleaning options (bandpass filter, spectral gating)
-//! - Musical note display with frequency
+//! - Musical note display with frequency and cents deviation, under a configurable A4 reference and temperament
 //! - Optional real-time WAV file saving
 //! 
 //! ## Running the GUI
@@ -23,9 +23,9 @@
 //! ## Required APIs from Other Crates
 //! This implementation requires the following APIs which already exist:
 //! - `audio_utils::MonoAudio` - for audio data representation
-//! - `audio_cleaning::clean_audio_for_pitch` - for audio preprocessing
-//! - `pitch_detection_utils::ExternalYinDetector` - for pitch detection
-//! - `pitch_detection_utils::hz_to_note_name` - for note name conversion
+//! - `audio_cleaning::CleaningPipeline` - for audio preprocessing
+//! - `pitch_detection_utils::DetectorKind` / `DynPitchDetector` - for runtime-selectable pitch detection
+//! - `pitch_detection_utils::hz_to_note` / `Tuning` - for note and cents conversion
 
 // Re-export main modules for library use
 pub mod audio_recorder;
@@ -6,8 +6,17 @@ mod audio_recorder;
 mod pitch_processor;
 
 use audio_recorder::AudioRecorder;
-use pitch_processor::PitchResult;
-use audio_cleaning::Spectrum;
+use pitch_processor::{AudioUpdate, PitchResult};
+use audio_cleaning::{Spectrum, CleaningPipelineConfig, HumConfig, SpectralGateConfig};
+use audio_cleaning::filters::BandpassConfig;
+use audio_cleaning::dynamics::AgcConfig;
+use pitch_detection_utils::{DetectorKind, DetectorSettings, Temperament, Tuning, NOTE_NAMES};
+
+/// Samples per analysis window, also the block size of the spectral gate
+const WINDOW_SIZE: usize = 2048;
+
+/// Samples between the starts of consecutive analysis windows
+const HOP_SIZE: usize = 1024;
 
 type NoiseProfile = Arc<Spectrum>;
 
@@ -35,16 +44,25 @@ struct PitchPerfecterApp {
     // Audio recording
     audio_recorder: Arc<Mutex<AudioRecorder>>,
     
-    // Pitch results receiver (processing now runs on audio thread)
-    pitch_receiver: Receiver<PitchResult>,
+    // Pitch and gain updates receiver (processing now runs on audio thread)
+    update_receiver: Receiver<AudioUpdate>,
     
     // UI state
     is_recording: bool,
     current_pitch: Option<PitchResult>,
+    input_gain_db: Option<f32>,
+    
+    // Pitch detection algorithm, applied when recording starts
+    detector_kind: DetectorKind,
+    
+    // Reference pitch, temperament and tonic notes are named against, applied when recording starts
+    tuning: Tuning,
     
     // Cleaning options
     enable_bandpass: bool,
     enable_spectral_gating: bool,
+    enable_hum_removal: bool,
+    enable_agc: bool,
     
     // Noise profile (Arc to avoid cloning large spectrum data)
     noise_profile: Option<NoiseProfile>,
@@ -61,17 +79,24 @@ struct PitchPerfecterApp {
 
 impl PitchPerfecterApp {
     fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        let (_pitch_tx, pitch_rx) = channel();
+        let (_update_tx, update_rx) = channel();
         
+        // cpal streams are not Send; the Arc<Mutex<..>> only provides interior mutability here
+        #[allow(clippy::arc_with_non_send_sync)]
         let audio_recorder = Arc::new(Mutex::new(AudioRecorder::new()));
         
         Self {
             audio_recorder,
-            pitch_receiver: pitch_rx,
+            update_receiver: update_rx,
             is_recording: false,
             current_pitch: None,
+            input_gain_db: None,
+            detector_kind: DetectorKind::default(),
+            tuning: Tuning::default(),
             enable_bandpass: true,
             enable_spectral_gating: false,
+            enable_hum_removal: false,
+            enable_agc: false,
             noise_profile: None,
             is_recording_noise: false,
             noise_receiver: None,
@@ -118,29 +143,30 @@ impl PitchPerfecterApp {
     fn start_recording(&mut self) {
         let save_to_file = self.save_to_file;
         let save_path = self.save_path.clone();
-        let enable_bandpass = self.enable_bandpass;
-        let enable_spectral_gating = self.enable_spectral_gating;
+        let cleaning = self.cleaning_config();
         // Clone the Arc reference to avoid expensive Spectrum data copying
         let noise_profile = self.noise_profile.clone();
         
         // Create a new channel for this recording session
-        let (pitch_tx, pitch_rx) = channel();
-        self.pitch_receiver = pitch_rx;
+        let (update_tx, update_rx) = channel();
+        self.update_receiver = update_rx;
+        self.input_gain_db = None;
         
-        // Detector parameters - same as in PitchProcessor
-        const WINDOW_SIZE: usize = 2048;
-        const HOP_SIZE: usize = 1024;
-        const POWER_THRESHOLD: f32 = 0.1;
-        const CLARITY_THRESHOLD: f32 = 0.7;
+        // Detector parameters
+        let detector_settings = DetectorSettings {
+            window_size: WINDOW_SIZE,
+            power_threshold: 0.1,
+            clarity_threshold: 0.7,
+            ..Default::default()
+        };
         
         let result = self.audio_recorder.lock().unwrap().start(
-            pitch_tx,
-            POWER_THRESHOLD,
-            CLARITY_THRESHOLD,
-            WINDOW_SIZE,
+            update_tx,
+            self.detector_kind,
+            detector_settings,
             HOP_SIZE,
-            enable_bandpass,
-            enable_spectral_gating,
+            self.tuning,
+            cleaning,
             noise_profile,
             save_to_file,
             save_path,
@@ -149,7 +175,11 @@ impl PitchPerfecterApp {
         match result {
             Ok(_) => {
                 self.is_recording = true;
-                self.status_message = "Recording...".to_string();
+                self.status_message = if self.gating_without_profile() {
+                    "Recording... (no noise profile: bandpass filter used instead of spectral gating)".to_string()
+                } else {
+                    "Recording...".to_string()
+                };
             }
             Err(e) => {
                 self.status_message = format!("Error starting recording: {}", e);
@@ -157,6 +187,38 @@ impl PitchPerfecterApp {
         }
     }
     
+    /// Build the cleaning pipeline configuration from the UI options.
+    /// Hum removal runs first, then spectral gating (when a noise profile is available),
+    /// then the bandpass filter, and finally the AGC so it levels the cleaned signal.
+    /// Without a noise profile, spectral gating falls back to the bandpass filter.
+    fn cleaning_config(&self) -> CleaningPipelineConfig {
+        let mut config = CleaningPipelineConfig::new();
+        if self.enable_hum_removal {
+            config = config.with_stage(HumConfig::default());
+        }
+        if self.enable_spectral_gating && self.noise_profile.is_some() {
+            // The gate keeps its own overlap-add frames across audio callbacks, so
+            // blocks of any size are gated without zero-padding. Frames of the pitch
+            // window size delay the cleaned signal by one window.
+            config = config.with_stage(SpectralGateConfig {
+                block_size: Some(WINDOW_SIZE),
+                ..Default::default()
+            });
+        }
+        if self.enable_bandpass || self.gating_without_profile() {
+            config = config.with_stage(BandpassConfig::default());
+        }
+        if self.enable_agc {
+            config = config.with_stage(AgcConfig::default());
+        }
+        config
+    }
+    
+    /// Whether spectral gating is enabled but no noise profile has been recorded
+    fn gating_without_profile(&self) -> bool {
+        self.enable_spectral_gating && self.noise_profile.is_none()
+    }
+    
     fn stop_recording(&mut self) {
         match self.audio_recorder.lock().unwrap().stop() {
             Ok(_) => {
@@ -174,8 +236,11 @@ impl eframe::App for PitchPerfecterApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Receive pitch results from the audio thread
         // Pitch detection now happens directly on the audio callback thread
-        while let Ok(pitch_result) = self.pitch_receiver.try_recv() {
-            self.current_pitch = Some(pitch_result);
+        while let Ok(update) = self.update_receiver.try_recv() {
+            match update {
+                AudioUpdate::Pitch(pitch_result) => self.current_pitch = Some(pitch_result),
+                AudioUpdate::InputGain(gain_db) => self.input_gain_db = Some(gain_db),
+            }
         }
         
         // Check for noise recording results
@@ -219,6 +284,50 @@ impl eframe::App for PitchPerfecterApp {
                 
                 ui.add_space(5.0);
                 ui.label(&self.status_message);
+                
+                ui.add_space(5.0);
+                ui.add_enabled_ui(!self.is_recording, |ui| {
+                    egui::ComboBox::from_label("Pitch Detector")
+                        .selected_text(self.detector_kind.name())
+                        .show_ui(ui, |ui| {
+                            for kind in DetectorKind::ALL {
+                                ui.selectable_value(&mut self.detector_kind, kind, kind.name());
+                            }
+                        });
+                })
+                .response
+                .on_disabled_hover_text("Stop recording to change the detector");
+                
+                ui.add_enabled_ui(!self.is_recording, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut self.tuning.reference_hz)
+                                .range(380.0..=480.0)
+                                .speed(0.1)
+                                .suffix(" Hz"),
+                        );
+                        ui.label("A4 Reference");
+                    });
+                    egui::ComboBox::from_label("Temperament")
+                        .selected_text(self.tuning.temperament.name())
+                        .show_ui(ui, |ui| {
+                            for temperament in Temperament::NAMED {
+                                ui.selectable_value(&mut self.tuning.temperament, temperament, temperament.name());
+                            }
+                        });
+                    // The tonic only shifts unequal temperaments
+                    ui.add_enabled_ui(self.tuning.temperament != Temperament::Equal, |ui| {
+                        egui::ComboBox::from_label("Tonic")
+                            .selected_text(NOTE_NAMES[self.tuning.tonic.rem_euclid(12) as usize])
+                            .show_ui(ui, |ui| {
+                                for (tonic, name) in (0..).zip(NOTE_NAMES) {
+                                    ui.selectable_value(&mut self.tuning.tonic, tonic, name);
+                                }
+                            });
+                    });
+                })
+                .response
+                .on_disabled_hover_text("Stop recording to change the tuning");
             });
             
             ui.add_space(10.0);
@@ -234,6 +343,12 @@ impl eframe::App for PitchPerfecterApp {
                 ui.checkbox(&mut self.enable_spectral_gating, "Spectral Gating (Noise Reduction)")
                     .on_hover_text("Reduce background noise using spectral gating (requires noise profile)");
                 
+                ui.checkbox(&mut self.enable_hum_removal, "Mains Hum Removal")
+                    .on_hover_text("Detect 50/60 Hz hum during the first second and notch it and its harmonics out");
+                
+                ui.checkbox(&mut self.enable_agc, "Automatic Gain Control")
+                    .on_hover_text("Boost quiet or distant singing before detection and gate background noise");
+                
                 ui.add_space(5.0);
                 ui.separator();
                 ui.add_space(5.0);
@@ -258,7 +373,7 @@ impl eframe::App for PitchPerfecterApp {
                 if self.noise_profile.is_none() && self.enable_spectral_gating {
                     ui.colored_label(
                         egui::Color32::YELLOW,
-                        "⚠ Spectral gating requires a noise profile"
+                        "⚠ Spectral gating requires a noise profile; the bandpass filter is used instead"
                     );
                 }
                 
@@ -275,7 +390,12 @@ impl eframe::App for PitchPerfecterApp {
                 if let Some(ref pitch) = self.current_pitch {
                     ui.horizontal(|ui| {
                         ui.label("Note:");
-                        ui.heading(&pitch.note_name);
+                        ui.heading(pitch.note.to_string());
+                    });
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Cents:");
+                        ui.heading(format!("{:+.0}", pitch.note.cents));
                     });
                     
                     ui.horizontal(|ui| {
@@ -290,6 +410,13 @@ impl eframe::App for PitchPerfecterApp {
                 } else {
                     ui.label("No pitch detected");
                 }
+                
+                if let Some(gain_db) = self.input_gain_db {
+                    ui.horizontal(|ui| {
+                        ui.label("Input Gain:");
+                        ui.label(format!("{:+.1} dB", gain_db));
+                    });
+                }
             });
             
             ui.add_space(10.0);
@@ -1,77 +1,70 @@
-use audio_utils::{MonoAudio, LatencyMetrics};
-use audio_cleaning::clean_audio_for_pitch;
-use pitch_detection_utils::{ThreadSafeYinDetector, MonoPitchDetector, hz_to_note_name};
-
-const WINDOW_SIZE: usize = 2048;
+use audio_utils::LatencyMetrics;
+use audio_cleaning::{AudioProcessor, CleaningPipeline};
+use pitch_detection_utils::{MonoPitchDetector, NoteReading, StreamingPitchTracker, Tuning, hz_to_note};
 
 #[derive(Debug, Clone)]
 pub struct PitchResult {
     pub frequency: f32,
-    pub note_name: String,
+    /// Nearest note under the recording's tuning, with the deviation in cents
+    pub note: NoteReading,
     pub clarity: f32,
     pub latency: LatencyMetrics,
 }
 
+/// A message from the audio thread to the UI
+#[derive(Debug, Clone)]
+pub enum AudioUpdate {
+    /// Pitch of a voiced frame
+    Pitch(PitchResult),
+    /// Gain applied by the cleaning pipeline's AGC. It is sent for every block, voiced
+    /// or not, so the gain meter keeps following the input through silence.
+    InputGain(f32),
+}
+
 pub struct PitchProcessor;
 
 impl PitchProcessor {
-    /// Process an audio chunk and return pitch detection result.
+    /// Clean a block of input samples and track its pitch.
     /// This is a static method that can be called from any thread.
-    pub fn process_audio_chunk(
-        detector: &mut ThreadSafeYinDetector,
-        samples: Vec<f32>,
-        sample_rate: u32,
-        enable_bandpass: bool,
-        enable_spectral_gating: bool,
-        noise_profile: Option<&audio_cleaning::Spectrum>,
+    ///
+    /// The cleaning pipeline is run in streaming mode, so consecutive blocks
+    /// share filter state and the spectral gate's overlapping frames; a block may be
+    /// any size the audio device delivers. The tracker buffers the cleaned samples and analyses a
+    /// window every hop, so a block may complete no frames or several; a pitch update
+    /// is returned for each voiced one, with its note read under `tuning`. If the
+    /// pipeline reports a gain, an input gain update comes first.
+    pub fn process_samples<D: MonoPitchDetector>(
+        tracker: &mut StreamingPitchTracker<D>,
+        samples: &mut [f32],
+        cleaning: Option<&mut CleaningPipeline>,
+        tuning: &Tuning,
         mut latency: LatencyMetrics,
-    ) -> Option<PitchResult> {
+    ) -> Vec<AudioUpdate> {
         // Mark the start of processing
         latency.mark_processing_start();
         
-        // Only process if we have enough samples
-        if samples.len() < WINDOW_SIZE {
-            return None;
-        }
-        
-        // Create audio object
-        let audio = MonoAudio::new(samples, sample_rate);
-        
         // Apply cleaning if enabled
-        let processed_audio = if enable_bandpass || enable_spectral_gating {
-            // Use noise profile only if spectral gating is enabled
-            // If noise_profile is None, cloned() returns None
-            let noise_spectrum = if enable_spectral_gating {
-                noise_profile.cloned()
-            } else {
-                None
-            };
-            
-            // Note: This uses defaults for bandpass (80-800 Hz for vocals)
-            // If noise_spectrum is None, clean_audio_for_pitch falls back to bandpass filtering
-            clean_audio_for_pitch(&audio, noise_spectrum, None)
-        } else {
-            audio
-        };
-        
-        // Detect pitch
-        let pitch_result = detector.get_mono_pitch(processed_audio);
-        
-        // Mark the end of processing (whether or not detection succeeded)
-        latency.mark_processing_end();
+        let input_gain_db = cleaning.and_then(|pipeline| {
+            pipeline.process_block(samples);
+            pipeline.gain_db()
+        });
         
-        // Return result with latency metrics
-        if let Some(pitch) = pitch_result {
-            let note_name = hz_to_note_name(pitch.frequency);
-            
-            Some(PitchResult {
-                frequency: pitch.frequency,
-                note_name,
-                clarity: pitch.clarity,
-                latency,
-            })
-        } else {
-            None
-        }
+        let gain_update = input_gain_db.map(AudioUpdate::InputGain);
+        let pitch_updates = tracker
+            .push(samples)
+            .filter_map(|frame| {
+                let frequency = frame.frequency?;
+                let note = hz_to_note(frequency, tuning)?;
+                // Each result's processing ends once its frame has been analysed
+                let mut latency = latency.clone();
+                latency.mark_processing_end();
+                Some(AudioUpdate::Pitch(PitchResult {
+                    frequency,
+                    note,
+                    clarity: frame.clarity,
+                    latency,
+                }))
+            });
+        gain_update.into_iter().chain(pitch_updates).collect()
     }
 }
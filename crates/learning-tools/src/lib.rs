@@ -2,7 +2,7 @@
 //! 
 //! This crate provides sight-singing learning logic and exercises.
 //! Features include:
-//! - Musical note representation (A4, C#5, etc.)
+//! - Musical note representation (A4, C#5, etc.), converted to and from frequencies under a configurable `Tuning`
 //! - Musical interval definitions and utilities
 //! - Spaced repetition system for learning
 //! - Interval learning plan with automatic scheduling
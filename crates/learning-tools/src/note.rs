@@ -4,6 +4,7 @@
 //! as discrete pitch classes with octaves (e.g., A4, C#5, Bb3).
 
 use std::fmt;
+use pitch_detection_utils::{hz_to_note, Tuning};
 
 /// Represents a musical pitch class (C, C#, D, etc.)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -159,17 +160,22 @@ impl Note {
 
     /// Convert note to frequency in Hz (using equal temperament, A4 = 440 Hz)
     pub fn to_frequency(&self) -> f32 {
-        let midi = self.to_midi();
-        440.0 * 2.0_f32.powf((midi - 69) as f32 / 12.0)
+        self.to_frequency_in(&Tuning::default())
+    }
+
+    /// Convert note to frequency in Hz under `tuning`
+    pub fn to_frequency_in(&self, tuning: &Tuning) -> f32 {
+        tuning.frequency(self.to_midi())
     }
 
     /// Create a note from a frequency (rounds to nearest note)
     pub fn from_frequency(hz: f32) -> Option<Self> {
-        if hz <= 0.0 {
-            return None;
-        }
-        let midi = (69.0 + 12.0 * (hz / 440.0).log2()).round() as i32;
-        Some(Self::from_midi(midi))
+        Self::from_frequency_in(hz, &Tuning::default())
+    }
+
+    /// Create a note from a frequency under `tuning` (rounds to nearest note)
+    pub fn from_frequency_in(hz: f32, tuning: &Tuning) -> Option<Self> {
+        hz_to_note(hz, tuning).map(|reading| Self::from_midi(reading.midi))
     }
 
     /// Apply an interval to this note
@@ -278,6 +284,17 @@ mod tests {
         assert_eq!(note.octave, 4);
     }
 
+    #[test]
+    fn test_note_frequency_with_tuning() {
+        let baroque = Tuning::with_reference(415.0);
+        let a4 = Note::new(PitchClass::A, 4);
+        assert!((a4.to_frequency_in(&baroque) - 415.0).abs() < 0.01);
+        // Modern A4 is a semitone higher at baroque pitch
+        assert_eq!(Note::from_frequency_in(440.0, &baroque), Some(Note::new(PitchClass::ASharp, 4)));
+        assert_eq!(Note::from_frequency_in(415.0, &baroque), Some(a4));
+        assert_eq!(Note::from_frequency_in(0.0, &baroque), None);
+    }
+
     #[test]
     fn test_note_transpose() {
         let a4 = Note::new(PitchClass::A, 4);
@@ -0,0 +1,425 @@
+//! Export of Analysis Results
+//!
+//! Writes a [`PitchTrack`] as CSV or JSON for spreadsheets and scripts, and
+//! [`NoteEvent`]s as a Standard MIDI File for DAWs and notation software.
+//!
+//! All formats are encoded by hand, so no serialization or MIDI crates are required.
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+use crate::pitch_tracking::notes::NoteEvent;
+use crate::pitch_tracking::track::{PitchFrame, PitchTrack};
+use crate::music_notation::Tuning;
+
+/// Header row of [`PitchTrack::to_csv`]
+const CSV_HEADER: &str = "time_s,frequency_hz,midi_note,cents_offset,clarity";
+
+/// Highest 14-bit pitch-bend value; the centre is half of one more than this
+const MAX_PITCH_BEND: u16 = 0x3fff;
+
+impl PitchTrack {
+    /// Encode the track as CSV, one row per frame, with notes named at A4 = 440 Hz in
+    /// equal temperament; see [`PitchTrack::to_csv_in`]
+    pub fn to_csv(&self) -> String {
+        self.to_csv_in(&Tuning::default())
+    }
+
+    /// Encode the track as CSV, one row per frame
+    ///
+    /// Columns are the frame time in seconds, the pitch in Hz, the nearest MIDI note
+    /// under `tuning`, the deviation from that note in cents and the clarity. The
+    /// pitch columns are empty for unvoiced frames.
+    pub fn to_csv_in(&self, tuning: &Tuning) -> String {
+        let mut csv = String::with_capacity((self.len() + 1) * 40);
+        csv.push_str(CSV_HEADER);
+        csv.push('\n');
+        for frame in &self.frames {
+            let (frequency, midi_note, cents) = pitch_fields(frame, tuning);
+            let field = |value: Option<String>| value.unwrap_or_default();
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                frame.time,
+                field(frequency.map(|f| f.to_string())),
+                field(midi_note.map(|n| n.to_string())),
+                field(cents.map(|c| c.to_string())),
+                frame.clarity,
+            );
+        }
+        csv
+    }
+
+    /// Encode the track as JSON, with notes named at A4 = 440 Hz in equal
+    /// temperament; see [`PitchTrack::to_json_in`]
+    pub fn to_json(&self) -> String {
+        self.to_json_in(&Tuning::default())
+    }
+
+    /// Encode the track as JSON: the window layout and an array of frames, with notes
+    /// named under `tuning` and `null` pitch fields for unvoiced frames
+    pub fn to_json_in(&self, tuning: &Tuning) -> String {
+        let mut json = format!(
+            "{{\"window_size\":{},\"hop_size\":{},\"sample_rate\":{},\"frames\":[",
+            self.window_size, self.hop_size, self.sample_rate
+        );
+        for (i, frame) in self.frames.iter().enumerate() {
+            let (frequency, midi_note, cents) = pitch_fields(frame, tuning);
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "{{\"time\":{},\"frequency_hz\":{},\"midi_note\":{},\"cents_offset\":{},\"clarity\":{}}}",
+                json_number(Some(frame.time)),
+                json_number(frequency),
+                midi_note.map_or("null".to_string(), |n| n.to_string()),
+                json_number(cents),
+                json_number(Some(frame.clarity)),
+            );
+        }
+        json.push_str("]}");
+        json
+    }
+
+    /// Write the track, choosing the format from the extension (`csv` or `json`), with
+    /// notes named at A4 = 440 Hz in equal temperament
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.save_in(path, &Tuning::default())
+    }
+
+    /// Write the track with notes named under `tuning`, choosing the format from the
+    /// extension (`csv` or `json`)
+    pub fn save_in(&self, path: impl AsRef<Path>, tuning: &Tuning) -> io::Result<()> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+        let text = match extension.as_deref() {
+            Some("csv") => self.to_csv_in(tuning),
+            Some("json") => self.to_json_in(tuning),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported pitch track format: {}", path.display()),
+                ))
+            }
+        };
+        std::fs::write(path, text)
+    }
+}
+
+/// Pitch in Hz, nearest MIDI note under `tuning` and deviation from it in cents, if
+/// voiced
+fn pitch_fields(frame: &PitchFrame, tuning: &Tuning) -> (Option<f32>, Option<i32>, Option<f32>) {
+    let note = frame.note(tuning);
+    (frame.frequency, note.map(|note| note.midi), note.map(|note| note.cents))
+}
+
+/// A JSON number, or `null` for a missing or non-finite value
+fn json_number(value: Option<f32>) -> String {
+    match value {
+        Some(value) if value.is_finite() => value.to_string(),
+        _ => "null".to_string(),
+    }
+}
+
+/// Configuration for Standard MIDI File export
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MidiExportConfig {
+    /// Resolution of the file in ticks per quarter note. Default: 480
+    pub ticks_per_quarter: u16,
+    /// Tempo written to the file; note times in seconds are converted to beats at
+    /// this tempo. Default: 120.0 BPM
+    pub tempo_bpm: f32,
+    /// MIDI channel, 0 to 15. Default: 0
+    pub channel: u8,
+    /// Note-on velocity, 1 to 127. Default: 96
+    pub velocity: u8,
+    /// Send each note's mean cents offset as a pitch bend just before it. Bends apply
+    /// to the whole channel, so they are exact for notes that do not overlap, as
+    /// produced by the note segmenter. Default: false
+    pub pitch_bend: bool,
+    /// Pitch-bend range in semitones, set on the receiver at the start of the file
+    /// when `pitch_bend` is on. Clamped to 1 to 24. Default: 2
+    pub pitch_bend_range: u8,
+}
+
+impl Default for MidiExportConfig {
+    fn default() -> Self {
+        Self {
+            ticks_per_quarter: 480,
+            tempo_bpm: 120.0,
+            channel: 0,
+            velocity: 96,
+            pitch_bend: false,
+            pitch_bend_range: 2,
+        }
+    }
+}
+
+/// Order of events that fall on the same tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum EventOrder {
+    NoteOff,
+    PitchBend,
+    NoteOn,
+}
+
+/// Encode notes as a single-track (format 0) Standard MIDI File
+pub fn notes_to_midi(notes: &[NoteEvent], config: &MidiExportConfig) -> Vec<u8> {
+    let channel = config.channel & 0x0f;
+    let velocity = config.velocity.clamp(1, 127);
+    let tempo_bpm = if config.tempo_bpm > 0.0 && config.tempo_bpm.is_finite() { config.tempo_bpm } else { 120.0 };
+    let ticks_per_quarter = config.ticks_per_quarter.clamp(1, 0x7fff);
+    let ticks_per_second = tempo_bpm / 60.0 * ticks_per_quarter as f32;
+    let tick = |seconds: f32| (seconds.max(0.0) * ticks_per_second).round() as u32;
+    let bend_range = config.pitch_bend_range.clamp(1, 24);
+
+    let mut events: Vec<(u32, EventOrder, Vec<u8>)> = Vec::with_capacity(3 * notes.len());
+    for note in notes {
+        let key = note.midi_note.min(127);
+        let onset = tick(note.onset);
+        let offset = tick(note.offset).max(onset + 1);
+        if config.pitch_bend {
+            let bend = pitch_bend_value(note.mean_cents_offset, bend_range);
+            events.push((onset, EventOrder::PitchBend, pitch_bend_message(channel, bend)));
+        }
+        events.push((onset, EventOrder::NoteOn, vec![0x90 | channel, key, velocity]));
+        events.push((offset, EventOrder::NoteOff, vec![0x80 | channel, key, 0]));
+    }
+    events.sort_by_key(|&(tick, order, _)| (tick, order));
+
+    let mut track = Vec::new();
+    let microseconds_per_quarter = (60_000_000.0 / tempo_bpm).round().clamp(1.0, 0xff_ffff as f32) as u32;
+    write_event(&mut track, 0, &[0xff, 0x51, 0x03]);
+    track.extend_from_slice(&microseconds_per_quarter.to_be_bytes()[1..]);
+    if config.pitch_bend {
+        // Registered parameter 0 sets the bend range; then deselect it
+        for (controller, value) in [(101, 0), (100, 0), (6, bend_range), (38, 0), (101, 127), (100, 127)] {
+            write_event(&mut track, 0, &[0xb0 | channel, controller, value]);
+        }
+    }
+    let mut last_tick = 0;
+    for (tick, _, message) in &events {
+        write_event(&mut track, tick - last_tick, message);
+        last_tick = *tick;
+    }
+    if config.pitch_bend {
+        write_event(&mut track, 0, &pitch_bend_message(channel, MAX_PITCH_BEND / 2 + 1));
+    }
+    write_event(&mut track, 0, &[0xff, 0x2f, 0x00]);
+
+    let mut bytes = Vec::with_capacity(22 + track.len());
+    bytes.extend_from_slice(b"MThd");
+    bytes.extend_from_slice(&6u32.to_be_bytes());
+    bytes.extend_from_slice(&0u16.to_be_bytes()); // format 0
+    bytes.extend_from_slice(&1u16.to_be_bytes()); // one track
+    bytes.extend_from_slice(&ticks_per_quarter.to_be_bytes());
+    bytes.extend_from_slice(b"MTrk");
+    bytes.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&track);
+    bytes
+}
+
+/// Write notes to a Standard MIDI File; see [`notes_to_midi`]
+pub fn save_midi(path: impl AsRef<Path>, notes: &[NoteEvent], config: &MidiExportConfig) -> io::Result<()> {
+    std::fs::write(path, notes_to_midi(notes, config))
+}
+
+/// 14-bit pitch-bend value for a deviation in cents, given the bend range
+fn pitch_bend_value(cents: f32, range_semitones: u8) -> u16 {
+    let centre = (MAX_PITCH_BEND / 2 + 1) as f32;
+    let range_cents = 100.0 * range_semitones as f32;
+    let cents = if cents.is_finite() { cents } else { 0.0 };
+    (centre + cents / range_cents * centre).round().clamp(0.0, MAX_PITCH_BEND as f32) as u16
+}
+
+fn pitch_bend_message(channel: u8, value: u16) -> Vec<u8> {
+    vec![0xe0 | channel, (value & 0x7f) as u8, (value >> 7) as u8]
+}
+
+/// Append a delta time and an event
+fn write_event(track: &mut Vec<u8>, delta: u32, event: &[u8]) {
+    write_variable_length(track, delta);
+    track.extend_from_slice(event);
+}
+
+/// Append `value` as a MIDI variable-length quantity: 7 bits per byte, most
+/// significant first, with the top bit set on all but the last byte
+fn write_variable_length(bytes: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        groups.push((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    bytes.extend(groups.iter().rev());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track() -> PitchTrack {
+        let mut track = PitchTrack::new(1024, 512, 16000);
+        let pitches = [Some(440.0), None, Some(261.6256 * 2f32.powf(20.0 / 1200.0))];
+        track.frames = pitches
+            .iter()
+            .enumerate()
+            .map(|(i, &frequency)| PitchFrame {
+                time: track.frame_time(i),
+                frequency,
+                clarity: if frequency.is_some() { 0.875 } else { 0.0 },
+                rms: 0.1,
+            })
+            .collect();
+        track
+    }
+
+    fn note(onset: f32, offset: f32, midi_note: u8, mean_cents_offset: f32) -> NoteEvent {
+        NoteEvent { onset, offset, midi_note, mean_cents_offset, stability: 1.0 }
+    }
+
+    /// Delta times and events of the single track in `bytes`; running status is not
+    /// used by the encoder, so each event is self-delimiting
+    fn decode_track(bytes: &[u8]) -> Vec<(u32, Vec<u8>)> {
+        assert_eq!(&bytes[14..18], b"MTrk");
+        let len = u32::from_be_bytes(bytes[18..22].try_into().unwrap()) as usize;
+        let data = &bytes[22..];
+        assert_eq!(data.len(), len);
+        let mut events = Vec::new();
+        let mut i = 0;
+        while i < data.len() {
+            let mut delta = 0;
+            loop {
+                delta = (delta << 7) | (data[i] & 0x7f) as u32;
+                i += 1;
+                if data[i - 1] & 0x80 == 0 {
+                    break;
+                }
+            }
+            let len = match data[i] {
+                0xff => 3 + data[i + 2] as usize,
+                _ => 3,
+            };
+            events.push((delta, data[i..i + len].to_vec()));
+            i += len;
+        }
+        events
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = track().to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], "0.032,440,69,0,0.875");
+        assert_eq!(lines[2], "0.064,,,,0");
+        let fields: Vec<&str> = lines[3].split(',').collect();
+        assert_eq!(fields[2], "60");
+        assert!((fields[3].parse::<f32>().unwrap() - 20.0).abs() < 0.01);
+
+        // At baroque pitch, 440 Hz is A#4 and a little sharp
+        let baroque = track().to_csv_in(&Tuning::with_reference(415.0));
+        let fields: Vec<&str> = baroque.lines().nth(1).unwrap().split(',').collect();
+        assert_eq!(fields[2], "70");
+        assert!((fields[3].parse::<f32>().unwrap() - 1200.0 * (440.0f32 / 415.0).log2() + 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_json() {
+        let json = track().to_json();
+        assert!(json.starts_with("{\"window_size\":1024,\"hop_size\":512,\"sample_rate\":16000,\"frames\":["));
+        assert!(json.contains(
+            "{\"time\":0.032,\"frequency_hz\":440,\"midi_note\":69,\"cents_offset\":0,\"clarity\":0.875}"
+        ));
+        assert!(json.contains(
+            "{\"time\":0.064,\"frequency_hz\":null,\"midi_note\":null,\"cents_offset\":null,\"clarity\":0}"
+        ));
+        assert!(json.ends_with("}]}"));
+        assert_eq!(json_number(Some(f32::NAN)), "null");
+        assert_eq!(PitchTrack::new(1024, 512, 16000).to_json(), "{\"window_size\":1024,\"hop_size\":512,\"sample_rate\":16000,\"frames\":[]}");
+    }
+
+    #[test]
+    fn test_midi_notes() {
+        // Two notes at 120 BPM, 480 ticks per quarter: 960 ticks per second
+        let notes = [note(0.0, 0.5, 62, 12.0), note(0.5, 1.25, 64, -3.0)];
+        let bytes = notes_to_midi(&notes, &MidiExportConfig::default());
+        assert_eq!(&bytes[..14], b"MThd\x00\x00\x00\x06\x00\x00\x00\x01\x01\xe0");
+
+        let events = decode_track(&bytes);
+        assert_eq!(events, vec![
+            (0, vec![0xff, 0x51, 0x03, 0x07, 0xa1, 0x20]),
+            (0, vec![0x90, 62, 96]),
+            (480, vec![0x80, 62, 0]),
+            (0, vec![0x90, 64, 96]),
+            (720, vec![0x80, 64, 0]),
+            (0, vec![0xff, 0x2f, 0x00]),
+        ]);
+    }
+
+    #[test]
+    fn test_midi_pitch_bend() {
+        let config = MidiExportConfig { pitch_bend: true, channel: 3, ..Default::default() };
+        let bytes = notes_to_midi(&[note(0.1, 0.5, 69, 50.0), note(0.5, 1.0, 71, -100.0)], &config);
+        let events: Vec<Vec<u8>> = decode_track(&bytes).into_iter().map(|(_, event)| event).collect();
+
+        // Bend range set to 2 semitones before any note
+        assert_eq!(events[1..7], [
+            vec![0xb3, 101, 0], vec![0xb3, 100, 0], vec![0xb3, 6, 2],
+            vec![0xb3, 38, 0], vec![0xb3, 101, 127], vec![0xb3, 100, 127],
+        ]);
+        let bend = |event: &[u8]| (event[2] as u16) << 7 | event[1] as u16;
+        assert_eq!(events[7][0], 0xe3);
+        assert_eq!(bend(&events[7]), 8192 + 2048);
+        assert_eq!(events[8], vec![0x93, 69, 96]);
+        // The first note ends before the second note's bend
+        assert_eq!(events[9], vec![0x83, 69, 0]);
+        assert_eq!(bend(&events[10]), 8192 - 4096);
+        // Bend is reset at the end
+        assert_eq!(bend(&events[events.len() - 2]), 8192);
+        assert_eq!(pitch_bend_value(500.0, 2), MAX_PITCH_BEND);
+    }
+
+    #[test]
+    fn test_midi_pitch_bend_range_clamped() {
+        // The range sent to the receiver is the one the bends are scaled by
+        for (range, sent) in [(0, 1), (12, 12), (48, 24)] {
+            let config = MidiExportConfig { pitch_bend: true, pitch_bend_range: range, ..Default::default() };
+            let bytes = notes_to_midi(&[note(0.0, 1.0, 60, 50.0)], &config);
+            let events: Vec<Vec<u8>> = decode_track(&bytes).into_iter().map(|(_, event)| event).collect();
+            assert_eq!(events[3], vec![0xb0, 6, sent]);
+            let bend = (events[7][2] as u16) << 7 | events[7][1] as u16;
+            assert_eq!(bend, pitch_bend_value(50.0, sent));
+        }
+    }
+
+    #[test]
+    fn test_variable_length_quantities() {
+        for (value, expected) in [(0, &[0x00][..]), (0x7f, &[0x7f]), (0x80, &[0x81, 0x00]), (0x0fff_ffff, &[0xff, 0xff, 0xff, 0x7f])] {
+            let mut bytes = Vec::new();
+            write_variable_length(&mut bytes, value);
+            assert_eq!(bytes, expected);
+        }
+    }
+
+    #[test]
+    fn test_save_chooses_format_from_extension() {
+        let track = track();
+        // Unique per process, so concurrent test runs do not share files
+        let stem = format!("pitch_detection_export_test_{}", std::process::id());
+        let path = std::env::temp_dir().join(stem).with_extension("txt");
+        assert_eq!(track.save(&path).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let path = path.with_extension("json");
+        track.save(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), track.to_json());
+        std::fs::remove_file(&path).unwrap();
+
+        let path = path.with_extension("mid");
+        let notes = [note(0.0, 1.0, 60, 0.0)];
+        save_midi(&path, &notes, &Default::default()).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), notes_to_midi(&notes, &Default::default()));
+        std::fs::remove_file(path).unwrap();
+    }
+}
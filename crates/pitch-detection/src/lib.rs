@@ -2,16 +2,32 @@
 //! 
 //! This crate provides pitch detection algorithms and utilities for musical
 //! note frequency analysis. It includes:
-//! - YIN pitch detection algorithm
-//! - Pitch tracking over time
-//! - Frequency to musical note conversion
+//! - YIN pitch detection algorithm (native, and wrappers around the `pitch_detection` crate)
+//! - Pitch tracking over time, as timestamped `PitchTrack`s, offline or streaming, with post-processing
+//! - Segmentation of pitch tracks into note events
+//! - Export of pitch tracks to CSV/JSON and of note events to Standard MIDI Files
+//! - Probabilistic YIN (pYIN) with HMM smoothing, offline and fixed-lag
+//! - McLeod (MPM) and autocorrelation detectors, and `DetectorKind` to choose one at runtime
+//! - Frequency to musical note conversion with cents, under a configurable `Tuning` (reference pitch and temperament)
 
 pub mod pitch_tracking;
 pub mod music_notation;
+pub mod export;
 
 pub use pitch_tracking::{
-    detection::{MonoPitchDetector, Pitch},
+    detection::{DetectorInfo, DynPitchDetector, MonoPitchDetector, Pitch},
     detection_algorithms::yin::{ExternalYinDetector, ThreadSafeYinDetector},
+    detection_algorithms::native_yin::{YinConfig, YinDetector, YinError},
+    detection_algorithms::pyin::{FixedLagPyin, PitchCandidate, PyinConfig, PyinDetector, PyinFrame},
+    detection_algorithms::mcleod::McLeodDetector,
+    detection_algorithms::autocorrelation::AutocorrelationDetector,
+    detection_algorithms::resizing::{ExternalDetector, ResizingDetector},
+    detection_algorithms::kind::{AnyDetector, DetectorKind, DetectorSettings, ParseDetectorKindError},
     tracking::{PitchTracker, PitchTrackerConfig},
+    track::{PitchFrame, PitchTrack},
+    streaming::{StreamingFrames, StreamingPitchTracker},
+    post_processing::{post_process, PostProcessingConfig, PostProcessor},
+    notes::{segment_notes, NoteEvent, NoteSegmentationConfig},
 };
-pub use music_notation::hz_to_note_name;
+pub use music_notation::{hz_to_note, hz_to_note_name, NoteReading, Temperament, Tuning, NOTE_NAMES};
+pub use export::{notes_to_midi, save_midi, MidiExportConfig};
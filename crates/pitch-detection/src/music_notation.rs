@@ -1,16 +1,189 @@
-/// Convert a frequency in Hz to the nearest musical note name (e.g., "A4", "C#5")
-pub fn hz_to_note_name(hz: f32) -> String {
-    if hz <= 0.0 {
-        return "N/A".to_string();
+//! Musical Notation
+//!
+//! Conversion of frequencies to notes under a [`Tuning`]: a reference frequency for
+//! A4 and a temperament. [`hz_to_note`] gives the nearest note together with how
+//! far the frequency is from it in cents.
+
+use audio_utils::pitch::{hz_to_midi, midi_to_hz};
+use std::fmt;
+
+/// Names of the pitch classes, from C
+pub const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// Pitch class of A, in semitones above C
+const A_PITCH_CLASS: i32 = 9;
+
+/// How the twelve notes of the octave are spaced
+///
+/// Each temperament is given as the deviation in cents of each note from equal
+/// temperament, for the notes 0 to 11 semitones above the tonic of the [`Tuning`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Temperament {
+    /// Twelve equal semitones
+    #[default]
+    Equal,
+    /// Pure fifths from the tonic, with the wolf fifth between the augmented
+    /// fifth and the minor third
+    Pythagorean,
+    /// 5-limit just intonation: pure thirds and fifths above the tonic
+    Just,
+    /// Quarter-comma meantone: pure major thirds, narrowed fifths
+    QuarterCommaMeantone,
+    /// Werckmeister III well temperament
+    WerckmeisterIII,
+    /// Custom deviations in cents from equal temperament, from the tonic upwards
+    Custom([f32; 12]),
+}
+
+impl Temperament {
+    /// The built-in temperaments
+    pub const NAMED: [Temperament; 5] = [
+        Temperament::Equal,
+        Temperament::Pythagorean,
+        Temperament::Just,
+        Temperament::QuarterCommaMeantone,
+        Temperament::WerckmeisterIII,
+    ];
+
+    /// Human-readable name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Temperament::Equal => "Equal",
+            Temperament::Pythagorean => "Pythagorean",
+            Temperament::Just => "Just intonation",
+            Temperament::QuarterCommaMeantone => "Quarter-comma meantone",
+            Temperament::WerckmeisterIII => "Werckmeister III",
+            Temperament::Custom(_) => "Custom",
+        }
+    }
+
+    /// Deviation in cents from equal temperament of the notes 0 to 11 semitones
+    /// above the tonic
+    pub fn offsets(&self) -> [f32; 12] {
+        match self {
+            Temperament::Equal => [0.0; 12],
+            Temperament::Pythagorean => [
+                0.0, 13.69, 3.91, -5.87, 7.82, -1.96, 11.73, 1.96, 15.64, 5.87, -3.91, 9.78,
+            ],
+            Temperament::Just => [
+                0.0, 11.73, 3.91, 15.64, -13.69, -1.96, -9.78, 1.96, 13.69, -15.64, 17.60, -11.73,
+            ],
+            Temperament::QuarterCommaMeantone => [
+                0.0, -23.95, -6.84, 10.26, -13.69, 3.42, -20.53, -3.42, -27.37, -10.26, 6.84, -17.11,
+            ],
+            Temperament::WerckmeisterIII => [
+                0.0, -9.78, -7.82, -5.87, -9.78, -1.96, -11.73, -3.91, -7.82, -11.73, -3.91, -7.82,
+            ],
+            Temperament::Custom(offsets) => *offsets,
+        }
+    }
+}
+
+impl fmt::Display for Temperament {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Reference pitch and temperament that frequencies are named against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuning {
+    /// Frequency of A4 in Hz, e.g. 415.0 for baroque pitch. Default: 440.0 Hz
+    pub reference_hz: f32,
+    /// Default: Equal
+    pub temperament: Temperament,
+    /// Note the temperament is built on, in semitones above C (0 to 11). Has no
+    /// effect in equal temperament. Default: 0 (C)
+    pub tonic: i32,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            reference_hz: 440.0,
+            temperament: Temperament::Equal,
+            tonic: 0,
+        }
+    }
+}
+
+impl Tuning {
+    /// Equal temperament with A4 at `reference_hz`
+    pub fn with_reference(reference_hz: f32) -> Self {
+        Self {
+            reference_hz,
+            ..Default::default()
+        }
+    }
+
+    /// Deviation in cents of MIDI note `midi` from equal temperament. The
+    /// temperament is shifted so that A stays at the reference frequency.
+    pub fn offset_cents(&self, midi: i32) -> f32 {
+        let offsets = self.temperament.offsets();
+        let offset = |pitch_class: i32| offsets[(pitch_class - self.tonic).rem_euclid(12) as usize];
+        offset(midi) - offset(A_PITCH_CLASS)
+    }
+
+    /// Position of `hz` as a fractional MIDI note number (A4 = 69), in equal-tempered
+    /// semitones from the reference frequency
+    pub fn midi(&self, hz: f32) -> f32 {
+        hz_to_midi(hz, self.reference_hz)
+    }
+
+    /// Frequency of MIDI note `midi` in Hz
+    pub fn frequency(&self, midi: i32) -> f32 {
+        midi_to_hz(midi as f32 + self.offset_cents(midi) / 100.0, self.reference_hz)
     }
-    // A4 = 440 Hz, MIDI note 69
-    let midi = (69.0 + 12.0 * (hz / 440.0).log2()).round() as i32;
-    let note_names = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
-    let note = note_names[(midi.rem_euclid(12)) as usize];
-    let octave = (midi / 12) - 1;
-    format!("{}{}", note, octave)
 }
 
+/// The note nearest a frequency, and how far the frequency is from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteReading {
+    /// MIDI note number (A4 = 69, middle C = 60)
+    pub midi: i32,
+    /// Pitch class name, e.g. "C#"
+    pub name: &'static str,
+    /// Octave number; middle C starts octave 4
+    pub octave: i32,
+    /// Deviation from the note in cents, in about [-50, 50]; positive when sharp
+    pub cents: f32,
+}
+
+impl fmt::Display for NoteReading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.name, self.octave)
+    }
+}
+
+/// Find the note nearest `hz` under `tuning`
+///
+/// Returns `None` if the frequency or the reference frequency is not positive and
+/// finite.
+pub fn hz_to_note(hz: f32, tuning: &Tuning) -> Option<NoteReading> {
+    let valid = |f: f32| f > 0.0 && f.is_finite();
+    if !valid(hz) || !valid(tuning.reference_hz) {
+        return None;
+    }
+    // Position in equal-tempered semitones; tempered notes lie within a few tens of
+    // cents of it, so the nearest one is among its neighbours
+    let equal = tuning.midi(hz);
+    let nearest = equal.round() as i32;
+    let (midi, cents) = (nearest - 1..=nearest + 1)
+        .map(|midi| (midi, 100.0 * (equal - midi as f32) - tuning.offset_cents(midi)))
+        .min_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))?;
+    Some(NoteReading {
+        midi,
+        name: NOTE_NAMES[midi.rem_euclid(12) as usize],
+        octave: midi.div_euclid(12) - 1,
+        cents,
+    })
+}
+
+/// Convert a frequency in Hz to the nearest musical note name (e.g., "A4", "C#5"),
+/// with A4 = 440 Hz in equal temperament
+pub fn hz_to_note_name(hz: f32) -> String {
+    hz_to_note(hz, &Tuning::default()).map_or("N/A".to_string(), |reading| reading.to_string())
+}
 
 #[cfg(test)]
 mod tests {
@@ -38,4 +211,65 @@ mod tests {
         assert_eq!(hz_to_note_name(311.13), "D#4"); // D#4
         assert_eq!(hz_to_note_name(466.16), "A#4"); // A#4
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_hz_to_note_cents_and_reference() {
+        let a4 = hz_to_note(440.0, &Tuning::default()).unwrap();
+        assert_eq!((a4.midi, a4.name, a4.octave), (69, "A", 4));
+        assert!(a4.cents.abs() < 1e-3);
+
+        let sharp_c4 = hz_to_note(261.6256 * 2f32.powf(20.0 / 1200.0), &Tuning::default()).unwrap();
+        assert_eq!(sharp_c4.to_string(), "C4");
+        assert!((sharp_c4.cents - 20.0).abs() < 0.01);
+        let flat_c4 = hz_to_note(261.6256 * 2f32.powf(-45.0 / 1200.0), &Tuning::default()).unwrap();
+        assert!((flat_c4.cents + 45.0).abs() < 0.01);
+
+        // Baroque pitch: 415 Hz is A4, and 440 Hz is about a semitone higher
+        let baroque = Tuning::with_reference(415.0);
+        let a4 = hz_to_note(415.0, &baroque).unwrap();
+        assert_eq!(a4.to_string(), "A4");
+        assert!(a4.cents.abs() < 1e-3);
+        let modern_a4 = hz_to_note(440.0, &baroque).unwrap();
+        assert_eq!(modern_a4.to_string(), "A#4");
+        assert!((modern_a4.cents - 1.27).abs() < 0.05);
+
+        assert_eq!(hz_to_note(0.0, &Tuning::default()), None);
+        assert_eq!(hz_to_note(f32::NAN, &Tuning::default()), None);
+        assert_eq!(hz_to_note(440.0, &Tuning::with_reference(0.0)), None);
+        let low = hz_to_note(7.7, &Tuning::default()).unwrap();
+        assert_eq!((low.midi, low.octave), (-1, -2));
+    }
+
+    #[test]
+    fn test_temperaments() {
+        let just = Tuning { temperament: Temperament::Just, ..Default::default() };
+        // A stays at the reference; the major third above the tonic is pure
+        assert!((just.frequency(69) - 440.0).abs() < 1e-3);
+        assert!((just.frequency(64) / just.frequency(60) - 1.25).abs() < 1e-4);
+        assert!((just.frequency(67) / just.frequency(60) - 1.5).abs() < 1e-4);
+        let e4 = hz_to_note(just.frequency(64), &just).unwrap();
+        assert_eq!(e4.midi, 64);
+        assert!(e4.cents.abs() < 0.01);
+        // Against equal temperament, the just third is 13.69 cents narrow
+        let equal_cents = |midi| hz_to_note(just.frequency(midi), &Tuning::default()).unwrap().cents;
+        assert!((equal_cents(64) - equal_cents(60) + 13.69).abs() < 0.05);
+
+        // Built on D instead of C, D to F# is the pure third
+        let just_in_d = Tuning { tonic: 2, ..just };
+        assert!((just_in_d.frequency(66) / just_in_d.frequency(62) - 1.25).abs() < 1e-4);
+
+        let meantone = Tuning { temperament: Temperament::QuarterCommaMeantone, ..Default::default() };
+        assert!((meantone.frequency(64) / meantone.frequency(60) - 1.25).abs() < 1e-4);
+        let pythagorean = Tuning { temperament: Temperament::Pythagorean, ..Default::default() };
+        assert!((pythagorean.frequency(62) / pythagorean.frequency(60) - 9.0 / 8.0).abs() < 1e-4);
+
+        let custom = Tuning { temperament: Temperament::Custom([10.0; 12]), ..Default::default() };
+        assert!((custom.frequency(60) - Tuning::default().frequency(60)).abs() < 1e-3);
+        for temperament in Temperament::NAMED {
+            let tuning = Tuning { temperament, ..Default::default() };
+            for midi in 40..90 {
+                assert_eq!(hz_to_note(tuning.frequency(midi), &tuning).unwrap().midi, midi, "{}", temperament);
+            }
+        }
+    }
+}
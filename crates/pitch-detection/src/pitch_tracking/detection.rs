@@ -1,8 +1,15 @@
 //! # Pitch Detection Traits
 //! This module defines traits for pitch detection, including a generic `PitchDetector`
 //! and a `MonoPitchDetector` for mono audio sources.
+//!
+//! `MonoPitchDetector` is generic over the audio source, so it cannot be used as a
+//! trait object. [`DynPitchDetector`] is its object-safe counterpart: it works on a
+//! plain `&[f32]` and a sample rate, and reports the detector's preferred window
+//! size and frequency range through [`DetectorInfo`]. Every `MonoPitchDetector` that
+//! also implements `DetectorInfo` is a `DynPitchDetector`, so algorithms can be held
+//! as `Box<dyn DynPitchDetector>` and swapped at runtime.
 
-use audio_utils::{MonoAudioSource};
+use audio_utils::{MonoAudioSlice, MonoAudioSource};
 use pitch_detection;
 
 pub type Pitch = pitch_detection::Pitch<f32>;
@@ -13,4 +20,51 @@ pub trait MonoPitchDetector {
     fn get_pitch<T: MonoAudioSource>(&mut self, audio: T) -> Option<Pitch> {
         self.get_mono_pitch(audio)
     }
-}
\ No newline at end of file
+}
+
+/// What a detector expects of its input
+pub trait DetectorInfo {
+    /// Number of samples per analysis window the detector works best with
+    fn preferred_window_size(&self) -> usize;
+
+    /// Lowest and highest detectable frequency in Hz for windows of the preferred
+    /// size at `sample_rate`
+    fn frequency_range(&self, sample_rate: u32) -> (f32, f32);
+}
+
+/// Object-safe pitch detector working on raw samples
+pub trait DynPitchDetector: DetectorInfo {
+    /// Detect the pitch of `samples` recorded at `sample_rate` Hz
+    fn detect_pitch(&mut self, samples: &[f32], sample_rate: u32) -> Option<Pitch>;
+}
+
+impl<D: MonoPitchDetector + DetectorInfo> DynPitchDetector for D {
+    fn detect_pitch(&mut self, samples: &[f32], sample_rate: u32) -> Option<Pitch> {
+        self.get_mono_pitch(MonoAudioSlice::new(samples, sample_rate))
+    }
+}
+
+/// Lets a boxed detector be used wherever a `MonoPitchDetector` is expected, such
+/// as in a `PitchTracker`
+impl MonoPitchDetector for Box<dyn DynPitchDetector + '_> {
+    fn get_mono_pitch<T: MonoAudioSource>(&mut self, mono_audio: T) -> Option<Pitch> {
+        (**self).detect_pitch(mono_audio.mono_samples(), mono_audio.sample_rate())
+    }
+}
+
+impl DetectorInfo for Box<dyn DynPitchDetector + '_> {
+    fn preferred_window_size(&self) -> usize {
+        (**self).preferred_window_size()
+    }
+
+    fn frequency_range(&self, sample_rate: u32) -> (f32, f32) {
+        (**self).frequency_range(sample_rate)
+    }
+}
+
+/// Frequency range of a detector searching periods from two samples up to half the
+/// window
+pub(crate) fn lag_frequency_range(window_size: usize, sample_rate: u32) -> (f32, f32) {
+    let sample_rate = sample_rate as f32;
+    (sample_rate / (window_size / 2).max(1) as f32, sample_rate / 2.0)
+}
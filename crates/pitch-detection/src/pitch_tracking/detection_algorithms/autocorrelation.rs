@@ -0,0 +1,53 @@
+//! # Autocorrelation Pitch Detection
+//! Wrapper around the autocorrelation detector of the external `pitch_detection`
+//! crate.
+//!
+//! The simplest of the detectors: the period is the first strong peak of the
+//! autocorrelation. Useful as a baseline when comparing algorithms.
+
+use pitch_detection::detector::autocorrelation::AutocorrelationDetector as ExternalAutocorrelationDetector;
+use super::resizing::ResizingDetector;
+
+/// Autocorrelation pitch detector
+///
+/// Accepts signals of any length (see [`ResizingDetector`]).
+pub type AutocorrelationDetector = ResizingDetector<ExternalAutocorrelationDetector<f32>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_utils::MonoAudio;
+    use crate::pitch_tracking::detection::MonoPitchDetector;
+
+    fn sine(freq: f32, sample_rate: u32, n: usize) -> Vec<f32> {
+        (0..n)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_autocorrelation_detector_sine_wave() {
+        let sample_rate = 8000;
+        let audio = MonoAudio::new(sine(440.0, sample_rate, 1024), sample_rate);
+        let mut detector = AutocorrelationDetector::new(0.1, 0.5, 1024, 512);
+        let pitch = detector.get_mono_pitch(audio).expect("voiced");
+        assert!((pitch.frequency - 440.0).abs() < 10.0, "Detected: {}", pitch.frequency);
+    }
+
+    #[test]
+    fn test_autocorrelation_detector_any_length() {
+        let sample_rate = 8000;
+        let mut detector = AutocorrelationDetector::new(0.1, 0.5, 1024, 512);
+        assert!(detector.get_mono_pitch(MonoAudio::new(vec![], sample_rate)).is_none());
+        let _ = detector.get_mono_pitch(MonoAudio::new(vec![0.1, 0.2, 0.3], sample_rate));
+        let pitch = detector.get_mono_pitch(MonoAudio::new(sine(330.0, sample_rate, 1500), sample_rate));
+        assert!(pitch.is_some_and(|p| (p.frequency - 330.0).abs() < 10.0));
+    }
+
+    #[test]
+    fn test_autocorrelation_detector_silent_audio() {
+        let mut detector = AutocorrelationDetector::new(0.1, 0.5, 1024, 512);
+        let pitch = detector.get_mono_pitch(MonoAudio::new(vec![0.0; 1024], 8000));
+        assert!(pitch.is_none(), "Expected None for silent audio");
+    }
+}
@@ -0,0 +1,281 @@
+//! # Choosing a Detector at Runtime
+//! [`DetectorKind`] names each pitch detection algorithm, and [`DetectorKind::create`]
+//! builds it from shared [`DetectorSettings`]. The result is an [`AnyDetector`],
+//! which implements [`MonoPitchDetector`] by dispatching to the chosen algorithm, so
+//! callers (the GUI, a `PitchTracker`, comparison tests) need not be generic over it.
+
+use std::fmt;
+use std::str::FromStr;
+use audio_utils::MonoAudioSource;
+use crate::pitch_tracking::detection::{DetectorInfo, DynPitchDetector, MonoPitchDetector, Pitch};
+use super::autocorrelation::AutocorrelationDetector;
+use super::mcleod::McLeodDetector;
+use super::native_yin::{YinConfig, YinDetector};
+use super::pyin::{PyinConfig, PyinDetector};
+use super::yin::ExternalYinDetector;
+
+/// Settings shared by all detectors. Each algorithm uses the ones it understands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DetectorSettings {
+    /// Samples per analysis window. Default: 2048
+    pub window_size: usize,
+    /// Minimum signal power for the external detectors. Default: 0.1
+    pub power_threshold: f32,
+    /// Minimum clarity for the external detectors (0.0-1.0). Default: 0.7
+    pub clarity_threshold: f32,
+    /// Lowest detectable frequency in Hz, for the native detectors. Default: 60.0 Hz
+    pub min_frequency: f32,
+    /// Highest detectable frequency in Hz, for the native detectors. Default: 1500.0 Hz
+    pub max_frequency: f32,
+}
+
+impl Default for DetectorSettings {
+    fn default() -> Self {
+        Self {
+            window_size: 2048,
+            power_threshold: 0.1,
+            clarity_threshold: 0.7,
+            min_frequency: 60.0,
+            max_frequency: 1500.0,
+        }
+    }
+}
+
+/// Pitch detection algorithm
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DetectorKind {
+    /// YIN from the external `pitch_detection` crate ([`ExternalYinDetector`]). It
+    /// panics on input whose length differs from the window size.
+    Yin,
+    /// In-crate YIN that accepts any input length ([`YinDetector`])
+    #[default]
+    NativeYin,
+    /// Probabilistic YIN, frame by frame ([`PyinDetector`])
+    Pyin,
+    /// McLeod pitch method ([`McLeodDetector`])
+    McLeod,
+    /// Autocorrelation ([`AutocorrelationDetector`])
+    Autocorrelation,
+}
+
+impl DetectorKind {
+    /// Every detector kind
+    pub const ALL: [DetectorKind; 5] = [
+        DetectorKind::Yin,
+        DetectorKind::NativeYin,
+        DetectorKind::Pyin,
+        DetectorKind::McLeod,
+        DetectorKind::Autocorrelation,
+    ];
+
+    /// Short name, as accepted by [`FromStr`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            DetectorKind::Yin => "yin",
+            DetectorKind::NativeYin => "native_yin",
+            DetectorKind::Pyin => "pyin",
+            DetectorKind::McLeod => "mcleod",
+            DetectorKind::Autocorrelation => "autocorrelation",
+        }
+    }
+
+    /// Build a boxed detector of this kind, for code that holds any
+    /// [`DynPitchDetector`]
+    pub fn create_boxed(self, settings: &DetectorSettings) -> Box<dyn DynPitchDetector> {
+        Box::new(self.create(settings))
+    }
+
+    /// Build a detector of this kind
+    pub fn create(self, settings: &DetectorSettings) -> AnyDetector {
+        let DetectorSettings { window_size, power_threshold, clarity_threshold, min_frequency, max_frequency } = *settings;
+        match self {
+            DetectorKind::Yin => AnyDetector::Yin(ExternalYinDetector::new(
+                power_threshold, clarity_threshold, window_size, window_size / 2,
+            )),
+            DetectorKind::NativeYin => AnyDetector::NativeYin(YinDetector::new(YinConfig {
+                min_frequency,
+                max_frequency,
+                ..Default::default()
+            })),
+            DetectorKind::Pyin => AnyDetector::Pyin(PyinDetector::new(PyinConfig {
+                min_frequency,
+                max_frequency,
+                frame_size: window_size,
+                ..Default::default()
+            })),
+            DetectorKind::McLeod => AnyDetector::McLeod(McLeodDetector::new(
+                power_threshold, clarity_threshold, window_size, window_size / 2,
+            )),
+            DetectorKind::Autocorrelation => AnyDetector::Autocorrelation(AutocorrelationDetector::new(
+                power_threshold, clarity_threshold, window_size, window_size / 2,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DetectorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for DetectorKind {
+    type Err = ParseDetectorKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DetectorKind::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ParseDetectorKindError { name: s.to_string() })
+    }
+}
+
+/// Error returned when parsing an unknown detector name
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseDetectorKindError {
+    pub name: String,
+}
+
+impl fmt::Display for ParseDetectorKindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = DetectorKind::ALL.iter().map(DetectorKind::name).collect();
+        write!(f, "Unknown detector '{}', expected one of: {}", self.name, names.join(", "))
+    }
+}
+
+impl std::error::Error for ParseDetectorKindError {}
+
+/// A detector of any kind, chosen at runtime
+pub enum AnyDetector {
+    Yin(ExternalYinDetector),
+    NativeYin(YinDetector),
+    Pyin(PyinDetector),
+    McLeod(McLeodDetector),
+    Autocorrelation(AutocorrelationDetector),
+}
+
+impl AnyDetector {
+    /// The algorithm this detector runs
+    pub fn kind(&self) -> DetectorKind {
+        match self {
+            AnyDetector::Yin(_) => DetectorKind::Yin,
+            AnyDetector::NativeYin(_) => DetectorKind::NativeYin,
+            AnyDetector::Pyin(_) => DetectorKind::Pyin,
+            AnyDetector::McLeod(_) => DetectorKind::McLeod,
+            AnyDetector::Autocorrelation(_) => DetectorKind::Autocorrelation,
+        }
+    }
+}
+
+impl MonoPitchDetector for AnyDetector {
+    fn get_mono_pitch<T: MonoAudioSource>(&mut self, mono_audio: T) -> Option<Pitch> {
+        match self {
+            AnyDetector::Yin(detector) => detector.get_mono_pitch(mono_audio),
+            AnyDetector::NativeYin(detector) => detector.get_mono_pitch(mono_audio),
+            AnyDetector::Pyin(detector) => detector.get_mono_pitch(mono_audio),
+            AnyDetector::McLeod(detector) => detector.get_mono_pitch(mono_audio),
+            AnyDetector::Autocorrelation(detector) => detector.get_mono_pitch(mono_audio),
+        }
+    }
+}
+
+impl DetectorInfo for AnyDetector {
+    fn preferred_window_size(&self) -> usize {
+        match self {
+            AnyDetector::Yin(detector) => detector.preferred_window_size(),
+            AnyDetector::NativeYin(detector) => detector.preferred_window_size(),
+            AnyDetector::Pyin(detector) => detector.preferred_window_size(),
+            AnyDetector::McLeod(detector) => detector.preferred_window_size(),
+            AnyDetector::Autocorrelation(detector) => detector.preferred_window_size(),
+        }
+    }
+
+    fn frequency_range(&self, sample_rate: u32) -> (f32, f32) {
+        match self {
+            AnyDetector::Yin(detector) => detector.frequency_range(sample_rate),
+            AnyDetector::NativeYin(detector) => detector.frequency_range(sample_rate),
+            AnyDetector::Pyin(detector) => detector.frequency_range(sample_rate),
+            AnyDetector::McLeod(detector) => detector.frequency_range(sample_rate),
+            AnyDetector::Autocorrelation(detector) => detector.frequency_range(sample_rate),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_utils::MonoAudio;
+    use crate::pitch_tracking::tracking::PitchTracker;
+
+    fn harmonic_tone(freq: f32, sample_rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                (1..=3).map(|h| (2.0 * std::f32::consts::PI * freq * h as f32 * t).sin() / h as f32).sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_every_kind_detects_the_same_tone() {
+        let sample_rate = 16000;
+        let tone = harmonic_tone(220.0, sample_rate, 2048);
+        for kind in DetectorKind::ALL {
+            let mut detector = kind.create(&DetectorSettings::default());
+            assert_eq!(detector.kind(), kind);
+            let pitch = detector
+                .get_mono_pitch(MonoAudio::new(tone.clone(), sample_rate))
+                .unwrap_or_else(|| panic!("{} found no pitch", kind));
+            assert!((pitch.frequency - 220.0).abs() < 5.0, "{} detected {} Hz", kind, pitch.frequency);
+            assert!(detector.get_mono_pitch(MonoAudio::new(vec![0.0; 2048], sample_rate)).is_none());
+        }
+    }
+
+    #[test]
+    fn test_boxed_detectors_report_their_info() {
+        let sample_rate = 16000;
+        let tone = harmonic_tone(220.0, sample_rate, 2048);
+        let settings = DetectorSettings { min_frequency: 80.0, max_frequency: 1000.0, ..Default::default() };
+        let mut detectors: Vec<Box<dyn DynPitchDetector>> =
+            DetectorKind::ALL.iter().map(|kind| kind.create_boxed(&settings)).collect();
+        for detector in &mut detectors {
+            assert_eq!(detector.preferred_window_size(), 2048);
+            let (low, high) = detector.frequency_range(sample_rate);
+            assert!(low > 0.0 && low <= 80.0 && high > 220.0 && high <= 8000.0);
+            let pitch = detector.detect_pitch(&tone, sample_rate).expect("voiced");
+            assert!((pitch.frequency - 220.0).abs() < 5.0);
+        }
+        assert_eq!(detectors[1].frequency_range(sample_rate), (80.0, 1000.0));
+        assert_eq!(detectors[0].frequency_range(sample_rate), (15.625, 8000.0));
+    }
+
+    #[test]
+    fn test_default_kind_accepts_any_length() {
+        let mut detector = DetectorKind::default().create(&DetectorSettings::default());
+        assert_eq!(detector.kind(), DetectorKind::NativeYin);
+        let tone = harmonic_tone(220.0, 16000, 1500);
+        let pitch = detector.get_mono_pitch(MonoAudio::new(tone, 16000)).expect("voiced");
+        assert!((pitch.frequency - 220.0).abs() < 5.0);
+        assert!(detector.get_mono_pitch(MonoAudio::new(vec![0.1; 3], 16000)).is_none());
+    }
+
+    #[test]
+    fn test_parse_kind_names() {
+        for kind in DetectorKind::ALL {
+            assert_eq!(kind.to_string().parse::<DetectorKind>(), Ok(kind));
+        }
+        assert_eq!(" McLeod ".parse::<DetectorKind>(), Ok(DetectorKind::McLeod));
+        let err = "crepe".parse::<DetectorKind>().unwrap_err();
+        assert!(err.to_string().contains("crepe") && err.to_string().contains("autocorrelation"));
+    }
+
+    #[test]
+    fn test_tracker_with_runtime_kind() {
+        let sample_rate = 16000;
+        let audio = MonoAudio::new(harmonic_tone(330.0, sample_rate, 8192), sample_rate);
+        let detector = "mcleod".parse::<DetectorKind>().unwrap().create(&DetectorSettings::default());
+        let track = PitchTracker::new(detector, 2048, 1024).pitches(audio);
+        assert_eq!(track.len(), 7);
+        assert!(track.frames.iter().all(|f| f.frequency.is_some_and(|f| (f - 330.0).abs() < 5.0)));
+    }
+}
@@ -1 +1,7 @@
-pub mod yin;
\ No newline at end of file
+pub mod yin;
+pub mod native_yin;
+pub mod pyin;
+pub mod mcleod;
+pub mod autocorrelation;
+pub mod resizing;
+pub mod kind;
@@ -0,0 +1,286 @@
+//! # Native YIN Pitch Detection
+//! An in-crate implementation of YIN (de Cheveigné & Kawahara, 2002).
+//!
+//! Unlike the wrappers around the external `pitch_detection` crate, it accepts a
+//! signal of any length: unusable input is reported as a [`YinError`] instead of a
+//! panic, so a live pipeline cannot crash on an odd-sized chunk.
+
+use audio_utils::MonoAudioSource;
+use crate::pitch_tracking::detection::{lag_frequency_range, DetectorInfo, MonoPitchDetector, Pitch};
+
+/// Configuration for the native YIN detector
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct YinConfig {
+    /// Absolute threshold on the cumulative mean normalized difference. The first
+    /// dip below it gives the period. Lower values are stricter. Default: 0.15
+    pub threshold: f32,
+    /// Lowest detectable frequency in Hz. Default: 60.0 Hz
+    pub min_frequency: f32,
+    /// Highest detectable frequency in Hz. Default: 1500.0 Hz
+    pub max_frequency: f32,
+    /// Refine the period by parabolic interpolation around the dip. Default: true
+    pub interpolate: bool,
+}
+
+impl Default for YinConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.15,
+            min_frequency: 60.0,
+            max_frequency: 1500.0,
+            interpolate: true,
+        }
+    }
+}
+
+/// Reasons a signal cannot be analysed
+#[derive(Debug, Clone, PartialEq)]
+pub enum YinError {
+    /// The signal has no samples
+    EmptySignal,
+    /// The sample rate is zero
+    InvalidSampleRate,
+    /// The signal contains NaN or infinite samples
+    NonFiniteSample,
+    /// The frequency range is empty or not positive
+    InvalidFrequencyRange { min_frequency: f32, max_frequency: f32 },
+    /// The signal is too short for even the highest frequency in the range
+    SignalTooShort { len: usize, min_len: usize },
+}
+
+impl std::fmt::Display for YinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            YinError::EmptySignal => write!(f, "Signal is empty"),
+            YinError::InvalidSampleRate => write!(f, "Sample rate must be positive"),
+            YinError::NonFiniteSample => write!(f, "Signal contains NaN or infinite samples"),
+            YinError::InvalidFrequencyRange { min_frequency, max_frequency } => write!(
+                f, "Invalid frequency range: {} Hz to {} Hz", min_frequency, max_frequency
+            ),
+            YinError::SignalTooShort { len, min_len } => write!(
+                f, "Signal of {} samples is too short, need at least {}", len, min_len
+            ),
+        }
+    }
+}
+
+impl std::error::Error for YinError {}
+
+/// YIN pitch detector that never panics
+///
+/// The longest detectable period is limited to half the signal length, so a short
+/// chunk loses the bottom of the frequency range rather than failing outright.
+#[derive(Clone, Debug, Default)]
+pub struct YinDetector {
+    config: YinConfig,
+}
+
+impl YinDetector {
+    /// Create a detector with the given configuration
+    pub fn new(config: YinConfig) -> Self {
+        Self { config }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &YinConfig {
+        &self.config
+    }
+
+    /// Detect the pitch of `signal`
+    ///
+    /// # Returns
+    /// `Ok(None)` if the signal is unvoiced (no dip below the threshold), or an error if
+    /// it cannot be analysed at all
+    pub fn detect(&self, signal: &[f32], sample_rate: u32) -> Result<Option<Pitch>, YinError> {
+        let (min_lag, max_lag) = lag_range(signal, sample_rate, &self.config)?;
+        let cmndf = cumulative_mean_normalized_difference(signal, max_lag);
+
+        // First dip below the threshold, followed down to its local minimum
+        let Some(mut lag) = (min_lag..=max_lag).find(|&lag| cmndf[lag] < self.config.threshold) else {
+            return Ok(None);
+        };
+        while lag < max_lag && cmndf[lag + 1] < cmndf[lag] {
+            lag += 1;
+        }
+
+        let period = if self.config.interpolate && lag > min_lag && lag < max_lag {
+            lag as f32 + parabolic_offset(cmndf[lag - 1], cmndf[lag], cmndf[lag + 1])
+        } else {
+            lag as f32
+        };
+        Ok(Some(Pitch {
+            frequency: sample_rate as f32 / period,
+            clarity: (1.0 - cmndf[lag]).clamp(0.0, 1.0),
+        }))
+    }
+}
+
+impl MonoPitchDetector for YinDetector {
+    fn get_mono_pitch<T: MonoAudioSource>(&mut self, mono_audio: T) -> Option<Pitch> {
+        self.detect(mono_audio.mono_samples(), mono_audio.sample_rate()).ok().flatten()
+    }
+}
+
+/// YIN works on any length; 2048 samples covers the default range down to 60 Hz at
+/// sample rates up to 48 kHz with room to spare
+const PREFERRED_WINDOW_SIZE: usize = 2048;
+
+impl DetectorInfo for YinDetector {
+    fn preferred_window_size(&self) -> usize {
+        PREFERRED_WINDOW_SIZE
+    }
+
+    fn frequency_range(&self, sample_rate: u32) -> (f32, f32) {
+        clamp_frequency_range(&self.config, PREFERRED_WINDOW_SIZE, sample_rate)
+    }
+}
+
+/// Frequency range of a configuration, narrowed to what windows of `window_size`
+/// samples at `sample_rate` can resolve
+pub(crate) fn clamp_frequency_range(config: &YinConfig, window_size: usize, sample_rate: u32) -> (f32, f32) {
+    let (lowest, highest) = lag_frequency_range(window_size, sample_rate);
+    (config.min_frequency.max(lowest), config.max_frequency.min(highest))
+}
+
+/// Check the input and return the range of lags (periods in samples) to search
+pub(crate) fn lag_range(signal: &[f32], sample_rate: u32, config: &YinConfig) -> Result<(usize, usize), YinError> {
+    if signal.is_empty() {
+        return Err(YinError::EmptySignal);
+    }
+    if sample_rate == 0 {
+        return Err(YinError::InvalidSampleRate);
+    }
+    let (min_frequency, max_frequency) = (config.min_frequency, config.max_frequency);
+    if !(min_frequency > 0.0 && min_frequency < max_frequency && max_frequency.is_finite()) {
+        return Err(YinError::InvalidFrequencyRange { min_frequency, max_frequency });
+    }
+    if signal.iter().any(|x| !x.is_finite()) {
+        return Err(YinError::NonFiniteSample);
+    }
+
+    let sample_rate = sample_rate as f32;
+    let min_lag = ((sample_rate / max_frequency).floor() as usize).max(2);
+    let max_lag = Ord::min((sample_rate / min_frequency).ceil() as usize, signal.len() / 2);
+    // Room for the dip and a neighbour on each side
+    if max_lag < min_lag + 1 {
+        return Err(YinError::SignalTooShort { len: signal.len(), min_len: 2 * (min_lag + 1) });
+    }
+    Ok((min_lag, max_lag))
+}
+
+/// Cumulative mean normalized difference function for lags `0..=max_lag`
+///
+/// The difference function is summed over the first `len - max_lag` samples, so
+/// every lag compares the same number of sample pairs. Silence gives 1.0 everywhere.
+pub(crate) fn cumulative_mean_normalized_difference(signal: &[f32], max_lag: usize) -> Vec<f32> {
+    let window = signal.len() - max_lag;
+    let mut cmndf = vec![1.0f32; max_lag + 1];
+    let mut running_sum = 0.0f64;
+    for lag in 1..=max_lag {
+        let difference: f64 = signal[..window]
+            .iter()
+            .zip(&signal[lag..lag + window])
+            .map(|(&a, &b)| {
+                let d = (a - b) as f64;
+                d * d
+            })
+            .sum();
+        running_sum += difference;
+        cmndf[lag] = if running_sum > 0.0 { (difference * lag as f64 / running_sum) as f32 } else { 1.0 };
+    }
+    cmndf
+}
+
+/// Offset of the vertex of the parabola through `(-1, a)`, `(0, b)` and `(1, c)`,
+/// clamped to half a sample
+pub(crate) fn parabolic_offset(a: f32, b: f32, c: f32) -> f32 {
+    let curvature = a - 2.0 * b + c;
+    if curvature <= 0.0 {
+        return 0.0;
+    }
+    (0.5 * (a - c) / curvature).clamp(-0.5, 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_utils::MonoAudio;
+    use sound_synth::white_noise;
+    use std::f32::consts::PI;
+
+    fn harmonic_tone(freq: f32, sample_rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                (1..=5).map(|h| (2.0 * PI * freq * h as f32 * t).sin() / h as f32).sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_harmonic_tones() {
+        let detector = YinDetector::default();
+        for (freq, sample_rate) in [(82.4, 16000), (220.0, 44100), (440.0, 8000), (1046.5, 48000), (196.3, 22050)] {
+            let signal = harmonic_tone(freq, sample_rate, 2048);
+            let pitch = detector.detect(&signal, sample_rate).unwrap().expect("voiced");
+            let cents = 1200.0 * (pitch.frequency / freq).log2();
+            assert!(cents.abs() < 5.0, "{} Hz at {} Hz: detected {} Hz", freq, sample_rate, pitch.frequency);
+            assert!(pitch.clarity > 0.9);
+        }
+    }
+
+    #[test]
+    fn test_interpolation_improves_accuracy() {
+        // 44100 / 331.0 = 133.2 samples, between integer lags
+        let signal = harmonic_tone(331.0, 44100, 2048);
+        let error = |interpolate: bool| {
+            let detector = YinDetector::new(YinConfig { interpolate, ..Default::default() });
+            (detector.detect(&signal, 44100).unwrap().unwrap().frequency - 331.0).abs()
+        };
+        assert!(error(true) < 0.2);
+        assert!(error(true) < error(false));
+    }
+
+    #[test]
+    fn test_never_panics_on_odd_input() {
+        let detector = YinDetector::default();
+        assert!(matches!(detector.detect(&[], 44100), Err(YinError::EmptySignal)));
+        assert!(matches!(detector.detect(&[0.1; 100], 0), Err(YinError::InvalidSampleRate)));
+        assert!(matches!(detector.detect(&[0.1, f32::NAN, 0.2], 44100), Err(YinError::NonFiniteSample)));
+        assert!(matches!(detector.detect(&[0.1; 5], 44100), Err(YinError::SignalTooShort { len: 5, .. })));
+        let inverted = YinDetector::new(YinConfig { min_frequency: 500.0, max_frequency: 100.0, ..Default::default() });
+        assert!(matches!(inverted.detect(&[0.1; 2048], 44100), Err(YinError::InvalidFrequencyRange { .. })));
+
+        // Through the trait, unusable input is simply no pitch
+        let mut detector = YinDetector::default();
+        for len in [0, 1, 7, 100, 1023, 1025, 3001] {
+            let audio = MonoAudio::new(harmonic_tone(440.0, 44100, len), 44100);
+            let _ = detector.get_mono_pitch(audio);
+        }
+    }
+
+    #[test]
+    fn test_short_chunk_keeps_upper_range() {
+        // 300 samples at 16 kHz cover periods up to 150 samples (107 Hz)
+        let detector = YinDetector::default();
+        let pitch = detector.detect(&harmonic_tone(440.0, 16000, 300), 16000).unwrap().unwrap();
+        assert!((pitch.frequency - 440.0).abs() < 3.0);
+    }
+
+    #[test]
+    fn test_silence_and_noise_are_unvoiced() {
+        let detector = YinDetector::default();
+        assert!(matches!(detector.detect(&[0.0; 2048], 44100), Ok(None)));
+        let noise = white_noise(2048, 0.5, 1);
+        assert!(matches!(detector.detect(&noise, 44100), Ok(None)));
+    }
+
+    #[test]
+    fn test_frequency_range_limits_detection() {
+        // A 100 Hz tone is out of range if the search starts at 150 Hz; YIN then
+        // finds no period or a harmonic, never 100 Hz
+        let detector = YinDetector::new(YinConfig { min_frequency: 150.0, ..Default::default() });
+        let pitch = detector.detect(&harmonic_tone(100.0, 16000, 2048), 16000).unwrap();
+        assert!(pitch.is_none_or(|p| p.frequency >= 150.0));
+    }
+}
@@ -0,0 +1,659 @@
+//! # Probabilistic YIN (pYIN)
+//! Pitch tracking after Mauch & Dixon (2014).
+//!
+//! Plain YIN commits to one period per frame using a single threshold, which gives
+//! octave jumps and spurious voiced frames. pYIN runs YIN for a whole distribution of
+//! thresholds (a Beta(2, 18) prior) and turns each frame into a set of
+//! [`PitchCandidate`]s with probabilities. A hidden Markov model over pitch bins,
+//! each with a voiced and an unvoiced state, then picks the most likely smooth,
+//! voicing-aware contour with Viterbi decoding.
+//!
+//! [`PyinDetector::track`] decodes a whole recording. [`FixedLagPyin`] decodes live,
+//! emitting each frame a fixed number of frames late.
+
+use std::collections::VecDeque;
+use audio_utils::MonoAudioSource;
+use crate::pitch_tracking::detection::{DetectorInfo, MonoPitchDetector, Pitch};
+use super::native_yin::{
+    clamp_frequency_range, cumulative_mean_normalized_difference, lag_range, parabolic_offset, YinConfig, YinError,
+};
+
+/// Number of thresholds the YIN threshold prior is sampled at
+const N_THRESHOLDS: usize = 100;
+/// Shape parameters of the Beta prior over YIN thresholds (mean 0.1)
+const BETA_PRIOR: (f32, f32) = (2.0, 18.0);
+/// Share of the candidate probability the HMM treats as evidence of voicing
+const YIN_TRUST: f32 = 0.5;
+
+/// Configuration for pYIN
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PyinConfig {
+    /// Lowest detectable frequency in Hz. Default: 60.0 Hz
+    pub min_frequency: f32,
+    /// Highest detectable frequency in Hz. Default: 1500.0 Hz
+    pub max_frequency: f32,
+    /// Samples per analysis frame when tracking. Default: 2048
+    pub frame_size: usize,
+    /// Samples between frames when tracking. Default: 256
+    pub hop_size: usize,
+    /// Resolution of the HMM pitch states. Default: 5 (20 cents)
+    pub bins_per_semitone: usize,
+    /// Largest pitch change between consecutive frames, in semitones. Default: 2.5
+    pub max_jump_semitones: f32,
+    /// Probability of switching between voiced and unvoiced from one frame to the
+    /// next. Lower values give longer voiced and unvoiced runs. Default: 0.01
+    pub voicing_switch_probability: f32,
+}
+
+impl Default for PyinConfig {
+    fn default() -> Self {
+        Self {
+            min_frequency: 60.0,
+            max_frequency: 1500.0,
+            frame_size: 2048,
+            hop_size: 256,
+            bins_per_semitone: 5,
+            max_jump_semitones: 2.5,
+            voicing_switch_probability: 0.01,
+        }
+    }
+}
+
+/// A possible pitch of one frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PitchCandidate {
+    /// Frequency in Hz
+    pub frequency: f32,
+    /// Probability that this is the frame's pitch. The probabilities of a frame sum
+    /// to at most 1; the remainder is the probability that it is unvoiced.
+    pub probability: f32,
+}
+
+/// One decoded frame of a pYIN track
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PyinFrame {
+    /// Time of the centre of the frame in seconds
+    pub time: f32,
+    /// Decoded pitch in Hz, or `None` if the frame is unvoiced
+    pub frequency: Option<f32>,
+    /// Total probability of the frame's candidates
+    pub voiced_probability: f32,
+}
+
+/// pYIN pitch detector and offline tracker
+#[derive(Clone, Debug)]
+pub struct PyinDetector {
+    config: PyinConfig,
+    /// Probability of each threshold `(i + 1) / N_THRESHOLDS`
+    threshold_prior: Vec<f32>,
+    hmm: PitchHmm,
+}
+
+impl PyinDetector {
+    /// Create a detector with the given configuration
+    ///
+    /// An empty or non-positive frequency range never panics: every frame is then a
+    /// [`YinError::InvalidFrequencyRange`] and the track is unvoiced.
+    pub fn new(config: PyinConfig) -> Self {
+        let (a, b) = BETA_PRIOR;
+        let density: Vec<f32> = (1..=N_THRESHOLDS)
+            .map(|i| {
+                let s = i as f32 / N_THRESHOLDS as f32;
+                s.powf(a - 1.0) * (1.0 - s).powf(b - 1.0)
+            })
+            .collect();
+        let total: f32 = density.iter().sum();
+        Self {
+            config,
+            threshold_prior: density.iter().map(|d| d / total).collect(),
+            hmm: PitchHmm::new(&config),
+        }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &PyinConfig {
+        &self.config
+    }
+
+    /// Pitch candidates of a single frame, most probable first
+    ///
+    /// Each threshold of the prior votes for the first local minimum of the YIN
+    /// function below it. Frames without such a minimum (silence, noise) have no
+    /// candidates.
+    pub fn candidates(&self, frame: &[f32], sample_rate: u32) -> Result<Vec<PitchCandidate>, YinError> {
+        let yin_config = YinConfig {
+            min_frequency: self.config.min_frequency,
+            max_frequency: self.config.max_frequency,
+            ..Default::default()
+        };
+        let (min_lag, max_lag) = lag_range(frame, sample_rate, &yin_config)?;
+        let cmndf = cumulative_mean_normalized_difference(frame, max_lag);
+
+        let minima: Vec<usize> = (min_lag..=max_lag)
+            .filter(|&lag| cmndf[lag] < cmndf[lag - 1] && (lag == max_lag || cmndf[lag] <= cmndf[lag + 1]))
+            .collect();
+        let mut votes = vec![0.0f32; minima.len()];
+        for (i, weight) in self.threshold_prior.iter().enumerate() {
+            let threshold = (i + 1) as f32 / N_THRESHOLDS as f32;
+            if let Some(m) = minima.iter().position(|&lag| cmndf[lag] < threshold) {
+                votes[m] += weight;
+            }
+        }
+
+        let mut candidates: Vec<PitchCandidate> = minima
+            .iter()
+            .zip(&votes)
+            .filter(|(_, &vote)| vote > 0.0)
+            .map(|(&lag, &probability)| {
+                let offset = if lag < max_lag { parabolic_offset(cmndf[lag - 1], cmndf[lag], cmndf[lag + 1]) } else { 0.0 };
+                PitchCandidate { frequency: sample_rate as f32 / (lag as f32 + offset), probability }
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+        Ok(candidates)
+    }
+
+    /// Track the pitch of a whole recording with Viterbi decoding
+    ///
+    /// Frames of `frame_size` samples start every `hop_size` samples; a signal shorter
+    /// than one frame gives an empty track. Frames with NaN or infinite samples are
+    /// unvoiced, as in [`FixedLagPyin`]; only errors that affect every frame (sample
+    /// rate, frequency range) fail the track.
+    pub fn track(&self, signal: &[f32], sample_rate: u32) -> Result<Vec<PyinFrame>, YinError> {
+        let frame_size = self.config.frame_size.max(1);
+        let hop = self.config.hop_size.max(1);
+        if signal.len() < frame_size {
+            return Ok(Vec::new());
+        }
+        let candidates = (0..=(signal.len() - frame_size) / hop)
+            .map(|t| match self.candidates(&signal[t * hop..t * hop + frame_size], sample_rate) {
+                Err(YinError::NonFiniteSample) => Ok(Vec::new()),
+                result => result,
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let frequencies = self.decode(&candidates);
+        Ok(candidates
+            .iter()
+            .zip(frequencies)
+            .enumerate()
+            .map(|(t, (frame, frequency))| PyinFrame {
+                time: self.frame_time(t, sample_rate),
+                frequency,
+                voiced_probability: frame.iter().map(|c| c.probability).sum(),
+            })
+            .collect())
+    }
+
+    /// Most likely pitch of each frame (`None` if unvoiced), given the candidates of
+    /// every frame
+    pub fn decode(&self, frames: &[Vec<PitchCandidate>]) -> Vec<Option<f32>> {
+        let Some(first) = frames.first() else {
+            return Vec::new();
+        };
+        let mut scores = self.hmm.log_observation(first);
+        let mut backpointers = Vec::with_capacity(frames.len() - 1);
+        for frame in &frames[1..] {
+            let mut pointers = Vec::new();
+            scores = self.hmm.step(&scores, &self.hmm.log_observation(frame), &mut pointers);
+            backpointers.push(pointers);
+        }
+
+        let mut state = argmax(&scores);
+        let mut states = vec![state; frames.len()];
+        for (t, pointers) in backpointers.iter().enumerate().rev() {
+            state = pointers[state] as usize;
+            states[t] = state;
+        }
+        states.iter().zip(frames).map(|(&state, candidates)| self.hmm.state_frequency(state, candidates)).collect()
+    }
+
+    /// Time of the centre of frame `t` in seconds
+    fn frame_time(&self, t: usize, sample_rate: u32) -> f32 {
+        (t * self.config.hop_size.max(1) + self.config.frame_size.max(1) / 2) as f32 / sample_rate as f32
+    }
+}
+
+impl Default for PyinDetector {
+    fn default() -> Self {
+        Self::new(PyinConfig::default())
+    }
+}
+
+/// Frame-by-frame detection without smoothing: the most probable candidate, if the
+/// frame is more likely voiced than not. Its clarity is the voiced probability.
+impl MonoPitchDetector for PyinDetector {
+    fn get_mono_pitch<T: MonoAudioSource>(&mut self, mono_audio: T) -> Option<Pitch> {
+        let candidates = self.candidates(mono_audio.mono_samples(), mono_audio.sample_rate()).ok()?;
+        let voiced_probability: f32 = candidates.iter().map(|c| c.probability).sum();
+        let best = candidates.first()?;
+        (voiced_probability >= 0.5).then_some(Pitch { frequency: best.frequency, clarity: voiced_probability })
+    }
+}
+
+impl DetectorInfo for PyinDetector {
+    fn preferred_window_size(&self) -> usize {
+        self.config.frame_size
+    }
+
+    fn frequency_range(&self, sample_rate: u32) -> (f32, f32) {
+        let yin_config = YinConfig {
+            min_frequency: self.config.min_frequency,
+            max_frequency: self.config.max_frequency,
+            ..Default::default()
+        };
+        clamp_frequency_range(&yin_config, self.config.frame_size, sample_rate)
+    }
+}
+
+/// Live pYIN with fixed-lag Viterbi decoding
+///
+/// Each pushed frame is decoded `lag` frames later, from the best path through the
+/// frames seen so far. Larger lags give smoother contours at the cost of latency
+/// (`lag * hop_size` samples). Unusable frames count as unvoiced.
+#[derive(Clone, Debug)]
+pub struct FixedLagPyin {
+    detector: PyinDetector,
+    lag: usize,
+    scores: Option<Vec<f32>>,
+    /// Undecided frames, oldest first: backpointers to the previous frame,
+    /// candidates and voiced probability
+    pending: VecDeque<(Vec<u32>, Vec<PitchCandidate>, f32)>,
+    /// Index of the oldest undecided frame
+    next_frame: usize,
+}
+
+impl FixedLagPyin {
+    /// Create a live tracker that decodes each frame `lag` frames late
+    pub fn new(config: PyinConfig, lag: usize) -> Self {
+        Self {
+            detector: PyinDetector::new(config),
+            lag,
+            scores: None,
+            pending: VecDeque::with_capacity(lag + 1),
+            next_frame: 0,
+        }
+    }
+
+    /// Get a reference to the configuration
+    pub fn config(&self) -> &PyinConfig {
+        self.detector.config()
+    }
+
+    /// Add the next frame (frames are `hop_size` samples apart) and return the frame
+    /// `lag` frames back, once there is one
+    pub fn push_frame(&mut self, frame: &[f32], sample_rate: u32) -> Option<PyinFrame> {
+        let candidates = self.detector.candidates(frame, sample_rate).unwrap_or_default();
+        self.push_candidates(candidates, sample_rate)
+    }
+
+    /// Add the candidates of the next frame, as given by [`PyinDetector::candidates`],
+    /// and return the frame `lag` frames back, once there is one
+    pub fn push_candidates(&mut self, candidates: Vec<PitchCandidate>, sample_rate: u32) -> Option<PyinFrame> {
+        let voiced_probability = candidates.iter().map(|c| c.probability).sum();
+        let observation = self.detector.hmm.log_observation(&candidates);
+        let mut pointers = Vec::new();
+        self.scores = Some(match self.scores.take() {
+            Some(scores) => self.detector.hmm.step(&scores, &observation, &mut pointers),
+            None => observation,
+        });
+        self.pending.push_back((pointers, candidates, voiced_probability));
+
+        if self.pending.len() <= self.lag {
+            return None;
+        }
+        let state = *self.backtrack().first()?;
+        let (_, candidates, voiced_probability) = self.pending.pop_front()?;
+        Some(self.emit(state, &candidates, voiced_probability, sample_rate))
+    }
+
+    /// Decode and return all frames still pending, then start a new track
+    pub fn flush(&mut self, sample_rate: u32) -> Vec<PyinFrame> {
+        let states = self.backtrack();
+        let pending = std::mem::take(&mut self.pending);
+        let frames = states
+            .into_iter()
+            .zip(pending)
+            .map(|(state, (_, candidates, voiced_probability))| self.emit(state, &candidates, voiced_probability, sample_rate))
+            .collect();
+        self.reset();
+        frames
+    }
+
+    /// Forget all frames and start a new track
+    pub fn reset(&mut self) {
+        self.scores = None;
+        self.pending.clear();
+        self.next_frame = 0;
+    }
+
+    /// States of the pending frames on the best path to the current best state
+    fn backtrack(&self) -> Vec<usize> {
+        let Some(scores) = &self.scores else {
+            return Vec::new();
+        };
+        let mut state = argmax(scores);
+        let mut states = vec![state; self.pending.len()];
+        for (k, (pointers, _, _)) in self.pending.iter().enumerate().skip(1).rev() {
+            state = pointers[state] as usize;
+            states[k - 1] = state;
+        }
+        states
+    }
+
+    fn emit(&mut self, state: usize, candidates: &[PitchCandidate], voiced_probability: f32, sample_rate: u32) -> PyinFrame {
+        let frame = PyinFrame {
+            time: self.detector.frame_time(self.next_frame, sample_rate),
+            frequency: self.detector.hmm.state_frequency(state, candidates),
+            voiced_probability,
+        };
+        self.next_frame += 1;
+        frame
+    }
+}
+
+/// HMM over pitch bins on a log-frequency grid. States `0..n_bins` are voiced and
+/// `n_bins..2 * n_bins` unvoiced; unvoiced states keep a pitch so that the contour
+/// can resume near where it stopped.
+#[derive(Clone, Debug)]
+struct PitchHmm {
+    min_frequency: f32,
+    bins_per_octave: f32,
+    n_bins: usize,
+    max_jump: usize,
+    /// Log probability of a jump of `d` bins, at index `d + max_jump`
+    log_jump: Vec<f32>,
+    log_stay: f32,
+    log_switch: f32,
+}
+
+impl PitchHmm {
+    fn new(config: &PyinConfig) -> Self {
+        let bins_per_semitone = config.bins_per_semitone.max(1);
+        let bins_per_octave = 12.0 * bins_per_semitone as f32;
+        // An invalid range (non-positive, inverted or infinite) gets a single bin
+        let octaves = (config.max_frequency / config.min_frequency).log2();
+        let octaves = if octaves.is_finite() { octaves.max(0.0) } else { 0.0 };
+        let n_bins = (octaves * bins_per_octave).floor() as usize + 1;
+        let max_jump = (config.max_jump_semitones * bins_per_semitone as f32).round().clamp(0.0, n_bins as f32) as usize;
+
+        // Triangular distribution of pitch changes
+        let weights: Vec<f32> = (0..=2 * max_jump).map(|i| (max_jump + 1 - i.abs_diff(max_jump)) as f32).collect();
+        let total: f32 = weights.iter().sum();
+        let switch = config.voicing_switch_probability.clamp(1e-6, 0.5);
+        Self {
+            min_frequency: config.min_frequency,
+            bins_per_octave,
+            n_bins,
+            max_jump,
+            log_jump: weights.iter().map(|w| (w / total).ln()).collect(),
+            log_stay: (1.0 - switch).ln(),
+            log_switch: switch.ln(),
+        }
+    }
+
+    fn bin_frequency(&self, bin: usize) -> f32 {
+        self.min_frequency * 2f32.powf(bin as f32 / self.bins_per_octave)
+    }
+
+    fn bin_of(&self, frequency: f32) -> Option<usize> {
+        let bin = (self.bins_per_octave * (frequency / self.min_frequency).log2()).round();
+        (bin >= 0.0 && bin < self.n_bins as f32).then_some(bin as usize)
+    }
+
+    /// Log probability of a frame's candidates in every state
+    fn log_observation(&self, candidates: &[PitchCandidate]) -> Vec<f32> {
+        let mut voiced = vec![0.0f32; self.n_bins];
+        for candidate in candidates {
+            if let Some(bin) = self.bin_of(candidate.frequency) {
+                voiced[bin] += candidate.probability;
+            }
+        }
+        let pitched = YIN_TRUST * voiced.iter().sum::<f32>().min(1.0);
+        let unvoiced = ((1.0 - pitched) / self.n_bins as f32).ln();
+        voiced
+            .iter()
+            .map(|p| (YIN_TRUST * p).ln())
+            .chain(std::iter::repeat_n(unvoiced, self.n_bins))
+            .collect()
+    }
+
+    /// One Viterbi step: the best score of each state after the next frame, with the
+    /// best previous state written to `backpointers`. Scores are kept relative to the
+    /// best one.
+    fn step(&self, scores: &[f32], observation: &[f32], backpointers: &mut Vec<u32>) -> Vec<f32> {
+        let n = self.n_bins;
+        backpointers.clear();
+        backpointers.resize(2 * n, 0);
+        let mut next = vec![f32::NEG_INFINITY; 2 * n];
+        for target in 0..2 * n {
+            let (target_voicing, bin) = (target / n, target % n);
+            let mut best = f32::NEG_INFINITY;
+            let mut best_source = target;
+            let first = bin.saturating_sub(self.max_jump);
+            let last = Ord::min(bin + self.max_jump, n - 1);
+            for source_bin in first..=last {
+                let jump = self.log_jump[source_bin + self.max_jump - bin];
+                for source_voicing in 0..2 {
+                    let source = source_voicing * n + source_bin;
+                    let transition = if source_voicing == target_voicing { self.log_stay } else { self.log_switch };
+                    let score = scores[source] + transition + jump;
+                    if score > best {
+                        best = score;
+                        best_source = source;
+                    }
+                }
+            }
+            next[target] = best + observation[target];
+            backpointers[target] = best_source as u32;
+        }
+        let max = next.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        if max.is_finite() {
+            next.iter_mut().for_each(|score| *score -= max);
+        }
+        next
+    }
+
+    /// Pitch of a decoded state: the most probable candidate within a bin of it, or
+    /// the bin centre
+    fn state_frequency(&self, state: usize, candidates: &[PitchCandidate]) -> Option<f32> {
+        if state >= self.n_bins {
+            return None;
+        }
+        let nearby = candidates
+            .iter()
+            .filter(|c| self.bin_of(c.frequency).is_some_and(|bin| bin.abs_diff(state) <= 1))
+            .max_by(|a, b| a.probability.total_cmp(&b.probability));
+        Some(nearby.map_or_else(|| self.bin_frequency(state), |c| c.frequency))
+    }
+}
+
+fn argmax(values: &[f32]) -> usize {
+    (0..values.len()).max_by(|&a, &b| values[a].total_cmp(&values[b])).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_utils::MonoAudio;
+    use std::f32::consts::PI;
+
+    fn cents(a: f32, b: f32) -> f32 {
+        1200.0 * (a / b).log2()
+    }
+
+    fn candidate(frequency: f32, probability: f32) -> PitchCandidate {
+        PitchCandidate { frequency, probability }
+    }
+
+    #[test]
+    fn test_candidates_of_harmonic_tone() {
+        let sample_rate = 16000;
+        let signal: Vec<f32> = (0..2048)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                (1..=4).map(|h| (2.0 * PI * 233.0 * h as f32 * t).sin() / h as f32).sum()
+            })
+            .collect();
+        let detector = PyinDetector::default();
+        let candidates = detector.candidates(&signal, sample_rate).unwrap();
+        assert!(cents(candidates[0].frequency, 233.0).abs() < 5.0);
+        let total: f32 = candidates.iter().map(|c| c.probability).sum();
+        assert!(total > 0.95 && total <= 1.0 + 1e-5);
+
+        assert!(detector.candidates(&[0.0; 2048], sample_rate).unwrap().is_empty());
+        assert!(detector.candidates(&[], sample_rate).is_err());
+    }
+
+    #[test]
+    fn test_viterbi_removes_octave_jumps() {
+        // Frames at 220 Hz where every fifth frame's best candidate is an octave up
+        let frames: Vec<Vec<PitchCandidate>> = (0..40)
+            .map(|t| {
+                if t % 5 == 2 {
+                    vec![candidate(440.0, 0.6), candidate(220.0, 0.3)]
+                } else {
+                    vec![candidate(220.0, 0.9), candidate(440.0, 0.05)]
+                }
+            })
+            .collect();
+        let decoded = PyinDetector::default().decode(&frames);
+        assert!(decoded.iter().all(|f| f.is_some_and(|f| cents(f, 220.0).abs() < 1.0)));
+    }
+
+    #[test]
+    fn test_viterbi_ignores_spurious_voicing() {
+        // Unvoiced stretch with a few weak, scattered candidates, then a voiced note
+        let mut frames: Vec<Vec<PitchCandidate>> = (0..20)
+            .map(|t| if t % 4 == 1 { vec![candidate(100.0 + 37.0 * t as f32, 0.1)] } else { Vec::new() })
+            .collect();
+        frames.extend((0..20).map(|_| vec![candidate(330.0, 0.95)]));
+        let decoded = PyinDetector::default().decode(&frames);
+        assert!(decoded[..18].iter().all(Option::is_none), "{:?}", &decoded[..20]);
+        assert!(decoded[22..].iter().all(|f| f.is_some_and(|f| cents(f, 330.0).abs() < 1.0)));
+    }
+
+    #[test]
+    fn test_track_glide_then_silence() {
+        // A glide from 200 Hz to 300 Hz, then silence
+        let sample_rate = 16000;
+        let sr = sample_rate as f32;
+        let voiced_len = 16000;
+        let f0 = |i: usize| 200.0 * 1.5f32.powf(i as f32 / voiced_len as f32);
+        let mut phase = 0.0f32;
+        let mut signal: Vec<f32> = (0..voiced_len)
+            .map(|i| {
+                phase += 2.0 * PI * f0(i) / sr;
+                (1..=4).map(|h| (h as f32 * phase).sin() / h as f32).sum()
+            })
+            .collect();
+        signal.extend(vec![0.0; 8000]);
+
+        let track = PyinDetector::default().track(&signal, sample_rate).unwrap();
+        assert_eq!(track.len(), (signal.len() - 2048) / 256 + 1);
+        for frame in &track {
+            let centre = (frame.time * sr) as usize;
+            if centre + 1024 < voiced_len {
+                let f = frame.frequency.expect("voiced frame");
+                assert!(cents(f, f0(centre)).abs() < 30.0, "{} Hz at {} s", f, frame.time);
+            } else if centre > voiced_len + 1024 {
+                assert!(frame.frequency.is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_track_skips_non_finite_frames() {
+        let sample_rate = 16000;
+        let mut signal: Vec<f32> = (0..16000)
+            .map(|i| (2.0 * PI * 196.0 * i as f32 / sample_rate as f32).sin())
+            .collect();
+        signal[8000] = f32::NAN;
+
+        let track = PyinDetector::default().track(&signal, sample_rate).unwrap();
+        assert_eq!(track.len(), (signal.len() - 2048) / 256 + 1);
+        for frame in &track {
+            let start = (frame.time * sample_rate as f32) as usize - 1024;
+            if (start..start + 2048).contains(&8000) {
+                assert!(frame.frequency.is_none() && frame.voiced_probability == 0.0);
+            } else {
+                assert!(frame.frequency.is_some_and(|f| cents(f, 196.0).abs() < 10.0), "{:?}", frame);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixed_lag_matches_offline_decoding() {
+        let frames: Vec<Vec<PitchCandidate>> = (0..30)
+            .map(|t| match t {
+                0..=9 => Vec::new(),
+                _ if t % 7 == 3 => vec![candidate(261.6, 0.4), candidate(523.2, 0.5)],
+                _ => vec![candidate(261.6 + t as f32, 0.9)],
+            })
+            .collect();
+        let offline = PyinDetector::default().decode(&frames);
+
+        // Feed the candidates straight to the decoder, bypassing audio analysis
+        let mut live = FixedLagPyin::new(PyinConfig::default(), 100);
+        for candidates in &frames {
+            assert!(live.push_candidates(candidates.clone(), 16000).is_none());
+        }
+        let flushed: Vec<Option<f32>> = live.flush(16000).iter().map(|f| f.frequency).collect();
+        assert_eq!(flushed, offline);
+    }
+
+    #[test]
+    fn test_fixed_lag_live_audio() {
+        let sample_rate = 16000;
+        let config = PyinConfig { frame_size: 1024, hop_size: 512, ..Default::default() };
+        let mut live = FixedLagPyin::new(config, 4);
+        let signal: Vec<f32> = (0..16000)
+            .map(|i| (2.0 * PI * 196.0 * i as f32 / sample_rate as f32).sin())
+            .collect();
+
+        let mut frames = Vec::new();
+        for start in (0..=signal.len() - 1024).step_by(512) {
+            frames.extend(live.push_frame(&signal[start..start + 1024], sample_rate));
+        }
+        // The first four frames are still pending
+        assert_eq!(frames.len(), (signal.len() - 1024) / 512 + 1 - 4);
+        assert!(frames.iter().all(|f| f.frequency.is_some_and(|f| cents(f, 196.0).abs() < 10.0)));
+        assert!((frames[1].time - (512.0 + 512.0) / 16000.0).abs() < 1e-6);
+        assert_eq!(live.flush(sample_rate).len(), 4);
+
+        // Unusable chunks never panic; they count as unvoiced
+        for _ in 0..5 {
+            live.push_frame(&[0.1; 3], sample_rate);
+        }
+        assert!(live.flush(sample_rate).iter().all(|f| f.frequency.is_none()));
+    }
+
+    #[test]
+    fn test_invalid_frequency_range_does_not_panic() {
+        let signal: Vec<f32> = (0..4096).map(|i| (2.0 * PI * 220.0 * i as f32 / 16000.0).sin()).collect();
+        let ranges = [(0.0, 1500.0), (-50.0, 1500.0), (500.0, 100.0), (60.0, f32::INFINITY), (f32::NAN, 1500.0)];
+        for (min_frequency, max_frequency) in ranges {
+            let config = PyinConfig { min_frequency, max_frequency, hop_size: 0, ..Default::default() };
+            let detector = PyinDetector::new(config);
+            assert!(matches!(detector.track(&signal, 16000), Err(YinError::InvalidFrequencyRange { .. })));
+            assert!(detector.clone().get_mono_pitch(MonoAudio::new(signal.clone(), 16000)).is_none());
+
+            let mut live = FixedLagPyin::new(config, 2);
+            for _ in 0..4 {
+                live.push_frame(&signal[..2048], 16000);
+            }
+            assert!(live.flush(16000).iter().all(|f| f.frequency.is_none()));
+        }
+    }
+
+    #[test]
+    fn test_single_frame_detection() {
+        let sample_rate = 8000;
+        let audio = MonoAudio::new(
+            (0..1024).map(|i| (2.0 * PI * 440.0 * i as f32 / sample_rate as f32).sin()).collect(),
+            sample_rate,
+        );
+        let pitch = PyinDetector::default().get_mono_pitch(audio).unwrap();
+        assert!(cents(pitch.frequency, 440.0).abs() < 10.0);
+        assert!(PyinDetector::default().get_mono_pitch(MonoAudio::new(vec![0.0; 1024], sample_rate)).is_none());
+    }
+}
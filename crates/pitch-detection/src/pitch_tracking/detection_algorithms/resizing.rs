@@ -0,0 +1,145 @@
+//! # Resizing Wrapper for External Detectors
+//! The detectors of the external `pitch_detection` crate are built for one input
+//! length and panic on any other. [`ResizingDetector`] builds another inner detector
+//! when the length changes, so it accepts signals of any length. The McLeod and
+//! autocorrelation wrappers are instances of it.
+
+use pitch_detection::detector::autocorrelation::AutocorrelationDetector as ExternalAutocorrelationDetector;
+use pitch_detection::detector::mcleod::McLeodDetector as ExternalMcLeodDetector;
+use pitch_detection::detector::PitchDetector;
+use crate::pitch_tracking::detection::{lag_frequency_range, DetectorInfo, MonoPitchDetector, Pitch};
+use audio_utils::MonoAudioSource;
+
+/// An external detector that can be built for a given input length
+pub trait ExternalDetector: PitchDetector<f32> {
+    /// Build a detector for signals of `size` samples
+    fn with_size(size: usize, padding: usize) -> Self;
+}
+
+impl ExternalDetector for ExternalMcLeodDetector<f32> {
+    fn with_size(size: usize, padding: usize) -> Self {
+        ExternalMcLeodDetector::new(size, padding)
+    }
+}
+
+impl ExternalDetector for ExternalAutocorrelationDetector<f32> {
+    fn with_size(size: usize, padding: usize) -> Self {
+        ExternalAutocorrelationDetector::new(size, padding)
+    }
+}
+
+/// External pitch detector that accepts signals of any length
+///
+/// A signal whose length differs from `window_size` does not panic: a second inner
+/// detector is built for the new length, with the padding scaled in proportion. The
+/// detector for `window_size` is kept, so returning to it needs no rebuild, and an
+/// empty signal has no pitch.
+pub struct ResizingDetector<D> {
+    pub power_threshold: f32,
+    pub clarity_threshold: f32,
+    pub window_size: usize,
+    pub padding: usize,
+    detector: D,
+    /// Detector for the last length other than `window_size`, and that length
+    resized: Option<(usize, D)>,
+}
+
+impl<D: ExternalDetector> ResizingDetector<D> {
+    /// Create a new detector.
+    ///
+    /// # Arguments
+    /// * `power_threshold` - Minimum signal power threshold
+    /// * `clarity_threshold` - Minimum clarity for pitch detection (0.0-1.0)
+    /// * `window_size` - Size of the analysis window in samples
+    /// * `padding` - Padding size for the detector
+    pub fn new(power_threshold: f32, clarity_threshold: f32, window_size: usize, padding: usize) -> Self {
+        ResizingDetector {
+            power_threshold,
+            clarity_threshold,
+            window_size,
+            padding,
+            detector: D::with_size(window_size, padding),
+            resized: None,
+        }
+    }
+
+    /// Inner detector for signals of `len` samples, built if needed
+    fn detector_for(&mut self, len: usize) -> &mut D {
+        if len == self.window_size {
+            return &mut self.detector;
+        }
+        if self.resized.as_ref().is_none_or(|(size, _)| *size != len) {
+            let padding = len * self.padding / self.window_size.max(1);
+            self.resized = Some((len, D::with_size(len, padding)));
+        }
+        &mut self.resized.as_mut().expect("built above").1
+    }
+}
+
+impl<D: ExternalDetector> MonoPitchDetector for ResizingDetector<D> {
+    fn get_mono_pitch<T: MonoAudioSource>(&mut self, mono_audio: T) -> Option<Pitch> {
+        let sample_rate = mono_audio.sample_rate();
+        let signal = mono_audio.mono_samples();
+        if signal.is_empty() {
+            return None;
+        }
+        let (power_threshold, clarity_threshold) = (self.power_threshold, self.clarity_threshold);
+        self.detector_for(signal.len()).get_pitch(signal, sample_rate as usize, power_threshold, clarity_threshold)
+    }
+}
+
+impl<D> DetectorInfo for ResizingDetector<D> {
+    fn preferred_window_size(&self) -> usize {
+        self.window_size
+    }
+
+    fn frequency_range(&self, sample_rate: u32) -> (f32, f32) {
+        lag_frequency_range(self.window_size, sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_utils::MonoAudio;
+    use std::cell::Cell;
+
+    thread_local! {
+        static BUILDS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Records the size it was built for instead of detecting anything
+    struct SizeRecorder {
+        size: usize,
+        padding: usize,
+    }
+
+    impl ExternalDetector for SizeRecorder {
+        fn with_size(size: usize, padding: usize) -> Self {
+            BUILDS.with(|builds| builds.set(builds.get() + 1));
+            SizeRecorder { size, padding }
+        }
+    }
+
+    impl PitchDetector<f32> for SizeRecorder {
+        fn get_pitch(&mut self, signal: &[f32], _: usize, _: f32, _: f32) -> Option<pitch_detection::Pitch<f32>> {
+            assert_eq!(signal.len(), self.size);
+            Some(pitch_detection::Pitch { frequency: self.padding as f32, clarity: 1.0 })
+        }
+    }
+
+    #[test]
+    fn test_keeps_configured_detector_and_scales_padding() {
+        let mut detector = ResizingDetector::<SizeRecorder>::new(0.1, 0.7, 1024, 256);
+        let mut padding = |len: usize| {
+            detector.get_mono_pitch(MonoAudio::new(vec![0.5; len], 8000)).map(|p| p.frequency)
+        };
+        assert_eq!(padding(1024), Some(256.0));
+        assert_eq!(padding(2048), Some(512.0));
+        assert_eq!(padding(2048), Some(512.0));
+        assert_eq!(padding(1024), Some(256.0));
+        assert_eq!(padding(0), None);
+        // The configured detector, and one rebuild for the new length
+        assert_eq!(BUILDS.with(Cell::get), 2);
+    }
+}
@@ -1,6 +1,6 @@
 use pitch_detection::detector::yin::YINDetector;
 use pitch_detection::detector::PitchDetector;
-use crate::pitch_tracking::detection::{MonoPitchDetector, Pitch};
+use crate::pitch_tracking::detection::{lag_frequency_range, DetectorInfo, MonoPitchDetector, Pitch};
 use audio_utils::MonoAudioSource;
 use std::sync::{Arc, Mutex};
 
@@ -33,6 +33,16 @@ impl MonoPitchDetector for ExternalYinDetector {
     }
 }
 
+impl DetectorInfo for ExternalYinDetector {
+    fn preferred_window_size(&self) -> usize {
+        self.window_size
+    }
+
+    fn frequency_range(&self, sample_rate: u32) -> (f32, f32) {
+        lag_frequency_range(self.window_size, sample_rate)
+    }
+}
+
 /// Thread-safe wrapper around YIN detector that can be sent across threads.
 /// 
 /// This wrapper uses Arc<Mutex<>> to make the detector Send-safe, allowing
@@ -93,6 +103,16 @@ impl MonoPitchDetector for ThreadSafeYinDetector {
     }
 }
 
+impl DetectorInfo for ThreadSafeYinDetector {
+    fn preferred_window_size(&self) -> usize {
+        self.window_size
+    }
+
+    fn frequency_range(&self, sample_rate: u32) -> (f32, f32) {
+        lag_frequency_range(self.window_size, sample_rate)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
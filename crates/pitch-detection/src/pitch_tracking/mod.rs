@@ -1,3 +1,7 @@
 pub mod detection_algorithms;
 pub mod detection;
-pub mod tracking;
\ No newline at end of file
+pub mod tracking;
+pub mod track;
+pub mod streaming;
+pub mod post_processing;
+pub mod notes;
@@ -0,0 +1,370 @@
+//! # Note Segmentation
+//! Turns a pitch track into discrete [`NoteEvent`]s: what note was sung, when, how
+//! far off pitch and how steadily.
+//!
+//! A track is cut into notes in three passes:
+//! 1. Sounding regions: voiced frames above a silence level, bridging short drop-outs.
+//! 2. Energy onsets: a dip in level followed by a rise re-articulates a note, which
+//!    separates repeated notes of the same pitch.
+//! 3. Pitch changes: a note ends when the pitch leaves the running median of the note
+//!    in one direction for long enough. Vibrato swings both ways around the median
+//!    and so does not split a note, while a legato step to a new note does.
+//!
+//! Notes shorter than a minimum length are dropped.
+
+use audio_utils::stats::median;
+use std::ops::Range;
+use crate::music_notation::Tuning;
+use crate::pitch_tracking::track::PitchTrack;
+
+/// Highest MIDI note number
+const MAX_MIDI_NOTE: f32 = 127.0;
+
+/// Configuration for note segmentation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoteSegmentationConfig {
+    /// Frames quieter than this RMS level count as silence even if a pitch was
+    /// detected. Default: 0.01
+    pub silence_rms: f32,
+    /// Unvoiced or silent gaps up to this many milliseconds do not end a sounding
+    /// region. Default: 30.0 ms
+    pub max_gap_ms: f32,
+    /// A rise in level by this factor out of a dip, which itself fell by this factor
+    /// from the preceding peak, starts a new note. Values of 1 or less disable.
+    /// Default: 1.5
+    pub onset_ratio: f32,
+    /// How far, in cents, the pitch may stray from the median of the note so far
+    /// before it counts as a change of note. Default: 60.0 cents
+    pub split_cents: f32,
+    /// How long the pitch must stay beyond `split_cents` on one side of the note
+    /// before the note is split. Default: 80.0 ms
+    pub min_change_ms: f32,
+    /// Notes shorter than this are dropped. Default: 80.0 ms
+    pub min_note_ms: f32,
+    /// Frames within this many cents of the note's median pitch count as stable.
+    /// Default: 25.0 cents
+    pub stability_cents: f32,
+    /// Reference pitch notes are numbered from; only its A4 frequency is used, as
+    /// notes are equal-tempered semitones from it. Default: A4 = 440 Hz
+    pub tuning: Tuning,
+}
+
+impl Default for NoteSegmentationConfig {
+    fn default() -> Self {
+        Self {
+            silence_rms: 0.01,
+            max_gap_ms: 30.0,
+            onset_ratio: 1.5,
+            split_cents: 60.0,
+            min_change_ms: 80.0,
+            min_note_ms: 80.0,
+            stability_cents: 25.0,
+            tuning: Tuning::default(),
+        }
+    }
+}
+
+/// A note found in a pitch track
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoteEvent {
+    /// Start of the note in seconds
+    pub onset: f32,
+    /// End of the note in seconds
+    pub offset: f32,
+    /// Nearest equal-tempered note to the median pitch under the configured tuning,
+    /// as a MIDI note number
+    pub midi_note: u8,
+    /// Mean deviation of the pitch from `midi_note` in cents; positive when sharp
+    pub mean_cents_offset: f32,
+    /// Fraction of the note's voiced frames within `stability_cents` of its median
+    /// pitch, from 0.0 to 1.0
+    pub stability: f32,
+}
+
+impl NoteEvent {
+    /// Length of the note in seconds
+    pub fn duration(&self) -> f32 {
+        self.offset - self.onset
+    }
+}
+
+/// Segment a track into notes
+pub fn segment_notes(track: &PitchTrack, config: &NoteSegmentationConfig) -> Vec<NoteEvent> {
+    let hop_ms = 1000.0 * track.hop_duration();
+    let hop_ms = if hop_ms > 0.0 && hop_ms.is_finite() { hop_ms } else { 1.0 };
+    let max_gap_frames = (config.max_gap_ms.max(0.0) / hop_ms).floor() as usize;
+    let min_change_frames = ((config.min_change_ms / hop_ms).ceil() as usize).max(1);
+    let min_note_frames = ((config.min_note_ms / hop_ms).ceil() as usize).max(1);
+
+    // Pitch of each frame as a MIDI note number, if voiced and not silent
+    let sounding: Vec<Option<f32>> = track
+        .frames
+        .iter()
+        .map(|frame| frame.frequency.map(|hz| config.tuning.midi(hz)).filter(|_| frame.rms >= config.silence_rms))
+        .collect();
+    let levels: Vec<f32> = track.frames.iter().map(|frame| frame.rms).collect();
+
+    let mut notes = Vec::new();
+    for region in sounding_regions(&sounding, max_gap_frames) {
+        for segment in energy_segments(&levels, &sounding, region, config.onset_ratio) {
+            for note in pitch_segments(&sounding, segment, config.split_cents, min_change_frames) {
+                notes.extend(note_event(track, &sounding, note, min_note_frames, config.stability_cents));
+            }
+        }
+    }
+    notes
+}
+
+impl PitchTrack {
+    /// Segment the track into notes; see [`segment_notes`]
+    pub fn notes(&self, config: &NoteSegmentationConfig) -> Vec<NoteEvent> {
+        segment_notes(self, config)
+    }
+}
+
+/// Runs from one sounding frame to another with no gap longer than `max_gap_frames`
+fn sounding_regions(sounding: &[Option<f32>], max_gap_frames: usize) -> Vec<Range<usize>> {
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    for (i, _) in sounding.iter().enumerate().filter(|(_, midi)| midi.is_some()) {
+        match regions.last_mut() {
+            Some(region) if i - region.end <= max_gap_frames => region.end = i + 1,
+            _ => regions.push(i..i + 1),
+        }
+    }
+    regions
+}
+
+/// Split `region` where the level rises by `ratio` out of a dip at least `ratio`
+/// below the preceding peak; the new note starts at the bottom of the dip
+fn energy_segments(levels: &[f32], sounding: &[Option<f32>], region: Range<usize>, ratio: f32) -> Vec<Range<usize>> {
+    if ratio <= 1.0 {
+        return vec![region];
+    }
+    let mut starts = vec![region.start];
+    let mut peak = 0.0;
+    let mut dip: Option<(usize, f32)> = None;
+    for i in region.clone() {
+        let level = levels[i];
+        if let Some((dip_index, dip_level)) = dip {
+            if sounding[i].is_some() && level >= ratio * dip_level && peak >= ratio * dip_level {
+                starts.push(dip_index);
+                peak = level;
+                dip = None;
+                continue;
+            }
+        }
+        if level >= peak {
+            peak = level;
+            dip = None;
+        } else if dip.is_none_or(|(_, dip_level)| level < dip_level) {
+            dip = Some((i, level));
+        }
+    }
+    ranges(starts, region.end)
+}
+
+/// Split `segment` where the pitch stays more than `split_cents` on one side of the
+/// median of the note so far for `min_change_frames` frames; the new note starts at
+/// the first of those frames
+fn pitch_segments(
+    sounding: &[Option<f32>],
+    segment: Range<usize>,
+    split_cents: f32,
+    min_change_frames: usize,
+) -> Vec<Range<usize>> {
+    let mut starts = vec![segment.start];
+    let mut note_start = segment.start;
+    // First frame of the current excursion, and its direction
+    let mut excursion: Option<(usize, bool)> = None;
+    for i in segment.clone() {
+        let Some(midi) = sounding[i] else {
+            continue;
+        };
+        let centre_end = excursion.map_or(i, |(start, _)| start);
+        let mut centre_pitches: Vec<f32> = sounding[note_start..centre_end].iter().flatten().copied().collect();
+        let Some(centre) = median(&mut centre_pitches) else {
+            continue;
+        };
+        let cents = 100.0 * (midi - centre);
+        if cents.abs() <= split_cents {
+            excursion = None;
+            continue;
+        }
+        let sharp = cents > 0.0;
+        let start = match excursion {
+            Some((start, direction)) if direction == sharp => start,
+            _ => i,
+        };
+        excursion = Some((start, sharp));
+        if i + 1 - start >= min_change_frames {
+            starts.push(start);
+            note_start = start;
+            excursion = None;
+        }
+    }
+    ranges(starts, segment.end)
+}
+
+/// Consecutive ranges beginning at each of `starts`, the last ending at `end`
+fn ranges(starts: Vec<usize>, end: usize) -> Vec<Range<usize>> {
+    let ends = starts.iter().skip(1).copied().chain(std::iter::once(end));
+    starts.iter().zip(ends).map(|(&start, end)| start..end).collect()
+}
+
+/// Summarise the sounding frames of `frames` as a note, unless it is too short
+fn note_event(
+    track: &PitchTrack,
+    sounding: &[Option<f32>],
+    frames: Range<usize>,
+    min_note_frames: usize,
+    stability_cents: f32,
+) -> Option<NoteEvent> {
+    let first = frames.clone().find(|&i| sounding[i].is_some())?;
+    let last = frames.rev().find(|&i| sounding[i].is_some())?;
+    if last + 1 - first < min_note_frames {
+        return None;
+    }
+    let mut pitches: Vec<f32> = sounding[first..=last].iter().flatten().copied().collect();
+    let centre = median(&mut pitches)?;
+    let midi_note = centre.round().clamp(0.0, MAX_MIDI_NOTE);
+    let count = pitches.len() as f32;
+    let mean_cents_offset = pitches.iter().map(|midi| 100.0 * (midi - midi_note)).sum::<f32>() / count;
+    let stable = pitches.iter().filter(|&&midi| (100.0 * (midi - centre)).abs() <= stability_cents).count();
+
+    // Each frame stands for the hop centred on its time
+    let half_hop = track.hop_duration() / 2.0;
+    Some(NoteEvent {
+        onset: (track.frames[first].time - half_hop).max(0.0),
+        offset: track.frames[last].time + half_hop,
+        midi_note: midi_note as u8,
+        mean_cents_offset,
+        stability: stable as f32 / count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_utils::{pitch, MonoAudio};
+    use sound_synth::voice_like_single_pitch;
+    use crate::pitch_tracking::detection_algorithms::native_yin::YinDetector;
+    use crate::pitch_tracking::track::PitchFrame;
+    use crate::pitch_tracking::tracking::PitchTracker;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn midi_to_hz(midi: f32) -> f32 {
+        pitch::midi_to_hz(midi, pitch::A4_HZ)
+    }
+
+    fn track(signal: Vec<f32>) -> PitchTrack {
+        PitchTracker::new(YinDetector::default(), 1024, 256).pitches(MonoAudio::new(signal, SAMPLE_RATE))
+    }
+
+    /// Phase-continuous harmonic tone following a per-sample frequency contour
+    fn sing(frequencies: impl Iterator<Item = f32>) -> Vec<f32> {
+        let mut phase = 0.0f32;
+        frequencies
+            .map(|frequency| {
+                phase = (phase + frequency / SAMPLE_RATE as f32).fract();
+                (1..=4).map(|h| (2.0 * std::f32::consts::PI * h as f32 * phase).sin() / h as f32).sum::<f32>() * 0.3
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_separate_notes_with_repeats() {
+        // C4, D4 12 cents sharp, E4 twice, a rest, then G4; 0.4 s each
+        let notes: [Option<f32>; 6] = [Some(60.0), Some(62.12), Some(64.0), Some(64.0), None, Some(67.0)];
+        let len = (0.4 * SAMPLE_RATE as f32) as usize;
+        let signal: Vec<f32> = notes
+            .iter()
+            .flat_map(|note| match note {
+                Some(midi) => voice_like_single_pitch(midi_to_hz(*midi), 4, SAMPLE_RATE as f32, len),
+                None => vec![0.0; len],
+            })
+            .collect();
+        let events = segment_notes(&track(signal), &NoteSegmentationConfig::default());
+
+        let midi: Vec<u8> = events.iter().map(|event| event.midi_note).collect();
+        assert_eq!(midi, vec![60, 62, 64, 64, 67], "{:?}", events);
+        for (event, start) in events.iter().zip([0.0, 0.4, 0.8, 1.2, 2.0]) {
+            assert!((event.onset - start).abs() < 0.06, "{:?}", event);
+            assert!(event.duration() > 0.25 && event.duration() < 0.45, "{:?}", event);
+            assert!(event.stability > 0.9, "{:?}", event);
+        }
+        assert!((events[1].mean_cents_offset - 12.0).abs() < 3.0, "{:?}", events[1]);
+        assert!(events[0].mean_cents_offset.abs() < 3.0, "{:?}", events[0]);
+    }
+
+    #[test]
+    fn test_legato_steps_split_without_energy_cues() {
+        // A3, B3, then a 50 ms glide down to G3, at constant level
+        let second = SAMPLE_RATE as usize / 2;
+        let glide = SAMPLE_RATE as usize / 20;
+        let contour = std::iter::repeat_n(midi_to_hz(57.0), second)
+            .chain(std::iter::repeat_n(midi_to_hz(59.0), second))
+            .chain((0..glide).map(|i| midi_to_hz(59.0 - 4.0 * i as f32 / glide as f32)))
+            .chain(std::iter::repeat_n(midi_to_hz(55.0), second));
+        let events = segment_notes(&track(sing(contour)), &NoteSegmentationConfig::default());
+
+        let midi: Vec<u8> = events.iter().map(|event| event.midi_note).collect();
+        assert_eq!(midi, vec![57, 59, 55], "{:?}", events);
+        assert!((events[1].onset - 0.5).abs() < 0.05, "{:?}", events);
+        assert!((events[2].onset - 1.0).abs() < 0.08, "{:?}", events);
+        // Notes follow each other without a gap
+        assert!((events[0].offset - events[1].onset).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_vibrato_stays_one_note() {
+        // 1.5 s of A4 with 5.5 Hz vibrato of ±50 cents
+        let contour = (0..3 * SAMPLE_RATE as usize / 2).map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            midi_to_hz(69.0 + 0.5 * (2.0 * std::f32::consts::PI * 5.5 * t).sin())
+        });
+        let events = segment_notes(&track(sing(contour)), &NoteSegmentationConfig::default());
+
+        assert_eq!(events.len(), 1, "{:?}", events);
+        assert_eq!(events[0].midi_note, 69);
+        assert!(events[0].mean_cents_offset.abs() < 10.0, "{:?}", events[0]);
+        assert!(events[0].stability < 0.8, "{:?}", events[0]);
+        assert!(events[0].duration() > 1.3, "{:?}", events[0]);
+    }
+
+    #[test]
+    fn test_short_blips_and_silence_are_ignored() {
+        let mut track = PitchTrack::new(640, 160, SAMPLE_RATE);
+        let pattern = [(None, 0.0, 20), (Some(300.0), 0.1, 3), (None, 0.0, 10), (Some(220.0), 0.1, 30), (Some(220.0), 0.001, 20)];
+        for (frequency, rms, count) in pattern {
+            for _ in 0..count {
+                let time = track.frame_time(track.len());
+                track.frames.push(PitchFrame { time, frequency, clarity: 0.9, rms });
+            }
+        }
+        let events = track.notes(&NoteSegmentationConfig::default());
+        assert_eq!(events.len(), 1, "{:?}", events);
+        assert_eq!(events[0].midi_note, 57);
+        assert!((events[0].duration() - 0.3).abs() < 1e-4, "{:?}", events[0]);
+        assert_eq!(events[0].stability, 1.0);
+        assert!(PitchTrack::new(640, 160, SAMPLE_RATE).notes(&Default::default()).is_empty());
+    }
+
+    #[test]
+    fn test_notes_follow_reference_pitch() {
+        // A4 at baroque pitch is G#4 a little flat at 440 Hz
+        let mut track = PitchTrack::new(640, 160, SAMPLE_RATE);
+        for _ in 0..30 {
+            let time = track.frame_time(track.len());
+            track.frames.push(PitchFrame { time, frequency: Some(415.0), clarity: 0.9, rms: 0.1 });
+        }
+        let modern = track.notes(&NoteSegmentationConfig::default());
+        assert_eq!(modern[0].midi_note, 68);
+        assert!((modern[0].mean_cents_offset + 1.3).abs() < 0.1, "{:?}", modern[0]);
+
+        let config = NoteSegmentationConfig { tuning: Tuning::with_reference(415.0), ..Default::default() };
+        let baroque = track.notes(&config);
+        assert_eq!(baroque[0].midi_note, 69);
+        assert!(baroque[0].mean_cents_offset.abs() < 1e-3, "{:?}", baroque[0]);
+    }
+}
@@ -0,0 +1,469 @@
+//! # Pitch Track Post-Processing
+//! Clean-up of raw pitch tracks: removal of short voiced islands, octave-error
+//! correction, gap interpolation, median smoothing and hysteresis.
+//!
+//! Every stage except hysteresis only looks a bounded number of frames back and
+//! ahead. [`post_process`] runs them over a whole track; [`PostProcessor`] runs the
+//! same stages live, holding each frame back by a fixed lookahead, and produces
+//! exactly the same frames.
+
+use audio_utils::stats::median;
+use std::collections::VecDeque;
+use crate::pitch_tracking::track::{PitchFrame, PitchTrack};
+
+/// Configuration for pitch track post-processing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostProcessingConfig {
+    /// Voiced runs shorter than this many milliseconds are made unvoiced.
+    /// 0 disables. Default: 40.0 ms
+    pub min_island_ms: f32,
+    /// Frames on each side whose median pitch a frame is compared with when
+    /// correcting octave errors. 0 disables. Default: 5
+    pub octave_context_frames: usize,
+    /// How close, in cents, a frame must be to an octave above or below the context
+    /// to be folded back. Default: 100.0 cents
+    pub octave_tolerance_cents: f32,
+    /// Unvoiced gaps up to this many milliseconds between voiced frames are filled
+    /// by interpolating the pitch. 0 disables. Default: 50.0 ms
+    pub max_gap_ms: f32,
+    /// Length of the median filter in frames; values of 1 or less disable it.
+    /// Default: 5
+    pub median_frames: usize,
+    /// The output holds its pitch until the input moves more than this many cents
+    /// away. 0 disables. Default: 0.0 cents
+    pub hysteresis_cents: f32,
+}
+
+impl Default for PostProcessingConfig {
+    fn default() -> Self {
+        Self {
+            min_island_ms: 40.0,
+            octave_context_frames: 5,
+            octave_tolerance_cents: 100.0,
+            max_gap_ms: 50.0,
+            median_frames: 5,
+            hysteresis_cents: 0.0,
+        }
+    }
+}
+
+/// Post-process a whole track
+pub fn post_process(track: &PitchTrack, config: &PostProcessingConfig) -> PitchTrack {
+    let stages = Stages::new(config, track.hop_size, track.sample_rate);
+    let mut frames = track.frames.clone();
+    let mut scratch = Scratch::with_capacity(frames.len());
+    stages.apply(&mut frames, &mut scratch);
+    let mut held = None;
+    for frame in &mut frames {
+        apply_hysteresis(frame, &mut held, config.hysteresis_cents);
+    }
+    PitchTrack { frames, ..*track }
+}
+
+impl PitchTrack {
+    /// Post-process the track; see [`post_process`]
+    pub fn post_processed(&self, config: &PostProcessingConfig) -> PitchTrack {
+        post_process(self, config)
+    }
+}
+
+/// Live post-processing with a fixed lookahead
+///
+/// Each frame pushed is returned [`lookahead_frames`](Self::lookahead_frames)
+/// frames later, post-processed exactly as [`post_process`] would.
+pub struct PostProcessor {
+    stages: Stages,
+    hysteresis_cents: f32,
+    held: Option<f32>,
+    /// Recent frames: enough history for the stages, then the frames not yet emitted
+    buffer: VecDeque<PitchFrame>,
+    /// Number of frames at the back of the buffer not yet emitted
+    pending: usize,
+    /// The buffer after the stages, reused so that `push` does not allocate
+    processed: Vec<PitchFrame>,
+    scratch: Scratch,
+}
+
+impl PostProcessor {
+    /// Create a post-processor for frames `hop_size` samples apart at `sample_rate`
+    pub fn new(config: &PostProcessingConfig, hop_size: usize, sample_rate: u32) -> Self {
+        let stages = Stages::new(config, hop_size, sample_rate);
+        let capacity = 2 * stages.reach() + 1;
+        Self {
+            buffer: VecDeque::with_capacity(capacity),
+            processed: Vec::with_capacity(capacity),
+            scratch: Scratch::with_capacity(capacity),
+            stages,
+            hysteresis_cents: config.hysteresis_cents,
+            held: None,
+            pending: 0,
+        }
+    }
+
+    /// Number of frames each frame is held back by
+    pub fn lookahead_frames(&self) -> usize {
+        self.stages.reach()
+    }
+
+    /// Add the next raw frame and return the post-processed frame `lookahead_frames`
+    /// back, once there is one
+    pub fn push(&mut self, frame: PitchFrame) -> Option<PitchFrame> {
+        self.buffer.push_back(frame);
+        self.pending += 1;
+        if self.pending <= self.lookahead_frames() {
+            return None;
+        }
+        self.process_buffer();
+        let mut frame = self.processed[self.processed.len() - self.pending];
+        self.pending -= 1;
+        apply_hysteresis(&mut frame, &mut self.held, self.hysteresis_cents);
+
+        // Keep enough history for the next frame
+        while self.buffer.len() > 2 * self.lookahead_frames() {
+            self.buffer.pop_front();
+        }
+        Some(frame)
+    }
+
+    /// Return the frames still held back, treating the input as finished, and start
+    /// over
+    pub fn flush(&mut self) -> Vec<PitchFrame> {
+        self.process_buffer();
+        let mut frames = self.processed[self.processed.len() - self.pending..].to_vec();
+        for frame in &mut frames {
+            apply_hysteresis(frame, &mut self.held, self.hysteresis_cents);
+        }
+        self.reset();
+        frames
+    }
+
+    /// Forget all frames and start over
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.pending = 0;
+        self.held = None;
+    }
+
+    fn process_buffer(&mut self) {
+        self.processed.clear();
+        self.processed.extend_from_slice(self.buffer.make_contiguous());
+        self.stages.apply(&mut self.processed, &mut self.scratch);
+    }
+}
+
+/// The windowed stages, with lengths converted to frames
+#[derive(Clone, Copy, Debug)]
+struct Stages {
+    min_island_frames: usize,
+    octave_context_frames: usize,
+    octave_tolerance_cents: f32,
+    max_gap_frames: usize,
+    median_radius: usize,
+}
+
+impl Stages {
+    fn new(config: &PostProcessingConfig, hop_size: usize, sample_rate: u32) -> Self {
+        let hop_ms = 1000.0 * hop_size.max(1) as f32 / sample_rate.max(1) as f32;
+        Self {
+            min_island_frames: (config.min_island_ms.max(0.0) / hop_ms).ceil() as usize,
+            octave_context_frames: config.octave_context_frames,
+            octave_tolerance_cents: config.octave_tolerance_cents,
+            max_gap_frames: (config.max_gap_ms.max(0.0) / hop_ms).floor() as usize,
+            median_radius: config.median_frames / 2,
+        }
+    }
+
+    /// Frames each output depends on, on either side
+    fn reach(&self) -> usize {
+        self.min_island_frames.saturating_sub(1)
+            + self.octave_context_frames
+            + self.max_gap_frames
+            + self.median_radius
+    }
+
+    fn apply(&self, frames: &mut [PitchFrame], scratch: &mut Scratch) {
+        remove_short_islands(frames, self.min_island_frames);
+        correct_octaves(frames, self.octave_context_frames, self.octave_tolerance_cents, scratch);
+        fill_gaps(frames, self.max_gap_frames);
+        median_smooth(frames, self.median_radius, scratch);
+    }
+}
+
+/// Buffers reused by the stages
+#[derive(Clone, Debug, Default)]
+struct Scratch {
+    /// Frequencies of the frames before the current stage
+    input: Vec<Option<f32>>,
+    /// Frequencies a median is taken of
+    window: Vec<f32>,
+}
+
+impl Scratch {
+    /// Buffers for up to `frames` frames
+    fn with_capacity(frames: usize) -> Self {
+        Self {
+            input: Vec::with_capacity(frames),
+            window: Vec::with_capacity(frames),
+        }
+    }
+}
+
+fn unvoice(frame: &mut PitchFrame) {
+    frame.frequency = None;
+    frame.clarity = 0.0;
+}
+
+/// Make voiced runs shorter than `min_frames` unvoiced
+fn remove_short_islands(frames: &mut [PitchFrame], min_frames: usize) {
+    for run in frames.chunk_by_mut(|a, b| a.is_voiced() == b.is_voiced()) {
+        if run[0].is_voiced() && run.len() < min_frames {
+            run.iter_mut().for_each(unvoice);
+        }
+    }
+}
+
+/// Fold frames an octave away from the median of their voiced neighbours back
+fn correct_octaves(frames: &mut [PitchFrame], context: usize, tolerance_cents: f32, scratch: &mut Scratch) {
+    if context == 0 {
+        return;
+    }
+    let Scratch { input, window } = scratch;
+    input.clear();
+    input.extend(frames.iter().map(|frame| frame.frequency));
+    for (t, frame) in frames.iter_mut().enumerate() {
+        let Some(frequency) = frame.frequency else {
+            continue;
+        };
+        window.clear();
+        window.extend(
+            (t.saturating_sub(context)..Ord::min(t + context + 1, input.len()))
+                .filter(|&i| i != t)
+                .filter_map(|i| input[i]),
+        );
+        let Some(reference) = median_pitch(window) else {
+            continue;
+        };
+        let cents = 1200.0 * (frequency / reference).log2();
+        if (cents - 1200.0).abs() < tolerance_cents {
+            frame.frequency = Some(frequency / 2.0);
+        } else if (cents + 1200.0).abs() < tolerance_cents {
+            frame.frequency = Some(frequency * 2.0);
+        }
+    }
+}
+
+/// Interpolate the pitch across unvoiced gaps of up to `max_frames` between voiced
+/// frames, evenly in cents. Filled frames take the lower clarity of the two ends.
+fn fill_gaps(frames: &mut [PitchFrame], max_frames: usize) {
+    let mut last_voiced: Option<usize> = None;
+    for t in 0..frames.len() {
+        let Some(end) = frames[t].frequency else {
+            continue;
+        };
+        if let Some(start_index) = last_voiced {
+            let gap = t - start_index - 1;
+            if gap > 0 && gap <= max_frames {
+                let start = frames[start_index].frequency.unwrap_or(end);
+                let clarity = frames[start_index].clarity.min(frames[t].clarity);
+                for (k, frame) in frames[start_index + 1..t].iter_mut().enumerate() {
+                    let fraction = (k + 1) as f32 / (gap + 1) as f32;
+                    frame.frequency = Some(start * (end / start).powf(fraction));
+                    frame.clarity = clarity;
+                }
+            }
+        }
+        last_voiced = Some(t);
+    }
+}
+
+/// Replace each voiced frame by the median of the voiced frames within `radius`
+fn median_smooth(frames: &mut [PitchFrame], radius: usize, scratch: &mut Scratch) {
+    if radius == 0 {
+        return;
+    }
+    let Scratch { input, window } = scratch;
+    input.clear();
+    input.extend(frames.iter().map(|frame| frame.frequency));
+    for (t, frame) in frames.iter_mut().enumerate() {
+        if frame.is_voiced() {
+            window.clear();
+            window.extend(input[t.saturating_sub(radius)..Ord::min(t + radius + 1, input.len())].iter().flatten());
+            frame.frequency = median_pitch(window);
+        }
+    }
+}
+
+/// Median of frequencies, reordering them in place; the geometric mean of the
+/// middle two for an even count
+fn median_pitch(frequencies: &mut [f32]) -> Option<f32> {
+    frequencies.iter_mut().for_each(|f| *f = f.log2());
+    median(frequencies).map(f32::exp2)
+}
+
+/// Hold the previous output while the input stays within `cents` of it
+fn apply_hysteresis(frame: &mut PitchFrame, held: &mut Option<f32>, cents: f32) {
+    if cents <= 0.0 {
+        return;
+    }
+    match (frame.frequency, *held) {
+        (Some(frequency), Some(previous)) if (1200.0 * (frequency / previous).log2()).abs() < cents => {
+            frame.frequency = Some(previous);
+        }
+        (frequency, _) => *held = frequency,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sound_synth::white_noise;
+
+    const HOP: usize = 160;
+    const SAMPLE_RATE: u32 = 16000;
+
+    /// Track with 10 ms frames
+    fn track(frequencies: &[Option<f32>]) -> PitchTrack {
+        let mut track = PitchTrack::new(640, HOP, SAMPLE_RATE);
+        track.frames = frequencies
+            .iter()
+            .enumerate()
+            .map(|(i, &frequency)| PitchFrame {
+                time: track.frame_time(i),
+                frequency,
+                clarity: if frequency.is_some() { 0.9 } else { 0.0 },
+                rms: 0.1,
+            })
+            .collect();
+        track
+    }
+
+    fn none() -> PostProcessingConfig {
+        PostProcessingConfig {
+            min_island_ms: 0.0,
+            octave_context_frames: 0,
+            max_gap_ms: 0.0,
+            median_frames: 1,
+            hysteresis_cents: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_octave_errors_are_corrected() {
+        let mut input = vec![Some(220.0); 20];
+        input[5] = Some(440.0);
+        input[12] = Some(111.0);
+        let config = PostProcessingConfig { octave_context_frames: 3, ..none() };
+        let output = post_process(&track(&input), &config).frequencies();
+        assert_eq!(output[5], Some(220.0));
+        assert_eq!(output[12], Some(222.0));
+        assert!(output.iter().all(|f| f.is_some_and(|f| (f - 220.0).abs() < 3.0)));
+
+        // A real octave leap is kept once the context has moved
+        let leap: Vec<Option<f32>> = (0..20).map(|i| Some(if i < 10 { 220.0 } else { 440.0 })).collect();
+        let output = post_process(&track(&leap), &config).frequencies();
+        assert_eq!(output[2], Some(220.0));
+        assert_eq!(output[17], Some(440.0));
+    }
+
+    #[test]
+    fn test_short_gaps_are_filled() {
+        let mut input = vec![Some(200.0); 5];
+        input.extend([None, None, None]);
+        input.extend(vec![Some(250.0); 5]);
+        input.extend(vec![None; 10]);
+        input.extend(vec![Some(300.0); 5]);
+        let config = PostProcessingConfig { max_gap_ms: 50.0, ..none() };
+        let mut input = track(&input);
+        input.frames[8].clarity = 0.6;
+        let processed = post_process(&input, &config);
+        let output = processed.frequencies();
+
+        // The 30 ms gap is interpolated evenly in cents; the 100 ms gap is left
+        let step = (250.0f32 / 200.0).powf(0.25);
+        for (k, frequency) in output[5..8].iter().enumerate() {
+            assert!((frequency.unwrap() - 200.0 * step.powi(k as i32 + 1)).abs() < 1e-3);
+        }
+        assert!(output[13..23].iter().all(Option::is_none));
+        // Filled frames are as clear as the less clear end
+        assert!(processed.frames[5..8].iter().all(|f| f.clarity == 0.6));
+    }
+
+    #[test]
+    fn test_median_pitch_is_geometric() {
+        assert_eq!(median_pitch(&mut []), None);
+        assert!((median_pitch(&mut [440.0, 220.0]).unwrap() - 311.127).abs() < 1e-2);
+        assert!((median_pitch(&mut [330.0, 110.0, 220.0]).unwrap() - 220.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_short_islands_are_removed() {
+        let mut input = vec![None; 5];
+        input.extend([Some(300.0), Some(310.0)]);
+        input.extend(vec![None; 5]);
+        input.extend(vec![Some(220.0); 6]);
+        let config = PostProcessingConfig { min_island_ms: 40.0, ..none() };
+        let output = post_process(&track(&input), &config);
+        assert!(output.frames[5..7].iter().all(|f| f.frequency.is_none() && f.clarity == 0.0));
+        assert_eq!(output.voiced_regions().count(), 1);
+        assert_eq!(output.voiced_regions().next().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_median_and_hysteresis_smoothing() {
+        let jitter = [0.0, 30.0, -20.0, 5.0, 60.0, -10.0, 0.0, 15.0, -5.0, 10.0];
+        let input: Vec<Option<f32>> = jitter.iter().map(|c| Some(440.0 * 2f32.powf(c / 1200.0))).collect();
+
+        let median = post_process(&track(&input), &PostProcessingConfig { median_frames: 3, ..none() });
+        let cents = |f: Option<f32>| 1200.0 * (f.unwrap() / 440.0).log2();
+        assert!(median.frequencies().iter().all(|&f| cents(f).abs() <= 30.0 + 1e-3));
+        assert!(cents(median.frames[4].frequency).abs() < 10.0 + 1e-3);
+
+        let held = post_process(&track(&input), &PostProcessingConfig { hysteresis_cents: 70.0, ..none() });
+        assert!(held.frequencies().iter().all(|&f| f == Some(440.0)));
+        let loose = post_process(&track(&input), &PostProcessingConfig { hysteresis_cents: 25.0, ..none() });
+        assert_eq!(loose.frames[1].frequency, input[1]);
+    }
+
+    #[test]
+    fn test_streaming_matches_offline() {
+        // Pseudo-random track with octave errors, drop-outs and blips
+        let uniform = white_noise(300, 0.5, 7);
+        let input: Vec<Option<f32>> = (0..300)
+            .map(|i| {
+                let r = uniform[i] + 0.5;
+                let note = i / 40;
+                let base = 200.0 + note as f32 * 30.0;
+                match r {
+                    // Every third note is a rest with the odd blip
+                    _ if note % 3 == 2 => (r < 0.05).then_some(500.0),
+                    r if r < 0.08 => None,
+                    r if r < 0.12 => Some(2.0 * base),
+                    r if r < 0.15 => Some(0.5 * base),
+                    _ => Some(base * (1.0 + 0.01 * (r - 0.5))),
+                }
+            })
+            .collect();
+        let input = track(&input);
+
+        for config in [
+            PostProcessingConfig::default(),
+            PostProcessingConfig { hysteresis_cents: 20.0, median_frames: 7, ..Default::default() },
+            none(),
+        ] {
+            let offline = post_process(&input, &config);
+            let mut live = PostProcessor::new(&config, HOP, SAMPLE_RATE);
+            let mut streamed = Vec::new();
+            for (i, &frame) in input.frames.iter().enumerate() {
+                let output = live.push(frame);
+                assert_eq!(output.is_some(), i >= live.lookahead_frames());
+                streamed.extend(output);
+            }
+            streamed.extend(live.flush());
+            assert_eq!(streamed, offline.frames);
+        }
+        assert_eq!(PostProcessor::new(&none(), HOP, SAMPLE_RATE).lookahead_frames(), 0);
+        // 3 (islands) + 5 (octaves) + 5 (gaps) + 2 (median) frames of 10 ms
+        assert_eq!(PostProcessor::new(&PostProcessingConfig::default(), HOP, SAMPLE_RATE).lookahead_frames(), 15);
+    }
+}
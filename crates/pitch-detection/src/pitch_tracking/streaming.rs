@@ -0,0 +1,205 @@
+//! # Streaming Pitch Tracking
+//! Incremental counterpart of [`PitchTracker`](crate::pitch_tracking::tracking::PitchTracker)
+//! for live input.
+//!
+//! Audio arrives in blocks of whatever size the audio callback delivers. The tracker
+//! keeps the samples of the window in progress, analyses a window every `hop_size`
+//! samples and stamps each frame exactly as the offline tracker would, so a live
+//! track and an offline track of the same recording match frame for frame.
+
+use std::collections::VecDeque;
+use audio_utils::MonoAudioSlice;
+use crate::pitch_tracking::detection::MonoPitchDetector;
+use crate::pitch_tracking::track::{frame_time, rms, PitchFrame};
+
+/// Pitch tracker that consumes audio block by block
+pub struct StreamingPitchTracker<D: MonoPitchDetector> {
+    detector: D,
+    window_size: usize,
+    hop_size: usize,
+    sample_rate: u32,
+    /// Samples from the start of the next window onwards
+    buffer: VecDeque<f32>,
+    /// Incoming samples to drop before the next window starts, when the hop is longer
+    /// than the window
+    skip: usize,
+    /// Index of the next frame
+    frame_index: usize,
+}
+
+impl<D: MonoPitchDetector> StreamingPitchTracker<D> {
+    /// Create a streaming tracker analysing windows of `window_size` samples every
+    /// `hop_size` samples of audio at `sample_rate` Hz. Sizes of zero are treated as
+    /// one.
+    pub fn new(detector: D, window_size: usize, hop_size: usize, sample_rate: u32) -> Self {
+        let window_size = window_size.max(1);
+        let hop_size = hop_size.max(1);
+        Self {
+            detector,
+            window_size,
+            hop_size,
+            sample_rate,
+            buffer: VecDeque::with_capacity(window_size + hop_size),
+            skip: 0,
+            frame_index: 0,
+        }
+    }
+
+    /// Add the next block of samples and return the frames it completes
+    ///
+    /// Frames are analysed as the iterator is consumed. Frames left unconsumed when
+    /// it is dropped are returned by the next call.
+    pub fn push(&mut self, samples: &[f32]) -> StreamingFrames<'_, D> {
+        let skipped = self.skip.min(samples.len());
+        self.skip -= skipped;
+        self.buffer.extend(&samples[skipped..]);
+        StreamingFrames { tracker: self }
+    }
+
+    /// Forget buffered audio and restart the frame count, for a new recording
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.skip = 0;
+        self.frame_index = 0;
+    }
+
+    /// Get a reference to the detector
+    pub fn detector(&self) -> &D {
+        &self.detector
+    }
+
+    /// Replace the detector; buffered audio and timestamps carry on
+    pub fn set_detector(&mut self, detector: D) {
+        self.detector = detector;
+    }
+
+    /// Samples per analysis window
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Samples between the starts of consecutive windows
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    /// Sample rate of the input in Hz
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of frames emitted since creation or the last reset
+    pub fn frames_emitted(&self) -> usize {
+        self.frame_index
+    }
+
+    fn next_frame(&mut self) -> Option<PitchFrame> {
+        if self.buffer.len() < self.window_size {
+            return None;
+        }
+        let window = &self.buffer.make_contiguous()[..self.window_size];
+        let rms = rms(window);
+        let (frequency, clarity) = match self.detector.get_mono_pitch(MonoAudioSlice::new(window, self.sample_rate)) {
+            Some(pitch) => (Some(pitch.frequency), pitch.clarity),
+            None => (None, 0.0),
+        };
+        let frame = PitchFrame {
+            time: frame_time(self.frame_index, self.window_size, self.hop_size, self.sample_rate),
+            frequency,
+            clarity,
+            rms,
+        };
+        self.frame_index += 1;
+
+        let consumed = self.hop_size.min(self.buffer.len());
+        self.buffer.drain(..consumed);
+        self.skip = self.hop_size - consumed;
+        Some(frame)
+    }
+}
+
+/// Frames completed by [`StreamingPitchTracker::push`]
+pub struct StreamingFrames<'a, D: MonoPitchDetector> {
+    tracker: &'a mut StreamingPitchTracker<D>,
+}
+
+impl<D: MonoPitchDetector> Iterator for StreamingFrames<'_, D> {
+    type Item = PitchFrame;
+
+    fn next(&mut self) -> Option<PitchFrame> {
+        self.tracker.next_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_utils::MonoAudio;
+    use crate::pitch_tracking::detection_algorithms::native_yin::YinDetector;
+    use crate::pitch_tracking::tracking::PitchTracker;
+
+    /// A tone that changes pitch halfway, followed by silence
+    fn test_signal(sample_rate: u32) -> Vec<f32> {
+        (0..20000)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                let freq = if i < 8000 { 220.0 } else { 330.0 };
+                if i < 16000 { (2.0 * std::f32::consts::PI * freq * t).sin() } else { 0.0 }
+            })
+            .collect()
+    }
+
+    fn stream_in_chunks(window: usize, hop: usize, signal: &[f32], chunk_sizes: &[usize]) -> Vec<PitchFrame> {
+        let mut tracker = StreamingPitchTracker::new(YinDetector::default(), window, hop, 16000);
+        let mut frames = Vec::new();
+        let mut start = 0;
+        for &size in chunk_sizes.iter().cycle() {
+            if start >= signal.len() {
+                break;
+            }
+            let end = Ord::min(start + size, signal.len());
+            frames.extend(tracker.push(&signal[start..end]));
+            start = end;
+        }
+        assert_eq!(tracker.frames_emitted(), frames.len());
+        frames
+    }
+
+    #[test]
+    fn test_matches_offline_tracker() {
+        let signal = test_signal(16000);
+        for (window, hop) in [(1024, 256), (1024, 1024), (512, 700)] {
+            let offline = PitchTracker::new(YinDetector::default(), window, hop)
+                .pitches(MonoAudio::new(signal.clone(), 16000));
+            for chunk_sizes in [&[1][..], &[64], &[441, 17, 2000, 3], &[signal.len()]] {
+                let streamed = stream_in_chunks(window, hop, &signal, chunk_sizes);
+                assert_eq!(streamed, offline.frames, "window {} hop {} chunks {:?}", window, hop, chunk_sizes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_emits_at_hop_rate() {
+        let mut tracker = StreamingPitchTracker::new(YinDetector::default(), 1024, 256, 16000);
+        assert_eq!(tracker.push(&[0.0; 1000]).count(), 0);
+        assert_eq!(tracker.push(&[0.0; 24]).count(), 1);
+        // Each further hop completes one window
+        assert_eq!(tracker.push(&[0.0; 256 * 3]).count(), 3);
+        assert_eq!(tracker.push(&[0.0; 255]).count(), 0);
+        assert_eq!(tracker.push(&[0.0; 1]).count(), 1);
+    }
+
+    #[test]
+    fn test_unconsumed_frames_carry_over() {
+        let signal = test_signal(16000);
+        let mut tracker = StreamingPitchTracker::new(YinDetector::default(), 1024, 512, 16000);
+        let first = tracker.push(&signal[..4096]).next().unwrap();
+        let rest: Vec<PitchFrame> = tracker.push(&[]).collect();
+        assert_eq!(rest.len(), 6);
+        assert!(rest[0].time > first.time);
+
+        tracker.reset();
+        assert_eq!(tracker.frames_emitted(), 0);
+        assert_eq!(tracker.push(&signal[..1024]).next().unwrap(), first);
+    }
+}
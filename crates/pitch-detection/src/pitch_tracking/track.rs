@@ -0,0 +1,195 @@
+//! # Pitch Tracks
+//! The output of pitch tracking: one [`PitchFrame`] per analysis window, collected in
+//! a [`PitchTrack`] that also records how the windows were laid out.
+//!
+//! A frame without a pitch keeps its clarity and level, so silence (low `rms`) can be
+//! told apart from a failed detection on a loud frame.
+
+use crate::music_notation::{hz_to_note, NoteReading, Tuning};
+
+/// Pitch estimate for one analysis window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PitchFrame {
+    /// Time of the centre of the window in seconds
+    pub time: f32,
+    /// Detected pitch in Hz, or `None` if nothing was detected
+    pub frequency: Option<f32>,
+    /// Detector confidence; 0.0 when nothing was detected
+    pub clarity: f32,
+    /// Root mean square level of the window
+    pub rms: f32,
+}
+
+impl PitchFrame {
+    /// Whether a pitch was detected
+    pub fn is_voiced(&self) -> bool {
+        self.frequency.is_some()
+    }
+
+    /// Pitch as a fractional MIDI note number (A4 = 69 at 440 Hz); see
+    /// [`Tuning::midi`] for other reference pitches
+    pub fn midi(&self) -> Option<f32> {
+        self.frequency.map(|frequency| Tuning::default().midi(frequency))
+    }
+
+    /// Nearest note under `tuning`, with the deviation from it in cents
+    pub fn note(&self, tuning: &Tuning) -> Option<NoteReading> {
+        hz_to_note(self.frequency?, tuning)
+    }
+
+    /// Deviation from the nearest equal-tempered note (A4 = 440 Hz) in cents, in
+    /// about [-50, 50]; see [`PitchFrame::note`] for other tunings
+    pub fn cents_offset(&self) -> Option<f32> {
+        self.note(&Tuning::default()).map(|note| note.cents)
+    }
+
+    /// Distance from `reference_hz` in cents; positive when sharp
+    pub fn cents_from(&self, reference_hz: f32) -> Option<f32> {
+        self.frequency.map(|frequency| 1200.0 * (frequency / reference_hz).log2())
+    }
+}
+
+/// Pitch frames of a recording, with the window layout they were analysed with
+#[derive(Clone, Debug, PartialEq)]
+pub struct PitchTrack {
+    /// One frame per window, in time order
+    pub frames: Vec<PitchFrame>,
+    /// Samples per analysis window
+    pub window_size: usize,
+    /// Samples between the starts of consecutive windows
+    pub hop_size: usize,
+    /// Sample rate of the analysed audio in Hz
+    pub sample_rate: u32,
+}
+
+impl PitchTrack {
+    /// Create an empty track for the given window layout
+    pub fn new(window_size: usize, hop_size: usize, sample_rate: u32) -> Self {
+        Self {
+            frames: Vec::new(),
+            window_size,
+            hop_size,
+            sample_rate,
+        }
+    }
+
+    /// Number of frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether the track has no frames
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Time between consecutive frames in seconds
+    pub fn hop_duration(&self) -> f32 {
+        self.hop_size as f32 / self.sample_rate as f32
+    }
+
+    /// Time of the centre of frame `index` in seconds
+    pub fn frame_time(&self, index: usize) -> f32 {
+        frame_time(index, self.window_size, self.hop_size, self.sample_rate)
+    }
+
+    /// Detected pitch of each frame in Hz
+    pub fn frequencies(&self) -> Vec<Option<f32>> {
+        self.frames.iter().map(|frame| frame.frequency).collect()
+    }
+
+    /// Pitch of each frame as a fractional MIDI note number
+    pub fn midi(&self) -> Vec<Option<f32>> {
+        self.frames.iter().map(PitchFrame::midi).collect()
+    }
+
+    /// Deviation of each frame from its nearest equal-tempered note in cents
+    pub fn cents_offsets(&self) -> Vec<Option<f32>> {
+        self.frames.iter().map(PitchFrame::cents_offset).collect()
+    }
+
+    /// Maximal runs of consecutive voiced frames
+    pub fn voiced_regions(&self) -> impl Iterator<Item = &[PitchFrame]> {
+        self.frames
+            .chunk_by(|a, b| a.is_voiced() == b.is_voiced())
+            .filter(|run| run[0].is_voiced())
+    }
+}
+
+/// Time of the centre of window `index` in seconds, for windows of `window_size`
+/// samples starting every `hop_size` samples
+pub(crate) fn frame_time(index: usize, window_size: usize, hop_size: usize, sample_rate: u32) -> f32 {
+    (index * hop_size) as f32 / sample_rate as f32 + window_size as f32 / (2.0 * sample_rate as f32)
+}
+
+/// Root mean square level of `samples`; 0.0 when empty
+pub(crate) fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(time: f32, frequency: Option<f32>) -> PitchFrame {
+        PitchFrame { time, frequency, clarity: if frequency.is_some() { 0.9 } else { 0.0 }, rms: 0.1 }
+    }
+
+    #[test]
+    fn test_midi_and_cents() {
+        let a4 = frame(0.0, Some(440.0));
+        assert!((a4.midi().unwrap() - 69.0).abs() < 1e-4);
+        assert!(a4.cents_offset().unwrap().abs() < 1e-3);
+
+        // 20 cents sharp of C4, and 30 cents flat of C#4
+        let sharp_c4 = frame(0.0, Some(261.6256 * 2f32.powf(20.0 / 1200.0)));
+        assert!((sharp_c4.cents_offset().unwrap() - 20.0).abs() < 0.01);
+        assert!((sharp_c4.midi().unwrap() - 60.2).abs() < 1e-3);
+        let flat = frame(0.0, Some(261.6256 * 2f32.powf(70.0 / 1200.0)));
+        assert!((flat.cents_offset().unwrap() + 30.0).abs() < 0.01);
+        assert!((a4.cents_from(220.0).unwrap() - 1200.0).abs() < 1e-3);
+
+        let unvoiced = frame(0.0, None);
+        assert!(unvoiced.midi().is_none() && unvoiced.cents_offset().is_none());
+        assert!(unvoiced.note(&Tuning::default()).is_none());
+    }
+
+    #[test]
+    fn test_note_under_tuning() {
+        // 415 Hz is A4 at baroque pitch, and about 100 cents flat at 440 Hz
+        let baroque_a4 = frame(0.0, Some(415.0));
+        let note = baroque_a4.note(&Tuning::with_reference(415.0)).unwrap();
+        assert_eq!((note.midi, note.name), (69, "A"));
+        assert!(note.cents.abs() < 1e-3);
+        assert_eq!(baroque_a4.note(&Tuning::default()).unwrap().name, "G#");
+        assert!((Tuning::with_reference(415.0).midi(415.0) - 69.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_voiced_regions() {
+        let pattern = [None, Some(220.0), Some(221.0), None, None, Some(330.0), None, Some(440.0), Some(441.0)];
+        let mut track = PitchTrack::new(1024, 512, 16000);
+        track.frames = pattern.iter().enumerate().map(|(i, &f)| frame(track.frame_time(i), f)).collect();
+
+        let regions: Vec<Vec<Option<f32>>> =
+            track.voiced_regions().map(|run| run.iter().map(|f| f.frequency).collect()).collect();
+        assert_eq!(regions, vec![
+            vec![Some(220.0), Some(221.0)],
+            vec![Some(330.0)],
+            vec![Some(440.0), Some(441.0)],
+        ]);
+        assert_eq!(track.voiced_regions().next().unwrap()[0].time, track.frame_time(1));
+        assert_eq!(PitchTrack::new(1024, 512, 16000).voiced_regions().count(), 0);
+    }
+
+    #[test]
+    fn test_frame_times() {
+        let track = PitchTrack::new(2048, 512, 16000);
+        assert_eq!(track.frame_time(0), 0.064);
+        assert!((track.frame_time(10) - (0.064 + 10.0 * 0.032)).abs() < 1e-6);
+        assert_eq!(track.hop_duration(), 0.032);
+    }
+}
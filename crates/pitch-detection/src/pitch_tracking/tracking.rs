@@ -3,6 +3,7 @@
 
 use audio_utils::{IterableAudio, MonoAudioSource};
 use crate::pitch_tracking::detection::MonoPitchDetector;
+use crate::pitch_tracking::track::{frame_time, rms, PitchFrame, PitchTrack};
 
 /// Configuration for pitch tracking
 #[derive(Clone, Copy, Debug)]
@@ -37,23 +38,47 @@ impl<D: MonoPitchDetector> PitchTracker<D> {
             step_size,
         }
     }
+
+    /// Get a reference to the detector
+    pub fn detector(&self) -> &D {
+        &self.detector
+    }
+
+    /// Replace the detector. With `D = Box<dyn DynPitchDetector>` this switches
+    /// algorithms at runtime.
+    ///
+    /// [`DynPitchDetector`]: crate::pitch_tracking::detection::DynPitchDetector
+    pub fn set_detector(&mut self, detector: D) {
+        self.detector = detector;
+    }
 }
 
 /// Track pitches in audio streams using a specified pitch detector and configuration.
 impl<D: MonoPitchDetector> PitchTracker<D> {
+    /// Detect the pitch of every full window of `audio`
+    ///
+    /// Windows start every `step_size` samples; each frame is timestamped at the
+    /// centre of its window.
     pub fn pitches(
         &mut self,
         audio: impl IterableAudio + MonoAudioSource,
-    ) -> Vec<f32> {
-        let mut pitches = Vec::new();
-        for window in audio.sliding_windows(self.window_size, self.step_size) {
-            if let Some(pitch) = self.detector.get_mono_pitch(window) {
-                pitches.push(pitch.frequency);
-            } else {
-                pitches.push(0.0);
-            }
+    ) -> PitchTrack {
+        let sample_rate = audio.sample_rate();
+        let mut track = PitchTrack::new(self.window_size, self.step_size, sample_rate);
+        for (index, window) in audio.sliding_windows(self.window_size, self.step_size).enumerate() {
+            let rms = rms(window.mono_samples());
+            let (frequency, clarity) = match self.detector.get_mono_pitch(window) {
+                Some(pitch) => (Some(pitch.frequency), pitch.clarity),
+                None => (None, 0.0),
+            };
+            track.frames.push(PitchFrame {
+                time: frame_time(index, self.window_size, self.step_size, sample_rate),
+                frequency,
+                clarity,
+                rms,
+            });
         }
-        pitches
+        track
     }
 }
 
@@ -63,7 +88,7 @@ impl<D: MonoPitchDetector> PitchTracker<D> {
 mod tests {
     use super::*;
     use audio_utils::MonoAudio;
-    use crate::pitch_tracking::detection::{MonoPitchDetector, Pitch};
+    use crate::pitch_tracking::detection::{DetectorInfo, DynPitchDetector, MonoPitchDetector, Pitch};
 
     // Dummy detector that always returns a fixed pitch
     struct DummyDetector;
@@ -77,10 +102,13 @@ mod tests {
     fn test_pitch_tracker_fixed_pitch() {
         let audio = MonoAudio { samples: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], sample_rate: 44100 };
         let mut tracker = PitchTracker::new(DummyDetector, 2, 2);
-        let pitches = tracker.pitches(audio);
+        let track = tracker.pitches(audio);
         // With window_size=2, step_size=2, expect 3 windows
-        assert_eq!(pitches.len(), 3);
-        assert!(pitches.iter().all(|&f| (f - 123.0).abs() < 1e-6));
+        assert_eq!(track.len(), 3);
+        assert!(track.frames.iter().all(|f| f.frequency == Some(123.0) && f.clarity == 1.0));
+        assert_eq!((track.window_size, track.hop_size, track.sample_rate), (2, 2, 44100));
+        assert!((track.frames[1].time - 3.0 / 44100.0).abs() < 1e-9);
+        assert!((track.frames[2].rms - (30.5f32).sqrt()).abs() < 1e-5);
     }
 
     // Dummy detector that returns None for every window
@@ -95,10 +123,42 @@ mod tests {
     fn test_pitch_tracker_none_pitch() {
         let audio = MonoAudio { samples: vec![1.0, 2.0, 3.0, 4.0], sample_rate: 44100 };
         let mut tracker = PitchTracker::new(NoneDetector, 2, 1);
-        let pitches = tracker.pitches(audio);
+        let track = tracker.pitches(audio);
         // With window_size=2, step_size=1, expect 3 windows
-        assert_eq!(pitches.len(), 3);
-        assert!(pitches.iter().all(|&f| f == 0.0));
+        assert_eq!(track.len(), 3);
+        assert!(track.frames.iter().all(|f| f.frequency.is_none() && f.clarity == 0.0 && f.rms > 0.0));
+        assert_eq!(track.voiced_regions().count(), 0);
+    }
+
+    impl DetectorInfo for DummyDetector {
+        fn preferred_window_size(&self) -> usize {
+            2
+        }
+
+        fn frequency_range(&self, _sample_rate: u32) -> (f32, f32) {
+            (123.0, 123.0)
+        }
     }
 
+    impl DetectorInfo for NoneDetector {
+        fn preferred_window_size(&self) -> usize {
+            2
+        }
+
+        fn frequency_range(&self, _sample_rate: u32) -> (f32, f32) {
+            (0.0, 0.0)
+        }
+    }
+
+    #[test]
+    fn test_pitch_tracker_swaps_boxed_detectors() {
+        let audio = MonoAudio { samples: vec![1.0, 2.0, 3.0, 4.0], sample_rate: 44100 };
+        let mut tracker: PitchTracker<Box<dyn DynPitchDetector>> = PitchTracker::new(Box::new(DummyDetector), 2, 2);
+        assert_eq!(tracker.pitches(audio.clone()).frequencies(), vec![Some(123.0), Some(123.0)]);
+        assert_eq!(tracker.detector().frequency_range(44100), (123.0, 123.0));
+
+        tracker.set_detector(Box::new(NoneDetector));
+        assert_eq!(tracker.pitches(audio).frequencies(), vec![None, None]);
+        assert_eq!(tracker.detector().preferred_window_size(), 2);
+    }
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 use audio_utils::MonoAudio;
 use pitch_detection_utils::{ExternalYinDetector, MonoPitchDetector};
 use sound_synth::voice_like_single_pitch;
-use audio_cleaning::clean_audio_for_pitch;
+use audio_cleaning::{clean_audio_for_pitch, CleaningReport, ReportConfig};
 use rand::Rng;
 
 /// Example demonstrating the effectiveness of signal cleaning for pitch detection.
@@ -18,6 +18,7 @@ use rand::Rng;
 /// 3. Verify that YIN detector fails on the noisy signal
 /// 4. Apply signal cleaning to the noisy signal
 /// 5. Verify that YIN detector succeeds on the cleaned signal
+/// 6. Print a cleaning report, including how many short frames YIN finds voiced
 fn main() {
     // Test configuration
     let target_freq = 220.0; // A3 note
@@ -59,7 +60,7 @@ fn main() {
     let cleaned_audio = clean_audio_for_pitch(&noisy_audio, None, None);
     
     // Create a new detector for the cleaned signal test
-    let cleaned_pitch_result = yin_detector.get_mono_pitch(cleaned_audio);
+    let cleaned_pitch_result = yin_detector.get_mono_pitch(cleaned_audio.clone());
 
     // Should detect accurately after cleaning
     let cleaned_detection_accurate = match &cleaned_pitch_result {
@@ -80,4 +81,14 @@ fn main() {
         println!("  Target: {:.1} Hz, Cleaned frequency: {:?}", 
             target_freq, cleaned_pitch_result.as_ref().map(|p| p.frequency));
     }
-}
\ No newline at end of file
+
+    // Step 3: Report on the cleaning, counting voiced frames with a short-window YIN
+    let frame_size = 512;
+    let mut frame_detector = ExternalYinDetector::new(0.1, 0.7, frame_size, frame_size / 2);
+    let config = ReportConfig { frame_size, hop_size: frame_size / 2, ..Default::default() };
+    let report = CleaningReport::new(&noisy_audio.samples, &cleaned_audio.samples, sample_rate as f32, &config)
+        .with_voiced_frames(&noisy_audio.samples, &cleaned_audio.samples, frame_size, frame_size / 2, |window| {
+            frame_detector.get_mono_pitch(MonoAudio::new(window.to_vec(), sample_rate)).is_some()
+        });
+    println!("\nCleaning report:\n{}", report);
+}
@@ -2,10 +2,12 @@
 //! 
 //! This crate provides sound generation utilities for testing and demonstration.
 //! It includes functions for generating voice-like signals with harmonics,
-//! vibrato, and amplitude envelopes.
+//! vibrato, and amplitude envelopes, and seeded white noise.
 //!
 //! All functions depend only on `audio-utils` for audio types.
 
+pub mod noise;
 pub mod voice_synth;
 
+pub use noise::white_noise;
 pub use voice_synth::{vibrato_sine_wave, voice_like_signal, voice_like_single_pitch};
@@ -0,0 +1,13 @@
+/// Generate uniform white noise in [-amplitude, amplitude]
+///
+/// Uses a linear congruential generator, so the same `seed` always gives the same
+/// samples and tests stay reproducible.
+pub fn white_noise(len: usize, amplitude: f32, seed: u32) -> Vec<f32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            amplitude * ((state >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0)
+        })
+        .collect()
+}